@@ -26,6 +26,15 @@ impl BoundingBox {
     /// for explanation see https://www.scratchapixel.com/lessons/3d-basic-rendering/minimal-ray-tracer-rendering-simple-shapes/ray-box-intersection
     /// see also https://gamedev.stackexchange.com/questions/18436/most-efficient-aabb-vs-ray-collision-algorithms
     pub fn hit(&self, ray: &Ray) -> bool {
+        self.hit_range(ray).is_some()
+    }
+
+    /// ray parameters where the ray enters and leaves the box, the entry is negative if the
+    /// ray starts inside, None if the ray misses the box or it lies behind the ray
+    pub fn hit_range(&self, ray: &Ray) -> Option<(f32, f32)> {
+        if self.is_empty() {
+            return None;
+        }
         // get ray parameters that show where the ray intersects the box planes
         let t_lower_x = (self.lower_bound.x - ray.origin.x) / ray.direction.x;
         let t_upper_x = (self.upper_bound.x - ray.origin.x) / ray.direction.x;
@@ -48,13 +57,52 @@ impl BoundingBox {
 
         // intersection, but opposite to ray direction
         if t_max < 0.0 {
-            return false;
+            return None;
         }
 
         if t_min > t_max {
-            return false;
+            return None;
         }
-        true
+        Some((t_min, t_max))
+    }
+
+    /// true if the bounds cross, e.g. for the overlap of disjoint boxes
+    pub fn is_empty(&self) -> bool {
+        self.lower_bound.x > self.upper_bound.x
+            || self.lower_bound.y > self.upper_bound.y
+            || self.lower_bound.z > self.upper_bound.z
+    }
+
+    /// smallest box containing both boxes
+    pub fn enclosing(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::new(
+            Vec3::new(
+                min(self.lower_bound.x, other.lower_bound.x),
+                min(self.lower_bound.y, other.lower_bound.y),
+                min(self.lower_bound.z, other.lower_bound.z),
+            ),
+            Vec3::new(
+                max(self.upper_bound.x, other.upper_bound.x),
+                max(self.upper_bound.y, other.upper_bound.y),
+                max(self.upper_bound.z, other.upper_bound.z),
+            ),
+        )
+    }
+
+    /// box of the points inside of both boxes, its bounds cross if the boxes are disjoint
+    pub fn overlap(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::new(
+            Vec3::new(
+                max(self.lower_bound.x, other.lower_bound.x),
+                max(self.lower_bound.y, other.lower_bound.y),
+                max(self.lower_bound.z, other.lower_bound.z),
+            ),
+            Vec3::new(
+                min(self.upper_bound.x, other.upper_bound.x),
+                min(self.upper_bound.y, other.upper_bound.y),
+                min(self.upper_bound.z, other.upper_bound.z),
+            ),
+        )
     }
 }
 
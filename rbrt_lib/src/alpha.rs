@@ -0,0 +1,144 @@
+use crate::materials::ScatterRecord;
+use crate::medium::Medium;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Ray, RayScattering};
+
+/// cuts holes into a material, e.g. for leaves on flat cards or decals,
+/// everything else is delegated to the wrapped material
+pub struct AlphaCutout {
+    pub material: Box<dyn RayScattering + Sync>,
+    /// opacity from 0.0 (invisible) to 1.0, read from the first channel of the texture
+    pub alpha: Box<dyn Texture>,
+    /// with a threshold, the surface is either opaque or cut away, without one,
+    /// rays pass through partially opaque parts at random
+    pub threshold: Option<f32>,
+}
+
+impl RayScattering for AlphaCutout {
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        self.material.sample(incoming_ray, hit_info)
+    }
+
+    fn eval(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        self.material.eval(incoming_dir, scattered_dir, hit_info)
+    }
+
+    fn pdf(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+        self.material.pdf(incoming_dir, scattered_dir, hit_info)
+    }
+
+    fn emitted(&self, incoming_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        self.material.emitted(incoming_dir, hit_info)
+    }
+
+    fn shading_normal(&self, hit_info: &HitInformation) -> Vec3 {
+        self.material.shading_normal(hit_info)
+    }
+
+    fn medium(&self) -> Option<&dyn Medium> {
+        self.material.medium()
+    }
+
+    fn is_invisible(&self) -> bool {
+        self.material.is_invisible()
+    }
+
+    fn opacity(&self, hit_info: &HitInformation) -> f32 {
+        let alpha = self.alpha.value(hit_info.uv, &hit_info.hit_point).x;
+        match self.threshold {
+            Some(threshold) if alpha >= threshold => 1.0,
+            Some(_) => 0.0,
+            None => alpha.clamp(0.0, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AlphaCutout;
+    use crate::lambertian::Lambertian;
+    use crate::quad::Quad;
+    use crate::scene::Scene;
+    use crate::texture::Texture;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray, RayScattering};
+
+    /// opaque for u below 0.5, half transparent above
+    struct HalfMask;
+
+    impl Texture for HalfMask {
+        fn value(&self, uv: (f32, f32), _point: &Vec3) -> Vec3 {
+            let alpha = if uv.0 < 0.5 { 1.0 } else { 0.5 };
+            Vec3::new(alpha, alpha, alpha)
+        }
+    }
+
+    /// two unit quads at z = -1 and z = -2, the front one is cut out
+    fn new_scene(threshold: Option<f32>) -> Scene {
+        let front = Quad::new(
+            Vec3::new(0.0, 0.0, -1.0),
+            [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+            Box::new(AlphaCutout {
+                material: Box::new(Lambertian { albedo: 0.5.into() }),
+                alpha: Box::new(HalfMask),
+                threshold,
+            }),
+        );
+        let back = Quad::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+            Box::new(Lambertian { albedo: 0.5.into() }),
+        );
+        Scene {
+            elements: vec![
+                Box::new(front) as Box<dyn Intersectable + Sync>,
+                Box::new(back),
+            ],
+            triangle_meshes: vec![],
+            lights: vec![],
+            background_color: None,
+            fog: None,
+        }
+    }
+
+    fn hit_dist(scene: &Scene, x: f32) -> f32 {
+        let ray = Ray::new(Vec3::new(x, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        scene
+            .hit(&ray, 1e-3, f32::MAX)
+            .map(|hit_info| hit_info.dist_from_ray_orig)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_alpha_threshold() {
+        let scene = new_scene(Some(0.6));
+        assert!((hit_dist(&scene, 0.25) - 1.0).abs() < 1e-5);
+        // the cut away part is skipped, also by shadow rays
+        assert!((hit_dist(&scene, 0.75) - 2.0).abs() < 1e-5);
+        let shadow_ray = Ray::new(Vec3::new(0.75, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            scene.transmittance(&shadow_ray, 1e-3, 1.5, None),
+            Vec3::new(1.0, 1.0, 1.0)
+        );
+
+        let material = AlphaCutout {
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+            alpha: Box::new(HalfMask),
+            threshold: None,
+        };
+        assert!(material.medium().is_none());
+    }
+
+    #[test]
+    fn test_stochastic_alpha() {
+        let scene = new_scene(None);
+        let num_samples = 10000;
+        let num_front_hits = (0..num_samples)
+            .filter(|_| hit_dist(&scene, 0.75) < 1.5)
+            .count();
+        let fraction = num_front_hits as f32 / num_samples as f32;
+        assert!((fraction - 0.5).abs() < 0.03, "{}", fraction);
+        assert!((0..100).all(|_| hit_dist(&scene, 0.25) < 1.5));
+    }
+}
@@ -1,35 +1,456 @@
-use crate::dielectric::Dielectric;
+use crate::alpha::AlphaCutout;
+use crate::cam::Camera;
+use crate::cone::Cone;
+use crate::csg::{Csg, CsgOperation};
+use crate::cuboid::Cuboid;
+use crate::cylinder::Cylinder;
+use crate::dielectric::{Dielectric, RefractiveIndex};
+use crate::diffuse_light::DiffuseLight;
+use crate::instance::{InstancedMesh, MeshInstance, Transformed};
 use crate::lambertian::Lambertian;
 use crate::materials::RayScattering;
-use crate::mesh::TriangleMesh;
+use crate::medium::{HomogeneousMedium, MediumBoundary};
+use crate::mesh::{load_mesh_from_file, transform_mesh_data, TriangleMesh};
 use crate::metal::Metal;
 
+use crate::perlin::Perlin;
+use crate::plane::{Disk, Plane};
+use crate::procedural_texture::{CheckerTexture, MarbleTexture, NoiseTexture, WoodTexture};
+use crate::quad::Quad;
+use crate::scene::Light;
+use crate::sdf::{Sdf, SdfNode, DEFAULT_MAX_STEPS};
+use crate::sidedness::{Sided, Sidedness};
 use crate::sphere::Sphere;
+use crate::subsurface::Subsurface;
+use crate::surface_detail::{DetailMap, SurfaceDetail};
+use crate::texture::{ImageTexture, Texture};
+use crate::torus::Torus;
+use crate::transform::{EulerOrder, Transform};
+use crate::triangle::BasicTriangle;
 use crate::vec3::Vec3;
+use crate::volume::{load_density_grid, GridMedium};
 use crate::{Intersectable, Scene};
 
 use std::fs::File;
 
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "texture_type", rename_all = "lowercase")]
+pub enum TextureBlueprint {
+    Constant {
+        color: Vec3,
+    },
+    /// png or jpeg image, mapped with the texture coordinates of the surface
+    Image {
+        filepath: String,
+    },
+    /// 3d checkerboard of cubes with edge length 1/scale, alternating between two textures
+    Checker {
+        scale: f32,
+        even: Box<TextureBlueprint>,
+        odd: Box<TextureBlueprint>,
+    },
+    /// fractal perlin noise blending from black to color
+    Noise {
+        scale: f32,
+        octaves: Option<u32>,
+        color: Vec3,
+        seed: Option<u64>,
+    },
+    /// marble veins along the z axis, turbulence controls their distortion
+    Marble {
+        scale: f32,
+        turbulence: f32,
+        octaves: Option<u32>,
+        base_color: Vec3,
+        vein_color: Vec3,
+        seed: Option<u64>,
+    },
+    /// growth rings around the y axis
+    Wood {
+        ring_frequency: f32,
+        turbulence: f32,
+        light_color: Vec3,
+        dark_color: Vec3,
+        seed: Option<u64>,
+    },
+}
+
+/// number of noise octaves of procedural textures if not specified
+const DEFAULT_NOISE_OCTAVES: u32 = 6;
+
+/// one step of a transform, the steps of a transform list are applied in order
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "transform_type", rename_all = "snake_case")]
+pub enum TransformStepBlueprint {
+    /// affine 4x4 matrix
+    Matrix {
+        rows: [[f32; 4]; 4],
+    },
+    /// scale factors along the x, y and z axes
+    Scale {
+        factors: Vec3,
+    },
+    Translate {
+        offset: Vec3,
+    },
+    /// counter clockwise rotation around the axis
+    AxisAngle {
+        axis: Vec3,
+        angle_rad: f32,
+    },
+    /// rotation by the unit quaternion x*i + y*j + z*k + w
+    Quaternion {
+        x: f32,
+        y: f32,
+        z: f32,
+        w: f32,
+    },
+    /// rotations around the x, y and z axes of the scene, in the given order, e.g. XYZ
+    Euler {
+        angles_rad: Vec3,
+        order: EulerOrder,
+    },
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// material description shared by all scene elements, its fields are
+/// specified inline with the fields of the element
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MaterialBlueprint {
+    pub material_type: String,
+    pub albedo: Option<Vec3>,
+    pub material_param: Option<f32>,
+    /// spatially varying albedo, replaces albedo
+    pub albedo_texture: Option<TextureBlueprint>,
+    /// spatially varying roughness of metals and dielectrics, replaces material_param of metals
+    /// and roughness of dielectrics
+    pub roughness_texture: Option<TextureBlueprint>,
+    /// tangent space normal map
+    pub normal_map: Option<TextureBlueprint>,
+    /// height map, ignored if a normal map is given
+    pub bump_map: Option<TextureBlueprint>,
+    /// scales the heights of the bump map, defaults to 1.0
+    pub bump_strength: Option<f32>,
+    /// constant opacity between 0.0 (invisible) and 1.0
+    pub opacity: Option<f32>,
+    /// spatially varying opacity, replaces opacity, images are read from their alpha channel
+    pub opacity_texture: Option<TextureBlueprint>,
+    /// opacity below which the surface is cut away, partially opaque surfaces are
+    /// stochastically transparent without a threshold
+    pub alpha_threshold: Option<f32>,
+    /// radiance emitted by light materials
+    pub emission: Option<Vec3>,
+    /// surface roughness of dielectrics (frosted glass), the alpha of the GGX distribution
+    pub roughness: Option<f32>,
+    /// distance after which light travelling through a dielectric takes on the albedo color,
+    /// 1.0 by default
+    pub transmission_distance: Option<f32>,
+    /// dispersive dielectric: Cauchy coefficients [A, B] or [A, B, C], in micrometers
+    pub cauchy_coefficients: Option<Vec<f32>>,
+    /// dispersive dielectric: Sellmeier coefficients [B1, B2, B3, C1, C2, C3], in micrometers
+    pub sellmeier_coefficients: Option<Vec<f32>>,
+    /// medium enclosed by the surface: absorption coefficients per unit length
+    pub absorption: Option<Vec3>,
+    /// medium enclosed by the surface: scattering coefficients per unit length
+    pub scattering: Option<Vec3>,
+    /// medium enclosed by the surface: mean cosine of the scattering angle, defaults to 0.0
+    pub anisotropy: Option<f32>,
+    /// subsurface scattering: average distance light travels below the surface between
+    /// scattering events, per color channel
+    pub mean_free_path: Option<Vec3>,
+    /// two_sided (default), one_sided with a black back, or backface_culled
+    pub sidedness: Option<Sidedness>,
+}
+
+/// material selected by the material_type of a MaterialBlueprint
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaterialKind {
+    Metal,
+    Lambertian,
+    Dielectric,
+    Medium,
+    Subsurface,
+    Light,
+}
+
+impl MaterialBlueprint {
+    /// None if the material_type names none of the materials
+    pub fn kind(&self) -> Option<MaterialKind> {
+        let mat_type = self.material_type.to_lowercase();
+        if mat_type.contains("metal") {
+            Some(MaterialKind::Metal)
+        } else if mat_type.contains("lambert") {
+            Some(MaterialKind::Lambertian)
+        } else if mat_type.contains("dielectric") {
+            Some(MaterialKind::Dielectric)
+        } else if mat_type.contains("medium") {
+            Some(MaterialKind::Medium)
+        } else if mat_type.contains("subsurface") {
+            Some(MaterialKind::Subsurface)
+        } else if mat_type.contains("light") {
+            Some(MaterialKind::Light)
+        } else {
+            None
+        }
+    }
+}
+
+/// homogeneous participating medium, e.g. the fog of the scene
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediumBlueprint {
+    /// absorption coefficients per unit length
+    pub absorption: Vec3,
+    /// scattering coefficients per unit length
+    pub scattering: Vec3,
+    /// mean cosine of the scattering angle, 0.0 scatters uniformly, defaults to 0.0
+    #[serde(default)]
+    pub anisotropy: f32,
+}
+
+impl MediumBlueprint {
+    pub fn to_medium(&self) -> HomogeneousMedium {
+        assert!(
+            self.anisotropy.abs() < 1.0,
+            "anisotropy of a medium must lie between -1.0 and 1.0"
+        );
+        HomogeneousMedium {
+            absorption: self.absorption,
+            scattering: self.scattering,
+            anisotropy: self.anisotropy,
+        }
+    }
+}
+
+fn create_refractive_index_from_description(material_bp: &MaterialBlueprint) -> RefractiveIndex {
+    if let Some(coeffs) = &material_bp.cauchy_coefficients {
+        assert!(
+            coeffs.len() == 2 || coeffs.len() == 3,
+            "cauchy_coefficients must contain [A, B] or [A, B, C]"
+        );
+        RefractiveIndex::Cauchy {
+            a: coeffs[0],
+            b: coeffs[1],
+            c: coeffs.get(2).copied().unwrap_or(0.0),
+        }
+    } else if let Some(coeffs) = &material_bp.sellmeier_coefficients {
+        assert!(
+            coeffs.len() == 6,
+            "sellmeier_coefficients must contain [B1, B2, B3, C1, C2, C3]"
+        );
+        RefractiveIndex::Sellmeier {
+            b: [coeffs[0], coeffs[1], coeffs[2]],
+            c: [coeffs[3], coeffs[4], coeffs[5]],
+        }
+    } else {
+        RefractiveIndex::Constant(material_bp.material_param.expect(
+            "you forgot to specify a refractory index vector (i.e. material_param: 1.8) dielectric",
+        ))
+    }
+}
+
+/// material fields inlined next to the mesh fields, None if there are none.
+/// A plain flattened Option would also turn a malformed material into None
+fn deserialize_material_override<'de, D>(
+    deserializer: D,
+) -> Result<Option<MaterialBlueprint>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let fields = serde_yaml::Mapping::deserialize(deserializer)?;
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    MaterialBlueprint::deserialize(serde_yaml::Value::Mapping(fields))
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TriangleMeshBlueprint {
+    /// .obj, .gltf, .glb, .ply or .stl file
     pub obj_filepath: String,
+    #[serde(default = "default_scale")]
     pub scale: f32,
+    #[serde(default = "Vec3::zero")]
     pub translation: Vec3,
+    /// Z,X,Z euler angles, see Vec3::rotate_point
+    #[serde(default = "Vec3::zero")]
     pub rotation_rad: Vec3,
-    pub material_type: String,
-    pub albedo: Option<Vec3>,
-    pub material_param: Option<f32>,
+    /// applied after scale, rotation_rad and translation
+    pub transform: Option<Vec<TransformStepBlueprint>>,
+    /// interpolate vertex normals instead of using face normals, defaults to true
+    pub smooth_shading: Option<bool>,
+    /// intersect with the slower watertight test, which never lets rays slip through
+    /// edges shared by two triangles, defaults to false
+    pub watertight: Option<bool>,
+    /// overrides the materials of the .mtl file of the mesh
+    #[serde(flatten, deserialize_with = "deserialize_material_override")]
+    pub material: Option<MaterialBlueprint>,
+}
+
+/// copy of an instanced mesh
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MeshInstanceBlueprint {
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default = "Vec3::zero")]
+    pub translation: Vec3,
+    /// Z,X,Z euler angles, see Vec3::rotate_point
+    #[serde(default = "Vec3::zero")]
+    pub rotation_rad: Vec3,
+    /// applied after scale, rotation_rad and translation
+    pub transform: Option<Vec<TransformStepBlueprint>>,
+    /// overrides the material of the mesh for this copy
+    #[serde(flatten, deserialize_with = "deserialize_material_override")]
+    pub material: Option<MaterialBlueprint>,
+}
+
+/// mesh that is loaded once and placed many times, e.g. trees of a forest
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstancedMeshBlueprint {
+    /// .obj, .gltf, .glb, .ply or .stl file
+    pub obj_filepath: String,
+    /// interpolate vertex normals instead of using face normals, defaults to true
+    pub smooth_shading: Option<bool>,
+    /// intersect with the watertight test, defaults to false
+    pub watertight: Option<bool>,
+    pub instances: Vec<MeshInstanceBlueprint>,
+    /// overrides the materials of the mesh file for all copies
+    #[serde(flatten, deserialize_with = "deserialize_material_override")]
+    pub material: Option<MaterialBlueprint>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SphereBlueprint {
     pub radius: f32,
     pub center: Vec3,
-    pub material_type: String,
-    pub albedo: Option<Vec3>,
-    pub material_param: Option<f32>,
+    /// applied to the sphere, e.g. a non-uniform scale turns it into an ellipsoid
+    pub transform: Option<Vec<TransformStepBlueprint>>,
+    #[serde(flatten)]
+    pub material: MaterialBlueprint,
+}
+
+/// analytic shape, oriented with a transform where it has no normal of its own,
+/// the transform of triangles and quads is applied to their corners
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "lowercase")]
+pub enum ShapeBlueprint {
+    /// infinite plane, or a rectangle of size [along u, along v] centered at point
+    Plane {
+        point: Vec3,
+        normal: Vec3,
+        size: Option<[f32; 2]>,
+    },
+    /// disk, with a hole if inner_radius is given
+    Disk {
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        inner_radius: Option<f32>,
+    },
+    /// axis aligned box
+    #[serde(rename = "box")]
+    Cuboid {
+        lower_bound: Vec3,
+        upper_bound: Vec3,
+    },
+    /// upright cylinder around its center, capped unless capped: false
+    Cylinder {
+        center: Vec3,
+        radius: f32,
+        height: f32,
+        capped: Option<bool>,
+    },
+    /// upright cone with the base centered at center, capped unless capped: false
+    Cone {
+        center: Vec3,
+        radius: f32,
+        height: f32,
+        capped: Option<bool>,
+    },
+    /// torus lying in the xz plane
+    Torus {
+        center: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    /// single triangle, facing the side from which the corners are counter clockwise
+    Triangle { corners: [Vec3; 3] },
+    /// parallelogram spanned by edge_u and edge_v from corner, facing edge_u x edge_v
+    Quad {
+        corner: Vec3,
+        edge_u: Vec3,
+        edge_v: Vec3,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrimitiveBlueprint {
+    #[serde(flatten)]
+    pub shape: ShapeBlueprint,
+    pub transform: Option<Vec<TransformStepBlueprint>>,
+    #[serde(flatten)]
+    pub material: MaterialBlueprint,
+}
+
+/// boolean combination of solids, applied to the operands from first to last,
+/// e.g. a difference subtracts all other operands from the first one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsgBlueprint {
+    pub operation: CsgOperation,
+    pub operands: Vec<CsgOperandBlueprint>,
+    /// applied to the combined solid
+    pub transform: Option<Vec<TransformStepBlueprint>>,
+}
+
+/// solid that is combined by a csg node, operands can be csg nodes themselves
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CsgOperandBlueprint {
+    Csg(CsgBlueprint),
+    Primitive(PrimitiveBlueprint),
+    Sphere(SphereBlueprint),
+}
+
+/// shape given by a tree of signed distance field shapes and operators
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SdfBlueprint {
+    pub sdf: SdfNode,
+    /// scales the sphere tracing steps, defaults to 1.0
+    pub step_factor: Option<f32>,
+    pub max_steps: Option<u32>,
+    pub transform: Option<Vec<TransformStepBlueprint>>,
+    #[serde(flatten)]
+    pub material: MaterialBlueprint,
+}
+
+/// smoke or clouds: a heterogeneous medium whose density grid fills an axis aligned box
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeBlueprint {
+    /// dense grid file, see volume::parse_dense_grid
+    pub filepath: String,
+    pub lower_bound: Vec3,
+    pub upper_bound: Vec3,
+    /// absorption coefficients per unit length at density 1.0, defaults to 0.0
+    pub absorption: Option<Vec3>,
+    /// scattering coefficients per unit length at density 1.0
+    pub scattering: Vec3,
+    /// mean cosine of the scattering angle, defaults to 0.0
+    #[serde(default)]
+    pub anisotropy: f32,
+}
+
+/// point light, for spherical area lights use a sphere with light material
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LightBlueprint {
+    pub position: Vec3,
+    /// radiant intensity
+    pub color: Vec3,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,39 +459,252 @@ pub struct CameraBluePrint {
     pub camera_look_at: Vec3,
     pub camera_position: Vec3,
     pub camera_focal_length_mm: f32,
+    /// moves and turns the camera, applied to position, look_at and up
+    pub transform: Option<Vec<TransformStepBlueprint>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SceneBlueprint {
     pub camera_blueprint: CameraBluePrint,
     pub mesh_blueprints: Vec<TriangleMeshBlueprint>,
+    #[serde(default)]
+    pub instanced_mesh_blueprints: Vec<InstancedMeshBlueprint>,
     pub sphere_blueprints: Vec<SphereBlueprint>,
+    #[serde(default)]
+    pub primitive_blueprints: Vec<PrimitiveBlueprint>,
+    #[serde(default)]
+    pub csg_blueprints: Vec<CsgBlueprint>,
+    #[serde(default)]
+    pub sdf_blueprints: Vec<SdfBlueprint>,
+    #[serde(default)]
+    pub volume_blueprints: Vec<VolumeBlueprint>,
+    #[serde(default)]
+    pub light_blueprints: Vec<LightBlueprint>,
+    /// constant background color instead of the sky gradient
+    pub background_color: Option<Vec3>,
+    /// medium filling the whole scene, hides the background
+    #[serde(default)]
+    pub fog: Option<MediumBlueprint>,
+}
+
+/// color textures are sRGB encoded, data textures such as roughness maps are not
+fn create_texture_from_description(
+    texture_bp: &TextureBlueprint,
+    is_srgb: bool,
+) -> Box<dyn Texture> {
+    match texture_bp {
+        TextureBlueprint::Constant { color } => (*color).into(),
+        TextureBlueprint::Image { filepath } => Box::new(ImageTexture::load(filepath, is_srgb)),
+        TextureBlueprint::Checker { scale, even, odd } => Box::new(CheckerTexture {
+            scale: *scale,
+            even: create_texture_from_description(even, is_srgb),
+            odd: create_texture_from_description(odd, is_srgb),
+        }),
+        TextureBlueprint::Noise {
+            scale,
+            octaves,
+            color,
+            seed,
+        } => Box::new(NoiseTexture {
+            perlin: Perlin::new(seed.unwrap_or(0)),
+            scale: *scale,
+            octaves: octaves.unwrap_or(DEFAULT_NOISE_OCTAVES),
+            color: *color,
+        }),
+        TextureBlueprint::Marble {
+            scale,
+            turbulence,
+            octaves,
+            base_color,
+            vein_color,
+            seed,
+        } => Box::new(MarbleTexture {
+            perlin: Perlin::new(seed.unwrap_or(0)),
+            scale: *scale,
+            turbulence: *turbulence,
+            octaves: octaves.unwrap_or(DEFAULT_NOISE_OCTAVES),
+            base_color: *base_color,
+            vein_color: *vein_color,
+        }),
+        TextureBlueprint::Wood {
+            ring_frequency,
+            turbulence,
+            light_color,
+            dark_color,
+            seed,
+        } => Box::new(WoodTexture {
+            perlin: Perlin::new(seed.unwrap_or(0)),
+            ring_frequency: *ring_frequency,
+            turbulence: *turbulence,
+            light_color: *light_color,
+            dark_color: *dark_color,
+        }),
+    }
+}
+
+/// texture if it was specified, constant value otherwise
+fn create_texture_or_constant(
+    texture_bp: &Option<TextureBlueprint>,
+    constant: Option<Vec3>,
+    is_srgb: bool,
+    missing_msg: &str,
+) -> Box<dyn Texture> {
+    match texture_bp {
+        Some(texture_bp) => create_texture_from_description(texture_bp, is_srgb),
+        None => constant.expect(missing_msg).into(),
+    }
+}
+
+fn create_base_material_from_description(
+    material_bp: &MaterialBlueprint,
+) -> Option<Box<dyn RayScattering + std::marker::Sync + 'static>> {
+    let albedo = material_bp.albedo;
+    let material_param = material_bp.material_param;
+    match material_bp.kind() {
+        Some(MaterialKind::Metal) => Some(Box::new(Metal {
+            albedo: create_texture_or_constant(
+                &material_bp.albedo_texture,
+                albedo,
+                true,
+                "you forgot to specify an albedo vector for metal",
+            ),
+            roughness: create_texture_or_constant(
+                &material_bp.roughness_texture,
+                material_param.map(|roughness| Vec3::new(roughness, roughness, roughness)),
+                false,
+                "you forgot to specify a roughness (i.e. material_param: 0.1) for metal",
+            ),
+        })),
+        Some(MaterialKind::Lambertian) => Some(Box::new(Lambertian {
+            albedo: create_texture_or_constant(
+                &material_bp.albedo_texture,
+                albedo,
+                true,
+                "you forgot to specify an albedo vector for lambertian",
+            ),
+        })),
+        Some(MaterialKind::Dielectric) => {
+            let ior = create_refractive_index_from_description(material_bp);
+            let roughness = match &material_bp.roughness_texture {
+                Some(texture_bp) => create_texture_from_description(texture_bp, false),
+                None => material_bp.roughness.unwrap_or(0.0).into(),
+            };
+            // the albedo is the transmission color after a distance of 1.0 unless given otherwise,
+            // without albedo and transmission distance the dielectric does not absorb any light
+            let is_absorbing = albedo.is_some()
+                || material_bp.albedo_texture.is_some()
+                || material_bp.transmission_distance.is_some();
+            let transmission_color = if is_absorbing {
+                create_texture_or_constant(
+                    &material_bp.albedo_texture,
+                    albedo,
+                    true,
+                    "you forgot to specify an albedo (the transmission color) for the absorbing dielectric",
+                )
+            } else {
+                Vec3::new(1.0, 1.0, 1.0).into()
+            };
+            Some(Box::new(Dielectric {
+                ior,
+                transmission_color,
+                transmission_distance: material_bp.transmission_distance.unwrap_or(1.0),
+                roughness,
+            }))
+        }
+        Some(MaterialKind::Medium) => {
+            let medium_bp = MediumBlueprint {
+                absorption: material_bp.absorption.unwrap_or_else(Vec3::zero),
+                scattering: material_bp
+                    .scattering
+                    .expect("you forgot to specify a scattering vector for medium"),
+                anisotropy: material_bp.anisotropy.unwrap_or(0.0),
+            };
+            Some(Box::new(MediumBoundary {
+                medium: Box::new(medium_bp.to_medium()),
+            }))
+        }
+        Some(MaterialKind::Subsurface) => {
+            let anisotropy = material_bp.anisotropy.unwrap_or(0.0);
+            assert!(
+                anisotropy.abs() < 1.0,
+                "anisotropy of subsurface must lie between -1.0 and 1.0"
+            );
+            let mean_free_path = material_bp
+                .mean_free_path
+                .expect("you forgot to specify a mean_free_path vector for subsurface");
+            assert!(
+                mean_free_path.x > 0.0 && mean_free_path.y > 0.0 && mean_free_path.z > 0.0,
+                "mean_free_path of subsurface must be positive"
+            );
+            Some(Box::new(Subsurface::new(
+                albedo.expect("you forgot to specify an albedo vector for subsurface"),
+                mean_free_path,
+                anisotropy,
+            )))
+        }
+        Some(MaterialKind::Light) => Some(Box::new(DiffuseLight {
+            emission: material_bp
+                .emission
+                .expect("you forgot to specify an emission vector for light"),
+        })),
+        None => {
+            println!(
+                "Cannot figure out material_type from {}, material_type must be one of metal, lambertian, dielectric, medium, subsurface or light!", material_bp.material_type
+            );
+            None
+        }
+    }
 }
 
+fn create_opacity_texture(texture_bp: &TextureBlueprint) -> Box<dyn Texture> {
+    match texture_bp {
+        TextureBlueprint::Image { filepath } => Box::new(ImageTexture::load_opacity(filepath)),
+        _ => create_texture_from_description(texture_bp, false),
+    }
+}
+
+/// material with normal or bump map, alpha cutout and sidedness if they were specified
 fn create_material_from_description(
-    mat_type: &str,
-    albedo: Option<Vec3>,
-    material_param: Option<f32>,
+    material_bp: &MaterialBlueprint,
 ) -> Option<Box<dyn RayScattering + std::marker::Sync + 'static>> {
-    if mat_type.to_lowercase().contains("metal") {
-        return Some(Box::new(Metal {
-            albedo: albedo.expect("you forgot to specify an albedo vector for metal"),
-            roughness: material_param
-                .expect("you forgot to specify a roughness (i.e. material_param: 0.1) for metal"),
-        }));
-    } else if mat_type.to_lowercase().contains("lambert") {
-        return Some(Box::new(Lambertian {
-            albedo: albedo.expect("you forgot to specify an albedo vector for lambertian"),
-        }));
-    } else if mat_type.to_lowercase().contains("dielectric") {
-        return Some(Box::new(Dielectric {
-            ref_idx: material_param.expect("you forgot to specify a refractory index vector (i.e. material_param: 1.8) dielectric"),
-        }));
+    let material = create_detailed_material_from_description(material_bp)?;
+    match material_bp.sidedness {
+        Some(sidedness) if sidedness != Sidedness::TwoSided => Some(Box::new(Sided {
+            material,
+            sidedness,
+        })),
+        _ => Some(material),
     }
-    println!(
-        "Cannot figure out material_type from {}, material_type must be one of metal, lambertian or dielectric!", mat_type
-    );
-    None
+}
+
+fn create_detailed_material_from_description(
+    material_bp: &MaterialBlueprint,
+) -> Option<Box<dyn RayScattering + std::marker::Sync + 'static>> {
+    let mut material = create_base_material_from_description(material_bp)?;
+    let detail = match (&material_bp.normal_map, &material_bp.bump_map) {
+        (Some(normal_map), _) => Some(DetailMap::Normal(create_texture_from_description(
+            normal_map, false,
+        ))),
+        (None, Some(bump_map)) => Some(DetailMap::Bump {
+            height: create_texture_from_description(bump_map, false),
+            strength: material_bp.bump_strength.unwrap_or(1.0),
+        }),
+        (None, None) => None,
+    };
+    if let Some(detail) = detail {
+        material = Box::new(SurfaceDetail { material, detail });
+    }
+
+    let alpha = match (&material_bp.opacity_texture, material_bp.opacity) {
+        (Some(opacity_texture), _) => create_opacity_texture(opacity_texture),
+        (None, Some(opacity)) => opacity.into(),
+        (None, None) => return Some(material),
+    };
+    Some(Box::new(AlphaCutout {
+        material,
+        alpha,
+        threshold: material_bp.alpha_threshold,
+    }))
 }
 
 pub fn load_blueprints_from_yaml_file(filepath: &str) -> SceneBlueprint {
@@ -91,41 +725,368 @@ pub fn load_blueprints_from_yaml_file(filepath: &str) -> SceneBlueprint {
     }
 }
 
+fn create_transform_from_description(steps: &[TransformStepBlueprint]) -> Transform {
+    steps.iter().fold(Transform::identity(), |transform, step| {
+        let step = match step {
+            TransformStepBlueprint::Matrix { rows } => {
+                // Transform matrices are column major
+                let mut columns = [[0.0; 4]; 4];
+                for (row_idx, row) in rows.iter().enumerate() {
+                    for (col_idx, value) in row.iter().enumerate() {
+                        columns[col_idx][row_idx] = *value;
+                    }
+                }
+                Transform::from_matrix(columns)
+            }
+            TransformStepBlueprint::Scale { factors } => Transform::scale(*factors),
+            TransformStepBlueprint::Translate { offset } => Transform::translation(*offset),
+            TransformStepBlueprint::AxisAngle { axis, angle_rad } => {
+                Transform::axis_angle(*axis, *angle_rad)
+            }
+            TransformStepBlueprint::Quaternion { x, y, z, w } => {
+                let norm = (x * x + y * y + z * z + w * w).sqrt();
+                if norm == 0.0 || !norm.is_finite() {
+                    panic!(
+                        "Quaternion {} {} {} {} has no rotation, its length must not be 0",
+                        x, y, z, w
+                    );
+                }
+                Transform::quaternion(*x, *y, *z, *w)
+            }
+            TransformStepBlueprint::Euler { angles_rad, order } => {
+                Transform::euler(*angles_rad, *order)
+            }
+        };
+        transform.then(&step)
+    })
+}
+
+/// identity if no transform steps are given
+fn create_optional_transform(steps: &Option<Vec<TransformStepBlueprint>>) -> Transform {
+    match steps {
+        Some(steps) => create_transform_from_description(steps),
+        None => Transform::identity(),
+    }
+}
+
+/// transform of scale, rotation_rad and translation followed by the transform steps
+fn create_placement_from_description(
+    scale: f32,
+    rotation_rad: Vec3,
+    translation: Vec3,
+    steps: &Option<Vec<TransformStepBlueprint>>,
+) -> Transform {
+    let placement = Transform::from_scale_rotation_translation(scale, rotation_rad, translation);
+    match steps {
+        Some(steps) => placement.then(&create_transform_from_description(steps)),
+        None => placement,
+    }
+}
+
+pub fn create_camera_from_blueprint(
+    camera_bp: &CameraBluePrint,
+    img_height_pix: u32,
+    img_width_pix: u32,
+) -> Camera {
+    let transform = create_optional_transform(&camera_bp.transform);
+    // the look_at of the camera is its viewing direction
+    Camera::new(
+        transform.transform_point(&camera_bp.camera_position),
+        transform.transform_vector(&camera_bp.camera_look_at),
+        transform.transform_vector(&camera_bp.camera_up),
+        img_height_pix,
+        img_width_pix,
+        camera_bp.camera_focal_length_mm,
+    )
+}
+
 fn parse_mesh_bp(mesh_bp: TriangleMeshBlueprint) -> Option<TriangleMesh> {
-    let mat_box_op = create_material_from_description(
-        &mesh_bp.material_type,
-        mesh_bp.albedo,
-        mesh_bp.material_param,
-    );
-    match mat_box_op {
-        Some(mat_box) => Some(TriangleMesh::new(
-            &mesh_bp.obj_filepath,
-            mesh_bp.translation,
-            mesh_bp.rotation_rad,
+    let material_override = match &mesh_bp.material {
+        Some(material_bp) => match create_material_from_description(material_bp) {
+            Some(mat_box) => Some(mat_box),
+            None => {
+                println!("Failed to parse material info provided with mesh!");
+                return None;
+            }
+        },
+        None => None,
+    };
+    let mut mesh_data = load_mesh_from_file(&mesh_bp.obj_filepath, Vec3::zero(), Vec3::zero(), 1.0);
+    transform_mesh_data(
+        &mut mesh_data,
+        &create_placement_from_description(
             mesh_bp.scale,
-            mat_box,
-        )),
-        None => {
-            println!("Failed to parse material info provided with mesh!");
-            None
+            mesh_bp.rotation_rad,
+            mesh_bp.translation,
+            &mesh_bp.transform,
+        ),
+    );
+    let mut mesh = TriangleMesh::from_mesh_data(
+        mesh_data,
+        material_override,
+        mesh_bp.smooth_shading.unwrap_or(true),
+    );
+    mesh.watertight = mesh_bp.watertight.unwrap_or(false);
+    Some(mesh)
+}
+
+fn parse_instanced_mesh_bp(instanced_mesh_bp: InstancedMeshBlueprint) -> Option<InstancedMesh> {
+    let create_override = |material_bp: &Option<MaterialBlueprint>| match material_bp {
+        Some(material_bp) => match create_material_from_description(material_bp) {
+            Some(mat_box) => Ok(Some(mat_box)),
+            None => Err(()),
+        },
+        None => Ok(None),
+    };
+    let mesh_material = match create_override(&instanced_mesh_bp.material) {
+        Ok(material) => material,
+        Err(()) => {
+            println!("Failed to parse material info provided with instanced mesh!");
+            return None;
+        }
+    };
+    let mut instances = vec![];
+    for instance_bp in instanced_mesh_bp.instances {
+        match create_override(&instance_bp.material) {
+            Ok(material_override) => instances.push(MeshInstance {
+                transform: create_placement_from_description(
+                    instance_bp.scale,
+                    instance_bp.rotation_rad,
+                    instance_bp.translation,
+                    &instance_bp.transform,
+                ),
+                material_override,
+            }),
+            Err(()) => println!("Failed to parse material info provided with mesh instance!"),
         }
     }
+    // the mesh stays in object coordinates, the instances place it in the scene
+    let mut mesh = TriangleMesh::new(
+        &instanced_mesh_bp.obj_filepath,
+        Vec3::zero(),
+        Vec3::zero(),
+        1.0,
+        mesh_material,
+        instanced_mesh_bp.smooth_shading.unwrap_or(true),
+    );
+    mesh.watertight = instanced_mesh_bp.watertight.unwrap_or(false);
+    println!(
+        "Placed {} instances of {}",
+        instances.len(),
+        instanced_mesh_bp.obj_filepath
+    );
+    Some(InstancedMesh::new(mesh, instances))
 }
 
-fn parse_sphere_bp(sphere_bp: SphereBlueprint) -> Option<Sphere> {
-    let mat_box_op = create_material_from_description(
-        &sphere_bp.material_type,
-        sphere_bp.albedo,
-        sphere_bp.material_param,
-    );
+fn parse_sphere_bp(sphere_bp: SphereBlueprint) -> Option<Box<dyn Intersectable + Sync>> {
+    let mat_box_op = create_material_from_description(&sphere_bp.material);
 
-    match mat_box_op {
-        Some(mat_box) => Some(Sphere {
+    let sphere = match mat_box_op {
+        Some(mat_box) => Sphere {
             center: sphere_bp.center,
             radius: sphere_bp.radius,
             material: mat_box,
+        },
+        None => return None,
+    };
+    match &sphere_bp.transform {
+        Some(steps) => Some(Box::new(Transformed {
+            object: Box::new(sphere),
+            transform: create_transform_from_description(steps),
+        })),
+        None => Some(Box::new(sphere)),
+    }
+}
+
+fn parse_primitive_bp(primitive_bp: PrimitiveBlueprint) -> Option<Box<dyn Intersectable + Sync>> {
+    let material = match create_material_from_description(&primitive_bp.material) {
+        Some(mat_box) => mat_box,
+        None => {
+            println!("Failed to parse material info provided with primitive!");
+            return None;
+        }
+    };
+    let primitive: Box<dyn Intersectable + Sync> = match primitive_bp.shape {
+        ShapeBlueprint::Plane {
+            point,
+            normal,
+            size,
+        } => Box::new(Plane {
+            point,
+            normal,
+            size: size.map(|[size_u, size_v]| (size_u, size_v)),
+            material,
         }),
-        None => None,
+        ShapeBlueprint::Disk {
+            center,
+            normal,
+            radius,
+            inner_radius,
+        } => Box::new(Disk {
+            center,
+            normal,
+            radius,
+            inner_radius: inner_radius.unwrap_or(0.0),
+            material,
+        }),
+        ShapeBlueprint::Cuboid {
+            lower_bound,
+            upper_bound,
+        } => Box::new(Cuboid {
+            lower_bound,
+            upper_bound,
+            material,
+        }),
+        ShapeBlueprint::Cylinder {
+            center,
+            radius,
+            height,
+            capped,
+        } => Box::new(Cylinder {
+            center,
+            radius,
+            height,
+            capped: capped.unwrap_or(true),
+            material,
+        }),
+        ShapeBlueprint::Cone {
+            center,
+            radius,
+            height,
+            capped,
+        } => Box::new(Cone {
+            center,
+            radius,
+            height,
+            capped: capped.unwrap_or(true),
+            material,
+        }),
+        ShapeBlueprint::Torus {
+            center,
+            major_radius,
+            minor_radius,
+        } => Box::new(Torus {
+            center,
+            major_radius,
+            minor_radius,
+            material,
+        }),
+        ShapeBlueprint::Triangle { corners } => {
+            let transform = create_optional_transform(&primitive_bp.transform);
+            return Some(Box::new(BasicTriangle::new(
+                corners.map(|corner| transform.transform_point(&corner)),
+                material,
+            )));
+        }
+        ShapeBlueprint::Quad {
+            corner,
+            edge_u,
+            edge_v,
+        } => {
+            let transform = create_optional_transform(&primitive_bp.transform);
+            return Some(Box::new(Quad::new(
+                transform.transform_point(&corner),
+                [
+                    transform.transform_vector(&edge_u),
+                    transform.transform_vector(&edge_v),
+                ],
+                material,
+            )));
+        }
+    };
+    match &primitive_bp.transform {
+        Some(steps) => Some(Box::new(Transformed {
+            object: primitive,
+            transform: create_transform_from_description(steps),
+        })),
+        None => Some(primitive),
+    }
+}
+
+fn parse_csg_bp(csg_bp: CsgBlueprint) -> Option<Box<dyn Intersectable + Sync>> {
+    let mut operands = vec![];
+    for operand_bp in csg_bp.operands {
+        let operand = match operand_bp {
+            CsgOperandBlueprint::Csg(csg_bp) => parse_csg_bp(csg_bp),
+            CsgOperandBlueprint::Primitive(primitive_bp) => parse_primitive_bp(primitive_bp),
+            CsgOperandBlueprint::Sphere(sphere_bp) => parse_sphere_bp(sphere_bp),
+        };
+        match operand {
+            Some(operand) => operands.push(operand),
+            None => {
+                println!("Failed to parse operand of csg node!");
+                return None;
+            }
+        }
+    }
+    let operation = csg_bp.operation;
+    let node = operands
+        .into_iter()
+        .reduce(|left, right| -> Box<dyn Intersectable + Sync> {
+            Box::new(Csg {
+                operation,
+                left,
+                right,
+            })
+        });
+    let node = match node {
+        Some(node) => node,
+        None => {
+            println!("Skipping csg node without operands!");
+            return None;
+        }
+    };
+    match &csg_bp.transform {
+        Some(steps) => Some(Box::new(Transformed {
+            object: node,
+            transform: create_transform_from_description(steps),
+        })),
+        None => Some(node),
+    }
+}
+
+fn parse_volume_bp(volume_bp: VolumeBlueprint) -> Box<dyn Intersectable + Sync> {
+    assert!(
+        volume_bp.anisotropy.abs() < 1.0,
+        "anisotropy of a volume must lie between -1.0 and 1.0"
+    );
+    let medium = GridMedium::new(
+        load_density_grid(&volume_bp.filepath),
+        volume_bp.lower_bound,
+        volume_bp.upper_bound,
+        volume_bp.absorption.unwrap_or_else(Vec3::zero),
+        volume_bp.scattering,
+        volume_bp.anisotropy,
+    );
+    Box::new(Cuboid {
+        lower_bound: volume_bp.lower_bound,
+        upper_bound: volume_bp.upper_bound,
+        material: Box::new(MediumBoundary {
+            medium: Box::new(medium),
+        }),
+    })
+}
+
+fn parse_sdf_bp(sdf_bp: SdfBlueprint) -> Option<Box<dyn Intersectable + Sync>> {
+    let material = match create_material_from_description(&sdf_bp.material) {
+        Some(mat_box) => mat_box,
+        None => {
+            println!("Failed to parse material info provided with sdf!");
+            return None;
+        }
+    };
+    let sdf = Sdf {
+        root: sdf_bp.sdf,
+        step_factor: sdf_bp.step_factor.unwrap_or(1.0),
+        max_steps: sdf_bp.max_steps.unwrap_or(DEFAULT_MAX_STEPS),
+        material,
+    };
+    match &sdf_bp.transform {
+        Some(steps) => Some(Box::new(Transformed {
+            object: Box::new(sdf),
+            transform: create_transform_from_description(steps),
+        })),
+        None => Some(Box::new(sdf)),
     }
 }
 
@@ -138,24 +1099,655 @@ pub fn create_scene_from_scene_blueprint(scene_bp: SceneBlueprint) -> Scene {
         }
     }
 
-    let mut scene_elements: Vec<
-        std::boxed::Box<(dyn Intersectable + std::marker::Sync + 'static)>,
-    > = vec![];
+    let mut scene_elements: Vec<std::boxed::Box<dyn Intersectable + std::marker::Sync + 'static>> =
+        vec![];
+    let mut lights = vec![];
+    for light_bp in scene_bp.light_blueprints {
+        lights.push(Light {
+            position: light_bp.position,
+            radius: 0.0,
+            color: light_bp.color,
+            edges: None,
+        });
+    }
+
+    for instanced_mesh_bp in scene_bp.instanced_mesh_blueprints {
+        if let Some(instanced_mesh) = parse_instanced_mesh_bp(instanced_mesh_bp) {
+            scene_elements.push(Box::new(instanced_mesh));
+        }
+    }
+
     for sphere_bp in scene_bp.sphere_blueprints {
+        // emissive spheres are sampled as area lights
+        if let (Some(MaterialKind::Light), Some(emission)) =
+            (sphere_bp.material.kind(), sphere_bp.material.emission)
+        {
+            let transform = create_optional_transform(&sphere_bp.transform);
+            let scale = transform.uniform_scale().unwrap_or_else(|| {
+                panic!(
+                    "Light spheres are sampled as spheres, their transform may only rotate, \
+                     translate and scale uniformly: {:?}",
+                    sphere_bp.transform
+                )
+            });
+            lights.push(Light {
+                position: transform.transform_point(&sphere_bp.center),
+                radius: scale * sphere_bp.radius,
+                color: emission,
+                edges: None,
+            });
+        }
         let sphere_op = parse_sphere_bp(sphere_bp);
         if let Some(sphere) = sphere_op {
-            scene_elements.push(Box::new(sphere))
+            scene_elements.push(sphere)
         }
     }
 
-    let lights = vec![];
+    for primitive_bp in scene_bp.primitive_blueprints {
+        // emissive quads are sampled as area lights
+        if primitive_bp.material.kind() == Some(MaterialKind::Light) {
+            match (&primitive_bp.shape, primitive_bp.material.emission) {
+                (
+                    ShapeBlueprint::Quad {
+                        corner,
+                        edge_u,
+                        edge_v,
+                    },
+                    Some(emission),
+                ) => {
+                    let transform = create_optional_transform(&primitive_bp.transform);
+                    lights.push(Light {
+                        position: transform.transform_point(corner),
+                        radius: 0.0,
+                        color: emission,
+                        edges: Some([
+                            transform.transform_vector(edge_u),
+                            transform.transform_vector(edge_v),
+                        ]),
+                    });
+                }
+                (ShapeBlueprint::Quad { .. }, None) => {}
+                _ => println!("Only quads and spheres are sampled as area lights!"),
+            }
+        }
+        if let Some(primitive) = parse_primitive_bp(primitive_bp) {
+            scene_elements.push(primitive);
+        }
+    }
+
+    for csg_bp in scene_bp.csg_blueprints {
+        if let Some(node) = parse_csg_bp(csg_bp) {
+            scene_elements.push(node);
+        }
+    }
+
+    for sdf_bp in scene_bp.sdf_blueprints {
+        if let Some(sdf) = parse_sdf_bp(sdf_bp) {
+            scene_elements.push(sdf);
+        }
+    }
+    for volume_bp in scene_bp.volume_blueprints {
+        scene_elements.push(parse_volume_bp(volume_bp));
+    }
 
     Scene {
         triangle_meshes: loaded_meshes,
         elements: scene_elements,
         lights,
+        background_color: scene_bp.background_color,
+        fog: scene_bp.fog.map(|fog_bp| fog_bp.to_medium()),
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::{
+        create_material_from_description, create_scene_from_scene_blueprint,
+        create_texture_from_description, create_transform_from_description, CsgOperandBlueprint,
+        InstancedMeshBlueprint, MaterialBlueprint, PrimitiveBlueprint, SceneBlueprint,
+        SdfBlueprint, ShapeBlueprint, SphereBlueprint, TextureBlueprint, TransformStepBlueprint,
+        TriangleMeshBlueprint,
+    };
+    use crate::materials::hit_on;
+    use crate::sdf::SdfNode;
+    use crate::sidedness::Sidedness;
+    use crate::vec3::Vec3;
+    use crate::Ray;
+
+    #[test]
+    fn test_parse_inline_material() {
+        let sphere_bp: SphereBlueprint = serde_yaml::from_str(
+            "
+radius: 1.5
+center: {x: 1.5, y: 1.25, z: -9.0}
+material_type: dielectric
+material_param: 1.8
+albedo: {x: 0.2, y: 0.8, z: 0.2}
+roughness: 0.1
+",
+        )
+        .unwrap();
+
+        assert_eq!(sphere_bp.radius, 1.5);
+        assert_eq!(sphere_bp.material.material_type, "dielectric");
+        assert_eq!(sphere_bp.material.material_param, Some(1.8));
+        assert_eq!(sphere_bp.material.albedo, Some(Vec3::new(0.2, 0.8, 0.2)));
+        assert_eq!(sphere_bp.material.roughness, Some(0.1));
+        assert_eq!(sphere_bp.material.transmission_distance, None);
+        assert_eq!(sphere_bp.material.sidedness, None);
+    }
+
+    #[test]
+    fn test_dielectric_albedo_absorbs_light() {
+        let material_bp = |yaml: &str| -> MaterialBlueprint { serde_yaml::from_str(yaml).unwrap() };
+        let tinted = create_material_from_description(&material_bp(
+            "{material_type: dielectric, material_param: 1.5, albedo: {x: 0.8, y: 0.6, z: 0.4}}",
+        ))
+        .unwrap();
+        let clear = create_material_from_description(&material_bp(
+            "{material_type: dielectric, material_param: 1.5}",
+        ))
+        .unwrap();
+
+        // leaving the glass after travelling the default transmission distance of 1.0
+        let incoming_ray = Ray::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit_info = hit_on(tinted.as_ref(), Vec3::new(0.0, 1.0, 0.0), false);
+        let rec = tinted.sample(&incoming_ray, &hit_info).unwrap();
+        assert!((rec.attentuation - Vec3::new(0.8, 0.6, 0.4)).length() < 1e-5);
+        let hit_info = hit_on(clear.as_ref(), Vec3::new(0.0, 1.0, 0.0), false);
+        let rec = clear.sample(&incoming_ray, &hit_info).unwrap();
+        assert_eq!(rec.attentuation, Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_parse_procedural_textures() {
+        let sphere_bp: SphereBlueprint = serde_yaml::from_str(
+            "
+radius: 1000.0
+center: {x: 0.0, y: -1000.0, z: -5.0}
+material_type: lambertian
+albedo_texture:
+  texture_type: checker
+  scale: 1.0
+  even:
+    texture_type: constant
+    color: {x: 0.9, y: 0.9, z: 0.9}
+  odd:
+    texture_type: marble
+    scale: 2.0
+    turbulence: 5.0
+    base_color: {x: 0.8, y: 0.8, z: 0.8}
+    vein_color: {x: 0.1, y: 0.1, z: 0.1}
+",
+        )
+        .unwrap();
+
+        let texture_bp = sphere_bp.material.albedo_texture.unwrap();
+        match &texture_bp {
+            TextureBlueprint::Checker { scale, odd, .. } => {
+                assert_eq!(*scale, 1.0);
+                assert!(matches!(
+                    **odd,
+                    TextureBlueprint::Marble {
+                        octaves: None,
+                        seed: None,
+                        ..
+                    }
+                ));
+            }
+            _ => panic!("expected a checker texture"),
+        }
+        let texture = create_texture_from_description(&texture_bp, true);
+        assert_eq!(
+            texture.value((0.0, 0.0), &Vec3::new(0.5, 0.5, 0.5)),
+            Vec3::new(0.9, 0.9, 0.9)
+        );
+    }
+
+    #[test]
+    fn test_parse_optional_mesh_material() {
+        let mesh_yaml = "
+obj_filepath: model.obj
+scale: 1.0
+translation: {x: 0.0, y: 0.0, z: 0.0}
+rotation_rad: {x: 0.0, y: 0.0, z: 0.0}
+";
+        let mesh_bp: TriangleMeshBlueprint = serde_yaml::from_str(mesh_yaml).unwrap();
+        assert!(mesh_bp.material.is_none());
+
+        let mesh_bp: TriangleMeshBlueprint =
+            serde_yaml::from_str(&format!("{}material_type: lambertian\n", mesh_yaml)).unwrap();
+        assert_eq!(mesh_bp.material.unwrap().material_type, "lambertian");
+
+        // a broken override must not silently fall back to the .mtl materials
+        let result: Result<TriangleMeshBlueprint, _> = serde_yaml::from_str(&format!(
+            "{}material_type: lambertian\nalbedo: 0.5\n",
+            mesh_yaml
+        ));
+        assert!(result.is_err());
+        let result: Result<TriangleMeshBlueprint, _> = serde_yaml::from_str(&format!(
+            "{}albedo: {{x: 0.5, y: 0.5, z: 0.5}}\n",
+            mesh_yaml
+        ));
+        assert!(result.is_err());
+        let result: Result<InstancedMeshBlueprint, _> = serde_yaml::from_str(
+            "
+obj_filepath: tree.obj
+instances:
+  - scale: 1.0
+    material_type: metal
+    albedo: bright
+",
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_instanced_mesh() {
+        let instanced_mesh_bp: InstancedMeshBlueprint = serde_yaml::from_str(
+            "
+obj_filepath: tree.obj
+material_type: lambertian
+albedo: {x: 0.1, y: 0.5, z: 0.1}
+instances:
+  - scale: 1.0
+    translation: {x: 0.0, y: 0.0, z: -5.0}
+    rotation_rad: {x: 0.0, y: 0.0, z: 0.0}
+  - scale: 2.0
+    translation: {x: 3.0, y: 0.0, z: -5.0}
+    rotation_rad: {x: 0.5, y: 0.0, z: 0.0}
+    material_type: metal
+    material_param: 0.1
+    albedo: {x: 0.9, y: 0.9, z: 0.9}
+",
+        )
+        .unwrap();
+
+        assert_eq!(instanced_mesh_bp.smooth_shading, None);
+        assert_eq!(
+            instanced_mesh_bp.material.unwrap().material_type,
+            "lambertian"
+        );
+        assert_eq!(instanced_mesh_bp.instances.len(), 2);
+        assert!(instanced_mesh_bp.instances[0].material.is_none());
+        assert_eq!(instanced_mesh_bp.instances[1].scale, 2.0);
+        assert_eq!(
+            instanced_mesh_bp.instances[1]
+                .material
+                .as_ref()
+                .unwrap()
+                .material_type,
+            "metal"
+        );
+    }
+
+    #[test]
+    fn test_parse_transform_steps() {
+        let sphere_bp: SphereBlueprint = serde_yaml::from_str(
+            "
+radius: 1.0
+center: {x: 0.0, y: 0.0, z: 0.0}
+material_type: lambertian
+albedo: {x: 0.5, y: 0.5, z: 0.5}
+transform:
+  - transform_type: scale
+    factors: {x: 2.0, y: 1.0, z: 1.0}
+  - transform_type: euler
+    angles_rad: {x: 0.0, y: 0.0, z: 1.5707964}
+    order: XYZ
+  - {transform_type: axis_angle, axis: {x: 0.0, y: 1.0, z: 0.0}, angle_rad: 0.0}
+  - {transform_type: quaternion, x: 0.0, y: 0.0, z: 0.0, w: 1.0}
+  - transform_type: matrix
+    rows:
+      - [1.0, 0.0, 0.0, 0.0]
+      - [0.0, 1.0, 0.0, 0.0]
+      - [0.0, 0.0, 1.0, -5.0]
+      - [0.0, 0.0, 0.0, 1.0]
+",
+        )
+        .unwrap();
+
+        let transform = create_transform_from_description(&sphere_bp.transform.unwrap());
+        // stretched along x, turned onto y and moved back by the translation of the matrix
+        let point = transform.transform_point(&Vec3::new(1.0, 0.0, 0.0));
+        assert!((point - Vec3::new(0.0, 2.0, -5.0)).length() < 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quaternion 0 0 0 0 has no rotation")]
+    fn test_zero_quaternion_is_rejected() {
+        create_transform_from_description(&[TransformStepBlueprint::Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 0.0,
+        }]);
+    }
+
+    #[test]
+    fn test_parse_primitives() {
+        let primitive_bps: Vec<PrimitiveBlueprint> = serde_yaml::from_str(
+            "
+- shape: plane
+  point: {x: 0.0, y: 0.0, z: 0.0}
+  normal: {x: 0.0, y: 1.0, z: 0.0}
+  material_type: lambertian
+  albedo: {x: 0.5, y: 0.5, z: 0.5}
+- shape: box
+  lower_bound: {x: -1.0, y: 0.0, z: -6.0}
+  upper_bound: {x: 1.0, y: 1.0, z: -4.0}
+  material_type: metal
+  material_param: 0.1
+  albedo: {x: 0.9, y: 0.9, z: 0.9}
+- shape: cylinder
+  center: {x: 0.0, y: 1.0, z: -5.0}
+  radius: 0.5
+  height: 2.0
+  capped: false
+  material_type: lambertian
+  albedo: {x: 0.8, y: 0.2, z: 0.2}
+  transform:
+    - transform_type: axis_angle
+      axis: {x: 1.0, y: 0.0, z: 0.0}
+      angle_rad: 1.5707964
+- shape: triangle
+  corners:
+    - {x: 0.0, y: 0.0, z: -3.0}
+    - {x: 1.0, y: 0.0, z: -3.0}
+    - {x: 0.0, y: 1.0, z: -3.0}
+  material_type: lambertian
+  albedo: {x: 0.5, y: 0.5, z: 0.5}
+  sidedness: backface_culled
+- shape: quad
+  corner: {x: -0.5, y: 2.0, z: -0.5}
+  edge_u: {x: 1.0, y: 0.0, z: 0.0}
+  edge_v: {x: 0.0, y: 0.0, z: 1.0}
+  material_type: light
+  emission: {x: 10.0, y: 10.0, z: 10.0}
+",
+        )
+        .unwrap();
+
+        assert_eq!(primitive_bps.len(), 5);
+        assert!(matches!(
+            primitive_bps[0].shape,
+            ShapeBlueprint::Plane { size: None, .. }
+        ));
+        assert!(matches!(
+            primitive_bps[1].shape,
+            ShapeBlueprint::Cuboid { .. }
+        ));
+        assert_eq!(primitive_bps[1].material.material_type, "metal");
+        assert!(matches!(
+            primitive_bps[2].shape,
+            ShapeBlueprint::Cylinder {
+                capped: Some(false),
+                ..
+            }
+        ));
+        assert_eq!(primitive_bps[2].transform.as_ref().unwrap().len(), 1);
+        assert!(matches!(
+            primitive_bps[3].shape,
+            ShapeBlueprint::Triangle { .. }
+        ));
+        assert_eq!(
+            primitive_bps[3].material.sidedness,
+            Some(Sidedness::BackfaceCulled)
+        );
+        assert!(matches!(
+            primitive_bps[4].shape,
+            ShapeBlueprint::Quad { .. }
+        ));
+        assert_eq!(
+            primitive_bps[4].material.emission,
+            Some(Vec3::new(10.0, 10.0, 10.0))
+        );
+    }
+
+    #[test]
+    fn test_light_quads_are_sampled() {
+        let scene_bp: SceneBlueprint = serde_yaml::from_str(
+            "
+camera_blueprint:
+  camera_up: {x: 0.0, y: 1.0, z: 0.0}
+  camera_look_at: {x: 0.0, y: 0.0, z: -1.0}
+  camera_position: {x: 0.0, y: 1.0, z: 3.0}
+  camera_focal_length_mm: 35.0
+mesh_blueprints: []
+sphere_blueprints: []
+primitive_blueprints:
+  - shape: quad
+    corner: {x: -0.5, y: 0.0, z: -0.5}
+    edge_u: {x: 1.0, y: 0.0, z: 0.0}
+    edge_v: {x: 0.0, y: 0.0, z: 1.0}
+    material_type: light
+    emission: {x: 10.0, y: 10.0, z: 10.0}
+    transform:
+      - {transform_type: translate, offset: {x: 0.0, y: 2.0, z: 0.0}}
+",
+        )
+        .unwrap();
+
+        let scene = create_scene_from_scene_blueprint(scene_bp);
+        assert_eq!(scene.elements.len(), 1);
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.lights[0].position, Vec3::new(-0.5, 2.0, -0.5));
+        // the light is hit from below
+        assert!(scene.lights[0].sample(&Vec3::zero()).is_some());
+        assert!(scene.lights[0].contains_surface_point(&Vec3::new(0.0, 2.0, 0.0)));
+    }
+
+    fn light_sphere_scene(transform_yaml: &str) -> SceneBlueprint {
+        serde_yaml::from_str(&format!(
+            "
+camera_blueprint:
+  camera_up: {{x: 0.0, y: 1.0, z: 0.0}}
+  camera_look_at: {{x: 0.0, y: 0.0, z: -1.0}}
+  camera_position: {{x: 0.0, y: 1.0, z: 3.0}}
+  camera_focal_length_mm: 35.0
+mesh_blueprints: []
+sphere_blueprints:
+  - radius: 0.5
+    center: {{x: 1.0, y: 0.0, z: 0.0}}
+    material_type: light
+    emission: {{x: 10.0, y: 10.0, z: 10.0}}
+    transform:
+{}
+",
+            transform_yaml
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_transformed_light_spheres_are_sampled() {
+        let scene = create_scene_from_scene_blueprint(light_sphere_scene(
+            "      - {transform_type: scale, factors: {x: 2.0, y: 2.0, z: 2.0}}
+      - {transform_type: translate, offset: {x: 0.0, y: 3.0, z: 0.0}}",
+        ));
+        assert_eq!(scene.lights.len(), 1);
+        assert!((scene.lights[0].position - Vec3::new(2.0, 3.0, 0.0)).length() < 1e-5);
+        assert!((scene.lights[0].radius - 1.0).abs() < 1e-5);
+        assert!(scene.lights[0].contains_surface_point(&Vec3::new(2.0, 4.0, 0.0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Light spheres are sampled as spheres")]
+    fn test_distorted_light_spheres_are_rejected() {
+        create_scene_from_scene_blueprint(light_sphere_scene(
+            "      - {transform_type: scale, factors: {x: 2.0, y: 1.0, z: 1.0}}",
+        ));
+    }
+
+    #[test]
+    fn test_parse_csg_tree() {
+        let scene_bp: SceneBlueprint = serde_yaml::from_str(
+            "
+camera_blueprint:
+  camera_up: {x: 0.0, y: 1.0, z: 0.0}
+  camera_look_at: {x: 0.0, y: 0.0, z: -1.0}
+  camera_position: {x: 0.0, y: 0.0, z: 0.0}
+  camera_focal_length_mm: 35.0
+mesh_blueprints: []
+sphere_blueprints: []
+csg_blueprints:
+  - operation: difference
+    transform:
+      - {transform_type: translate, offset: {x: 0.0, y: 0.0, z: -5.0}}
+    operands:
+      - operation: intersection
+        operands:
+          - shape: box
+            lower_bound: {x: -1.0, y: -1.0, z: -1.0}
+            upper_bound: {x: 1.0, y: 1.0, z: 1.0}
+            material_type: lambertian
+            albedo: {x: 0.8, y: 0.2, z: 0.2}
+          - radius: 1.3
+            center: {x: 0.0, y: 0.0, z: 0.0}
+            material_type: lambertian
+            albedo: {x: 0.2, y: 0.2, z: 0.8}
+      - shape: cylinder
+        center: {x: 0.0, y: 0.0, z: 0.0}
+        radius: 0.5
+        height: 3.0
+        material_type: lambertian
+        albedo: {x: 0.2, y: 0.8, z: 0.2}
+        transform:
+          - {transform_type: axis_angle, axis: {x: 1.0, y: 0.0, z: 0.0}, angle_rad: 1.5707964}
+",
+        )
+        .unwrap();
+
+        assert_eq!(scene_bp.csg_blueprints.len(), 1);
+        assert!(matches!(
+            scene_bp.csg_blueprints[0].operands[0],
+            CsgOperandBlueprint::Csg(_)
+        ));
+        let scene = create_scene_from_scene_blueprint(scene_bp);
+        assert_eq!(scene.elements.len(), 1);
+        // the hole is drilled along z
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(scene.hit(&ray, 1e-3, f32::MAX).is_none());
+        // next to it the front face of the box is hit
+        let ray = Ray::new(Vec3::new(0.7, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = scene.hit(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-4);
+        // the corners of the box are cut off by the sphere
+        let ray = Ray::new(Vec3::new(0.85, 0.85, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = scene.hit(&ray, 1e-3, f32::MAX).unwrap();
+        assert!(
+            (hit_info.dist_from_ray_orig - (5.0 - (1.69_f32 - 2.0 * 0.7225).sqrt())).abs() < 1e-4
+        );
+    }
+
+    #[test]
+    fn test_parse_sdf_tree() {
+        let sdf_bp: SdfBlueprint = serde_yaml::from_str(
+            "
+sdf:
+  sdf_type: twist
+  rate: 0.5
+  child:
+    sdf_type: smooth_union
+    smoothness: 0.3
+    children:
+      - {sdf_type: box, center: {x: 0.0, y: 0.0, z: 0.0}, half_size: {x: 1.0, y: 2.0, z: 0.3}}
+      - {sdf_type: sphere, center: {x: 0.0, y: 2.0, z: 0.0}, radius: 0.6}
+      - sdf_type: repeat
+        period: {x: 0.0, y: 1.0, z: 0.0}
+        child: {sdf_type: torus, center: {x: 0.0, y: 0.0, z: 0.0}, major_radius: 1.0, minor_radius: 0.1}
+step_factor: 0.6
+material_type: lambertian
+albedo: {x: 0.5, y: 0.5, z: 0.5}
+",
+        )
+        .unwrap();
+
+        assert_eq!(sdf_bp.step_factor, Some(0.6));
+        assert_eq!(sdf_bp.max_steps, None);
+        match &sdf_bp.sdf {
+            SdfNode::Twist { child, .. } => match &**child {
+                SdfNode::SmoothUnion { children, .. } => {
+                    assert_eq!(children.len(), 3);
+                    assert!(matches!(
+                        children[0],
+                        SdfNode::Cuboid { rounding, .. } if rounding == 0.0
+                    ));
+                }
+                _ => panic!("expected a smooth union"),
+            },
+            _ => panic!("expected a twist"),
+        }
+        assert!(sdf_bp.sdf.distance(&Vec3::zero()) < 0.0);
+    }
+
+    #[test]
+    fn test_parse_fog_and_volumetric_sphere() {
+        let scene_bp: SceneBlueprint = serde_yaml::from_str(
+            "
+camera_blueprint:
+  camera_up: {x: 0.0, y: 1.0, z: 0.0}
+  camera_look_at: {x: 0.0, y: 0.0, z: -1.0}
+  camera_position: {x: 0.0, y: 0.0, z: 0.0}
+  camera_focal_length_mm: 35.0
+mesh_blueprints: []
+sphere_blueprints:
+  - radius: 1.0
+    center: {x: 0.0, y: 0.0, z: -5.0}
+    material_type: medium
+    absorption: {x: 0.5, y: 0.5, z: 0.5}
+    scattering: {x: 0.5, y: 0.5, z: 0.5}
+    anisotropy: 0.3
+fog:
+  absorption: {x: 0.0, y: 0.0, z: 0.0}
+  scattering: {x: 0.1, y: 0.1, z: 0.1}
+",
+        )
+        .unwrap();
+
+        let scene = create_scene_from_scene_blueprint(scene_bp);
+        let fog = scene.fog.unwrap();
+        assert_eq!(fog.anisotropy, 0.0);
+        assert_eq!(fog.scattering, Vec3::new(0.1, 0.1, 0.1));
+
+        // 8 units of fog and 2 units through the sphere
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let transmittance = scene.transmittance(&ray, 0.0, 10.0, scene.fog_medium());
+        let expected = (-0.1_f32 * 8.0 - 1.0 * 2.0).exp();
+        assert!(
+            (transmittance.x - expected).abs() < 1e-4,
+            "{:?}",
+            transmittance
+        );
+        // starting outside of any medium, the ray returns to the fog after the sphere
+        let transmittance = scene.transmittance(&ray, 0.0, 10.0, None);
+        let expected = (-2.0_f32 - 0.1 * 4.0).exp();
+        assert!(
+            (transmittance.x - expected).abs() < 1e-4,
+            "{:?}",
+            transmittance
+        );
+    }
+
+    #[test]
+    fn test_parse_subsurface_material() {
+        let sphere_bp: SphereBlueprint = serde_yaml::from_str(
+            "
+radius: 1.0
+center: {x: 0.0, y: 1.0, z: -4.0}
+material_type: subsurface
+albedo: {x: 0.9, y: 0.7, z: 0.5}
+mean_free_path: {x: 0.3, y: 0.1, z: 0.05}
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            sphere_bp.material.mean_free_path,
+            Some(Vec3::new(0.3, 0.1, 0.05))
+        );
+        let material = create_material_from_description(&sphere_bp.material).unwrap();
+        assert!(material.medium().is_some());
+        assert!(!material.is_invisible());
+    }
+}
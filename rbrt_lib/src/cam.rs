@@ -75,9 +75,6 @@ impl Camera {
             - 0.001 * img_row_center_offset_mm * self.up;
         let ray_direction = (ray_target_in_img_plane - self.position).normalize();
 
-        Ray {
-            origin: self.position,
-            direction: ray_direction,
-        }
+        Ray::new(self.position, ray_direction)
     }
 }
@@ -0,0 +1,140 @@
+use std::f32::consts::PI;
+
+use crate::cylinder::closer_hit;
+use crate::materials::orthonormal_basis;
+use crate::plane::intersect_disk;
+use crate::polynomial::solve_quadratic;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+/// cone standing on a base of radius around center, with the apex height above it along the y axis,
+/// u runs around the axis like on spheres, v from the base to the apex
+pub struct Cone {
+    pub center: Vec3,
+    pub radius: f32,
+    pub height: f32,
+    /// closed with a disk at the base
+    pub capped: bool,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+impl Cone {
+    fn intersect_side(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let direction = ray.direction.normalize();
+        let origin = ray.origin - self.center;
+        let (o_x, o_y, o_z) = (origin.x as f64, origin.y as f64, origin.z as f64);
+        let (d_x, d_y, d_z) = (direction.x as f64, direction.y as f64, direction.z as f64);
+        let height = self.height as f64;
+        let sq_slope = (self.radius as f64 / height).powi(2);
+        // x^2 + z^2 = (r/h)^2 * (h - y)^2 along the ray, which includes the mirrored cone above the apex
+        let roots = solve_quadratic(
+            d_x * d_x + d_z * d_z - sq_slope * d_y * d_y,
+            2.0 * (o_x * d_x + o_z * d_z + sq_slope * (height - o_y) * d_y),
+            o_x * o_x + o_z * o_z - sq_slope * (height - o_y).powi(2),
+        );
+        roots.into_iter().find_map(|root| {
+            let dist = root as f32;
+            let local = origin + dist * direction;
+            if dist < min_dist || dist > max_dist || local.y < 0.0 || local.y > self.height {
+                return None;
+            }
+            let slope = self.radius / self.height;
+            let radius_xz = (local.x * local.x + local.z * local.z).sqrt();
+            let (hit_normal, tangent, bitangent) = if radius_xz < 1e-6 * self.radius {
+                // neither the normal nor u are defined at the apex
+                let (tangent, bitangent) = orthonormal_basis(&Vec3::new(0.0, 1.0, 0.0));
+                (Vec3::new(0.0, 1.0, 0.0), tangent, bitangent)
+            } else {
+                (
+                    Vec3::new(local.x, slope * radius_xz, local.z).normalize(),
+                    2.0 * PI * Vec3::new(local.z, 0.0, -local.x),
+                    self.height
+                        * Vec3::new(
+                            -slope * local.x / radius_xz,
+                            1.0,
+                            -slope * local.z / radius_xz,
+                        ),
+                )
+            };
+            let phi = (-local.z).atan2(local.x) + PI;
+            Some(HitInformation {
+                hit_point: ray.origin + dist * direction,
+                geometric_normal: hit_normal,
+                shading_normal: hit_normal,
+                front_face: ray.direction.dot(&hit_normal) < 0.0,
+                uv: (phi / (2.0 * PI), local.y / self.height),
+                tangent,
+                bitangent,
+                hit_material: &*self.material,
+                vertex_color: None,
+                dist_from_ray_orig: dist,
+            })
+        })
+    }
+}
+
+impl Intersectable for Cone {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let side = self.intersect_side(ray, min_dist, max_dist);
+        if !self.capped {
+            return side;
+        }
+        let base = intersect_disk(
+            ray,
+            &self.center,
+            &Vec3::new(0.0, -1.0, 0.0),
+            self.radius,
+            0.0,
+            &*self.material,
+            min_dist,
+            side.map_or(max_dist, |hit| hit.dist_from_ray_orig),
+        );
+        closer_hit(side, base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cone;
+    use crate::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    #[test]
+    fn test_cone_intersection() {
+        let cone = Cone {
+            center: Vec3::new(0.0, 0.0, -5.0),
+            radius: 1.0,
+            height: 2.0,
+            capped: true,
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        };
+        // halfway up the radius is 0.5, the normal leans upwards
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = cone.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.5).abs() < 1e-5);
+        let expected_normal = Vec3::new(0.0, 0.5, 1.0).normalize();
+        assert!((hit_info.geometric_normal - expected_normal).length() < 1e-5);
+        assert!((hit_info.uv.1 - 0.5).abs() < 1e-6);
+
+        // the mirrored cone above the apex is not part of the surface
+        let ray = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cone.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+
+        // the base faces downwards
+        let ray = Ray::new(Vec3::new(0.3, -4.0, -5.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit_info = cone.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-5);
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, -1.0, 0.0));
+    }
+}
@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{HitInformation, Intersectable, Ray};
+
+/// upper limit of the surface crossings collected along a ray
+pub const MAX_CROSSINGS: usize = 64;
+
+/// first hit of the object behind the crossing along the ray, up to max_dist
+/// the search starts at the spawn origin of the crossing, which lies outside of its error
+/// bounds at any scale, so that the crossing is not found again
+/// the distance of the hit is measured from the origin of the ray
+pub fn hit_behind<'a, T: Intersectable + ?Sized>(
+    object: &'a T,
+    ray: &Ray,
+    crossing: &HitInformation,
+    max_dist: f32,
+) -> Option<HitInformation<'a>> {
+    let spawned_ray = Ray {
+        origin: crossing.spawn_origin(&ray.direction),
+        ..*ray
+    };
+    let crossing_dist = crossing.dist_from_ray_orig;
+    let mut hit_info = object.intersect_with_ray(&spawned_ray, 0.0, max_dist - crossing_dist)?;
+    hit_info.dist_from_ray_orig += crossing_dist;
+    Some(hit_info)
+}
+
+/// span of a ray inside of a solid, from the hit where it enters to the hit where it leaves
+/// entry is None if the ray starts inside, exit is None if it never leaves, e.g. a half space
+#[derive(Copy, Clone)]
+pub struct SolidInterval<'a> {
+    pub entry: Option<HitInformation<'a>>,
+    pub exit: Option<HitInformation<'a>>,
+}
+
+/// pairs up the sorted surface crossings of a closed solid with outward normals into intervals,
+/// the ray enters where it runs against the normal
+pub fn intervals_from_crossings<'a>(
+    crossings: Vec<HitInformation<'a>>,
+    ray: &Ray,
+) -> Vec<SolidInterval<'a>> {
+    let mut intervals = vec![];
+    let mut entry = None;
+    let mut inside = false;
+    for (crossing_idx, hit_info) in crossings.into_iter().enumerate() {
+        let entering = hit_info.geometric_normal.dot(&ray.direction) < 0.0;
+        if entering && !inside {
+            entry = Some(hit_info);
+            inside = true;
+        } else if !entering && (inside || crossing_idx == 0) {
+            // leaving without entering first: the ray started inside
+            intervals.push(SolidInterval {
+                entry: entry.take(),
+                exit: Some(hit_info),
+            });
+            inside = false;
+        }
+    }
+    if inside {
+        intervals.push(SolidInterval {
+            entry: entry.take(),
+            exit: None,
+        });
+    }
+    intervals
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    /// left without right
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(&self, inside_left: bool, inside_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => inside_left || inside_right,
+            CsgOperation::Intersection => inside_left && inside_right,
+            CsgOperation::Difference => inside_left && !inside_right,
+        }
+    }
+}
+
+/// where the ray enters or leaves one of the operands of a csg node
+struct Crossing<'a> {
+    dist: f32,
+    is_left: bool,
+    entering: bool,
+    hit_info: HitInformation<'a>,
+}
+
+fn collect_crossings<'a>(intervals: Vec<SolidInterval<'a>>, is_left: bool) -> Vec<Crossing<'a>> {
+    let mut crossings = vec![];
+    for interval in intervals {
+        for (hit_info, entering) in [(interval.entry, true), (interval.exit, false)] {
+            if let Some(hit_info) = hit_info {
+                crossings.push(Crossing {
+                    dist: hit_info.dist_from_ray_orig,
+                    is_left,
+                    entering,
+                    hit_info,
+                });
+            }
+        }
+    }
+    crossings
+}
+
+/// intervals of the combination of two solids given their intervals along the same ray
+pub fn combine_intervals<'a>(
+    operation: CsgOperation,
+    left: Vec<SolidInterval<'a>>,
+    right: Vec<SolidInterval<'a>>,
+) -> Vec<SolidInterval<'a>> {
+    let starts_inside = |intervals: &[SolidInterval]| {
+        intervals
+            .first()
+            .is_some_and(|interval| interval.entry.is_none())
+    };
+    let mut inside_left = starts_inside(&left);
+    let mut inside_right = starts_inside(&right);
+    let mut inside = operation.contains(inside_left, inside_right);
+
+    let mut crossings = collect_crossings(left, true);
+    crossings.extend(collect_crossings(right, false));
+    crossings.sort_by(|a, b| a.dist.total_cmp(&b.dist));
+
+    let mut intervals = vec![];
+    let mut entry = None;
+    for crossing in crossings {
+        if crossing.is_left {
+            inside_left = crossing.entering;
+        } else {
+            inside_right = crossing.entering;
+        }
+        let now_inside = operation.contains(inside_left, inside_right);
+        if now_inside == inside {
+            continue;
+        }
+        let mut hit_info = crossing.hit_info;
+        if !crossing.is_left && operation == CsgOperation::Difference {
+            // the inside of the subtracted solid becomes the outside of the result
+            hit_info.geometric_normal = -1.0 * hit_info.geometric_normal;
+            hit_info.shading_normal = -1.0 * hit_info.shading_normal;
+            hit_info.front_face = !hit_info.front_face;
+        }
+        if now_inside {
+            entry = Some(hit_info);
+        } else {
+            intervals.push(SolidInterval {
+                entry: entry.take(),
+                exit: Some(hit_info),
+            });
+        }
+        inside = now_inside;
+    }
+    if inside {
+        intervals.push(SolidInterval {
+            entry: entry.take(),
+            exit: None,
+        });
+    }
+    intervals
+}
+
+/// boolean combination of two solids, which need closed surfaces with outward normals,
+/// csg nodes can be operands of other csg nodes
+/// hits keep the material of the operand whose surface they lie on
+pub struct Csg {
+    pub operation: CsgOperation,
+    pub left: Box<dyn Intersectable + Sync>,
+    pub right: Box<dyn Intersectable + Sync>,
+}
+
+impl Intersectable for Csg {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        self.solid_intervals(ray, min_dist)
+            .into_iter()
+            .flat_map(|interval| [interval.entry, interval.exit])
+            .flatten()
+            .next()
+            .filter(|hit_info| hit_info.dist_from_ray_orig <= max_dist)
+    }
+
+    fn solid_intervals(&self, ray: &Ray, min_dist: f32) -> Vec<SolidInterval<'_>> {
+        combine_intervals(
+            self.operation,
+            self.left.solid_intervals(ray, min_dist),
+            self.right.solid_intervals(ray, min_dist),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Csg, CsgOperation};
+    use crate::cuboid::Cuboid;
+    use crate::instance::Transformed;
+    use crate::lambertian::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::transform::Transform;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+    use std::f32::consts::FRAC_PI_4;
+
+    fn new_sphere(center_x: f32) -> Box<Sphere> {
+        Box::new(Sphere {
+            center: Vec3::new(center_x, 0.0, -5.0),
+            radius: 1.0,
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        })
+    }
+
+    /// distances of the entries and exits of the node along the ray
+    fn interval_dists(node: &dyn Intersectable, ray: &Ray) -> Vec<(Option<f32>, Option<f32>)> {
+        node.solid_intervals(ray, 1e-3)
+            .iter()
+            .map(|interval| {
+                (
+                    interval.entry.map(|hit_info| hit_info.dist_from_ray_orig),
+                    interval.exit.map(|hit_info| hit_info.dist_from_ray_orig),
+                )
+            })
+            .collect()
+    }
+
+    fn assert_dists(
+        actual: &[(Option<f32>, Option<f32>)],
+        expected: &[(Option<f32>, Option<f32>)],
+    ) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            for (actual, expected) in [(actual.0, expected.0), (actual.1, expected.1)] {
+                match (actual, expected) {
+                    (Some(actual), Some(expected)) => {
+                        assert!(
+                            (actual - expected).abs() < 1e-4,
+                            "{} != {}",
+                            actual,
+                            expected
+                        )
+                    }
+                    (actual, expected) => assert_eq!(actual, expected),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_csg_of_spheres() {
+        // spheres at x = 0 and x = 1, the ray runs along x from x = -5
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        let new_node = |operation| Csg {
+            operation,
+            left: new_sphere(0.0),
+            right: new_sphere(1.0),
+        };
+
+        let union = new_node(CsgOperation::Union);
+        assert_dists(&interval_dists(&union, &ray), &[(Some(4.0), Some(7.0))]);
+        let intersection = new_node(CsgOperation::Intersection);
+        assert_dists(
+            &interval_dists(&intersection, &ray),
+            &[(Some(5.0), Some(6.0))],
+        );
+
+        let difference = new_node(CsgOperation::Difference);
+        assert_dists(
+            &interval_dists(&difference, &ray),
+            &[(Some(4.0), Some(5.0))],
+        );
+        // the cut face points out of the remaining solid
+        let hit_info = difference.intersect_with_ray(&ray, 4.5, f32::MAX).unwrap();
+        assert!((hit_info.geometric_normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-5);
+        // the ray does not hit the cut away part
+        assert!(difference.intersect_with_ray(&ray, 5.5, f32::MAX).is_none());
+
+        // starting inside, the first hit is where the ray leaves
+        let inside_ray = Ray::new(Vec3::new(-0.5, 0.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_dists(
+            &interval_dists(&difference, &inside_ray),
+            &[(None, Some(0.5))],
+        );
+    }
+
+    #[test]
+    fn test_nested_csg() {
+        // a sphere with a hole drilled along the z axis by a rotated, stretched box, united with
+        // another sphere
+        let drill = Transformed {
+            object: Box::new(Cuboid {
+                lower_bound: Vec3::new(-0.25, -0.25, -10.0),
+                upper_bound: Vec3::new(0.25, 0.25, 10.0),
+                material: Box::new(Lambertian { albedo: 0.5.into() }),
+            }),
+            transform: Transform::axis_angle(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_4),
+        };
+        let drilled = Csg {
+            operation: CsgOperation::Difference,
+            left: new_sphere(0.0),
+            right: Box::new(drill),
+        };
+        let node = Csg {
+            operation: CsgOperation::Union,
+            left: Box::new(drilled),
+            right: new_sphere(3.0),
+        };
+
+        // through the hole the ray only hits the second sphere
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(node.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+        let ray = Ray::new(Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = node.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-4);
+
+        // along x the ray enters the drilled sphere, crosses the hole and reaches the other sphere
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        let half_diagonal = 0.25 * 2.0_f32.sqrt();
+        assert_dists(
+            &interval_dists(&node, &ray),
+            &[
+                (Some(4.0), Some(5.0 - half_diagonal)),
+                (Some(5.0 + half_diagonal), Some(6.0)),
+                (Some(7.0), Some(9.0)),
+            ],
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+fn unit(axis: usize) -> Vec3 {
+    match axis {
+        0 => Vec3::new(1.0, 0.0, 0.0),
+        1 => Vec3::new(0.0, 1.0, 0.0),
+        _ => Vec3::new(0.0, 0.0, 1.0),
+    }
+}
+
+/// axis aligned box, oriented boxes are placed with a transform
+/// each face is textured from 0 to 1, u and v run along the next two axes (x -> y -> z -> x)
+pub struct Cuboid {
+    pub lower_bound: Vec3,
+    pub upper_bound: Vec3,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+impl Cuboid {
+    /// distances along the unit direction where the ray enters and leaves the box
+    fn slab_distances(&self, origin: &Vec3, direction: &Vec3) -> (f32, f32) {
+        let mut dist_enter = -f32::MAX;
+        let mut dist_exit = f32::MAX;
+        for axis in 0..3 {
+            let inv_direction = 1.0 / direction.axis(axis);
+            let dist_lower = (self.lower_bound.axis(axis) - origin.axis(axis)) * inv_direction;
+            let dist_upper = (self.upper_bound.axis(axis) - origin.axis(axis)) * inv_direction;
+            dist_enter = dist_enter.max(dist_lower.min(dist_upper));
+            dist_exit = dist_exit.min(dist_lower.max(dist_upper));
+        }
+        (dist_enter, dist_exit)
+    }
+}
+
+impl Intersectable for Cuboid {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let direction = ray.direction.normalize();
+        let (dist_enter, dist_exit) = self.slab_distances(&ray.origin, &direction);
+        if dist_enter > dist_exit {
+            return None;
+        }
+        // rays starting inside of the box hit it where they leave
+        let dist = if dist_enter >= min_dist {
+            dist_enter
+        } else {
+            dist_exit
+        };
+        if dist < min_dist || dist > max_dist {
+            return None;
+        }
+        let hit_point = ray.origin + dist * direction;
+
+        // the face is the one the hit point is relatively closest to
+        let center = 0.5 * (self.lower_bound + self.upper_bound);
+        let half_size = 0.5 * (self.upper_bound - self.lower_bound);
+        let local = hit_point - center;
+        let axis = (0..3)
+            .max_by(|a, b| {
+                let relative_a = (local.axis(*a) / half_size.axis(*a)).abs();
+                let relative_b = (local.axis(*b) / half_size.axis(*b)).abs();
+                relative_a.total_cmp(&relative_b)
+            })
+            .unwrap_or(0);
+        let hit_normal = local.axis(axis).signum() * unit(axis);
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let relative = |axis: usize| 0.5 * local.axis(axis) / half_size.axis(axis) + 0.5;
+
+        Some(HitInformation {
+            hit_point,
+            geometric_normal: hit_normal,
+            shading_normal: hit_normal,
+            front_face: ray.direction.dot(&hit_normal) < 0.0,
+            uv: (relative(u_axis), relative(v_axis)),
+            tangent: 2.0 * half_size.axis(u_axis) * unit(u_axis),
+            bitangent: 2.0 * half_size.axis(v_axis) * unit(v_axis),
+            hit_material: &*self.material,
+            vertex_color: None,
+            dist_from_ray_orig: dist,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cuboid;
+    use crate::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    #[test]
+    fn test_cuboid_intersection() {
+        let cuboid = Cuboid {
+            lower_bound: Vec3::new(-1.0, -2.0, -6.0),
+            upper_bound: Vec3::new(1.0, 2.0, -4.0),
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        };
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = cuboid.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-6);
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, 0.0, 1.0));
+        // u along x, v along y
+        assert!((hit_info.uv.0 - 0.75).abs() < 1e-6 && (hit_info.uv.1 - 0.75).abs() < 1e-6);
+
+        // from the inside the far side is hit
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_info = cuboid.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 2.0).abs() < 1e-6);
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, -1.0, 0.0));
+
+        // misses next to the box and beyond max_dist
+        let ray = Ray::new(Vec3::new(1.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cuboid.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cuboid.intersect_with_ray(&ray, 1e-3, 3.0).is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use std::f32::consts::PI;
+
+use crate::plane::intersect_disk;
+use crate::polynomial::solve_quadratic;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+/// closest of two optional hits
+pub fn closer_hit<'a>(
+    hit_a: Option<HitInformation<'a>>,
+    hit_b: Option<HitInformation<'a>>,
+) -> Option<HitInformation<'a>> {
+    match (hit_a, hit_b) {
+        (Some(a), Some(b)) if b.dist_from_ray_orig < a.dist_from_ray_orig => Some(b),
+        (Some(a), _) => Some(a),
+        (None, b) => b,
+    }
+}
+
+/// cylinder of height along the y axis around its center, oriented cylinders are placed with a
+/// transform, u runs around the axis like on spheres, v from the bottom to the top
+pub struct Cylinder {
+    pub center: Vec3,
+    pub radius: f32,
+    pub height: f32,
+    /// closed with disks at the top and bottom, otherwise a tube
+    pub capped: bool,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+impl Cylinder {
+    fn intersect_side(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let direction = ray.direction.normalize();
+        let origin = ray.origin - self.center;
+        let (o_x, o_z) = (origin.x as f64, origin.z as f64);
+        let (d_x, d_z) = (direction.x as f64, direction.z as f64);
+        // (o_x + t d_x)^2 + (o_z + t d_z)^2 = r^2
+        let roots = solve_quadratic(
+            d_x * d_x + d_z * d_z,
+            2.0 * (o_x * d_x + o_z * d_z),
+            o_x * o_x + o_z * o_z - (self.radius as f64).powi(2),
+        );
+        let half_height = 0.5 * self.height;
+        roots.into_iter().find_map(|root| {
+            let dist = root as f32;
+            let local = origin + dist * direction;
+            if dist < min_dist || dist > max_dist || local.y.abs() > half_height {
+                return None;
+            }
+            let phi = (-local.z).atan2(local.x) + PI;
+            let hit_normal = Vec3::new(local.x, 0.0, local.z).normalize();
+            Some(HitInformation {
+                hit_point: ray.origin + dist * direction,
+                geometric_normal: hit_normal,
+                shading_normal: hit_normal,
+                front_face: ray.direction.dot(&hit_normal) < 0.0,
+                uv: (phi / (2.0 * PI), local.y / self.height + 0.5),
+                tangent: 2.0 * PI * Vec3::new(local.z, 0.0, -local.x),
+                bitangent: Vec3::new(0.0, self.height, 0.0),
+                hit_material: &*self.material,
+                vertex_color: None,
+                dist_from_ray_orig: dist,
+            })
+        })
+    }
+}
+
+impl Intersectable for Cylinder {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let mut closest = self.intersect_side(ray, min_dist, max_dist);
+        if self.capped {
+            for side in [-1.0, 1.0] {
+                let max_dist = closest.map_or(max_dist, |hit| hit.dist_from_ray_orig);
+                let normal = Vec3::new(0.0, side, 0.0);
+                let cap = intersect_disk(
+                    ray,
+                    &(self.center + 0.5 * self.height * normal),
+                    &normal,
+                    self.radius,
+                    0.0,
+                    &*self.material,
+                    min_dist,
+                    max_dist,
+                );
+                closest = closer_hit(closest, cap);
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cylinder;
+    use crate::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    #[test]
+    fn test_cylinder_intersection() {
+        let new_cylinder = |capped| Cylinder {
+            center: Vec3::new(0.0, 0.0, -5.0),
+            radius: 1.0,
+            height: 2.0,
+            capped,
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        };
+        let tube = new_cylinder(false);
+        let ray = Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = tube.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-5);
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+        assert!((hit_info.uv.0 - 0.25).abs() < 1e-6 && (hit_info.uv.1 - 0.75).abs() < 1e-6);
+
+        // looking down the axis, the open tube is missed, the capped one is hit on top
+        let ray = Ray::new(Vec3::new(0.2, 5.0, -5.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(tube.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+        let capped = new_cylinder(true);
+        let hit_info = capped.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-5);
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, 1.0, 0.0));
+
+        // from the inside of the tube the far wall is hit
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit_info = tube.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 1.0).abs() < 1e-5);
+        assert!((hit_info.geometric_normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+    }
+}
@@ -1,62 +1,339 @@
-use crate::materials::reflect;
+use std::f32::consts::PI;
+
+use crate::materials::{facing_normal, local_to_world, reflect, ScatterRecord};
+use crate::spectrum::D_LINE_WAVELENGTH_NM;
+use crate::texture::Texture;
 use crate::vec3::Vec3;
 use crate::{HitInformation, Ray, RayScattering};
 
+/// wavelength dependent index of refraction
 #[derive(Copy, Clone, Debug)]
+pub enum RefractiveIndex {
+    Constant(f32),
+    /// n = a + b / lambda^2 + c / lambda^4, lambda in micrometers
+    Cauchy {
+        a: f32,
+        b: f32,
+        c: f32,
+    },
+    /// n^2 = 1 + sum_i b_i * lambda^2 / (lambda^2 - c_i), lambda in micrometers
+    Sellmeier {
+        b: [f32; 3],
+        c: [f32; 3],
+    },
+}
+
+impl RefractiveIndex {
+    /// index of refraction at the given wavelength, the d-line is used if the ray carries none
+    pub fn at(&self, wavelength_nm: Option<f32>) -> f32 {
+        let lambda_um = wavelength_nm.unwrap_or(D_LINE_WAVELENGTH_NM) / 1000.0;
+        let lambda_sq = lambda_um * lambda_um;
+        match *self {
+            RefractiveIndex::Constant(ref_idx) => ref_idx,
+            RefractiveIndex::Cauchy { a, b, c } => a + b / lambda_sq + c / (lambda_sq * lambda_sq),
+            RefractiveIndex::Sellmeier { b, c } => {
+                let mut n_sq = 1.0;
+                for i in 0..3 {
+                    n_sq += b[i] * lambda_sq / (lambda_sq - c[i]);
+                }
+                n_sq.sqrt()
+            }
+        }
+    }
+}
+
 pub struct Dielectric {
-    pub ref_idx: f32,
+    pub ior: RefractiveIndex,
+    /// color that light takes on after it travelled transmission_distance inside the medium,
+    /// looked up where the light leaves the medium, white glass does not absorb any light
+    pub transmission_color: Box<dyn Texture>,
+    pub transmission_distance: f32,
+    /// alpha of the GGX distribution of microfacet normals, read from the first channel,
+    /// 0.0 is perfectly smooth glass
+    pub roughness: Box<dyn Texture>,
 }
 
-impl RayScattering for Dielectric {
-    fn scatter(
-        &self,
-        incoming_ray: &Ray,
-        hit_info: &HitInformation,
-        attentuation: &mut Vec3,
-        scattered_ray: &mut Ray,
-    ) -> bool {
-        *attentuation = Vec3::new(1.0, 1.0, 1.0);
-        let reflected_ray_dir = reflect(&incoming_ray.direction, &hit_info.hit_normal);
-        let outward_normal;
-        let ni_over_nt;
-        let cosine;
-        let a = incoming_ray
-            .direction
-            .normalize()
-            .dot(&hit_info.hit_normal.normalize());
-        if a > 0.0 {
-            outward_normal = -1.0 * hit_info.hit_normal;
-            ni_over_nt = self.ref_idx;
-            cosine = self.ref_idx * a;
+impl Dielectric {
+    /// clear, smooth glass
+    pub fn new(ref_idx: f32) -> Dielectric {
+        Dielectric {
+            ior: RefractiveIndex::Constant(ref_idx),
+            transmission_color: Vec3::new(1.0, 1.0, 1.0).into(),
+            transmission_distance: 1.0,
+            roughness: 0.0.into(),
+        }
+    }
+}
+
+/// computes the absorption coefficient sigma_a so that exp(-sigma_a * distance) = color
+pub fn absorption_from_transmission_color(color: Vec3, distance: f32) -> Vec3 {
+    let channel = |c: f32| -c.clamp(f32::MIN_POSITIVE, 1.0).ln() / distance;
+    Vec3::new(channel(color.x), channel(color.y), channel(color.z))
+}
+
+/// fraction of light that is left after travelling distance through an absorbing medium
+pub fn beer_lambert(absorption: Vec3, distance: f32) -> Vec3 {
+    Vec3::new(
+        (-absorption.x * distance).exp(),
+        (-absorption.y * distance).exp(),
+        (-absorption.z * distance).exp(),
+    )
+}
+
+/// GGX density of microfacet normals with cosine cos_m to the macro normal,
+/// see Walter et al., "Microfacet Models for Refraction through Rough Surfaces"
+fn ggx_distribution(alpha: f32, cos_m: f32) -> f32 {
+    if cos_m <= 0.0 {
+        return 0.0;
+    }
+    let alpha_sq = alpha * alpha;
+    let denom = cos_m * cos_m * (alpha_sq - 1.0) + 1.0;
+    alpha_sq / (PI * denom * denom)
+}
+
+/// Smith shadowing term of the GGX distribution for one direction
+fn ggx_shadowing(alpha: f32, dir: &Vec3, facet_normal: &Vec3, normal: &Vec3) -> f32 {
+    let cos_n = dir.dot(normal);
+    if dir.dot(facet_normal) * cos_n <= 0.0 {
+        return 0.0;
+    }
+    let tan_sq = (1.0 - cos_n * cos_n).max(0.0) / (cos_n * cos_n);
+    2.0 / (1.0 + (1.0 + alpha * alpha * tan_sq).sqrt())
+}
+
+/// fresnel reflectance for light coming from the side with index eta_view, evaluated with the
+/// cosine on the optically thinner side, 1.0 for total internal reflection
+fn reflectance(cos_view: f32, eta_view: f32, eta_other: f32) -> f32 {
+    let ni_over_nt = eta_view / eta_other;
+    if ni_over_nt <= 1.0 {
+        return schlick(cos_view, eta_other / eta_view);
+    }
+    let sin_sq_transmitted = ni_over_nt * ni_over_nt * (1.0 - cos_view * cos_view);
+    if sin_sq_transmitted >= 1.0 {
+        return 1.0;
+    }
+    schlick((1.0 - sin_sq_transmitted).sqrt(), eta_view / eta_other)
+}
+
+impl Dielectric {
+    /// the macro normal on the side of the incoming ray, the refractive index on that side and
+    /// the one on the other side
+    fn sides(&self, hit_info: &HitInformation, wavelength_nm: Option<f32>) -> (Vec3, f32, f32) {
+        let ref_idx = self.ior.at(wavelength_nm);
+        if hit_info.front_face {
+            (facing_normal(hit_info), 1.0, ref_idx)
         } else {
-            outward_normal = hit_info.hit_normal;
-            ni_over_nt = 1.0 / self.ref_idx;
-            cosine = -a;
+            (facing_normal(hit_info), ref_idx, 1.0)
         }
+    }
+
+    /// the ray travelled inside the medium to reach a back face, so it got absorbed on the way
+    fn absorbed(&self, hit_info: &HitInformation) -> Vec3 {
+        if !hit_info.front_face {
+            let color = self
+                .transmission_color
+                .value(hit_info.uv, &hit_info.hit_point);
+            let absorption = absorption_from_transmission_color(color, self.transmission_distance);
+            beer_lambert(absorption, hit_info.dist_from_ray_orig)
+        } else {
+            Vec3::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    fn roughness_at(&self, hit_info: &HitInformation) -> f32 {
+        self.roughness
+            .value(hit_info.uv, &hit_info.hit_point)
+            .x
+            .max(0.0)
+    }
+
+    /// bsdf * cos and pdf of scattering from the view direction, which points away from the
+    /// surface, into scattered_dir at a rough interface
+    fn rough_eval_and_pdf(
+        alpha: f32,
+        view_dir: &Vec3,
+        scattered_dir: &Vec3,
+        (normal, eta_view, eta_other): (Vec3, f32, f32),
+    ) -> (f32, f32) {
+        let scattered_dir = scattered_dir.normalize();
+        let cos_view = view_dir.dot(&normal);
+        let cos_scattered = scattered_dir.dot(&normal);
+        if cos_view <= 0.0 || cos_scattered == 0.0 {
+            return (0.0, 0.0);
+        }
+        let is_reflection = cos_scattered > 0.0;
+        let eta_scattered = if is_reflection { eta_view } else { eta_other };
+        // the microfacet normal that scatters the view direction into scattered_dir
+        let half_vector = if is_reflection {
+            *view_dir + scattered_dir
+        } else {
+            -1.0 * (eta_view * *view_dir + eta_scattered * scattered_dir)
+        };
+        if half_vector.length() == 0.0 {
+            return (0.0, 0.0);
+        }
+        let mut facet_normal = half_vector.normalize();
+        if facet_normal.dot(&normal) < 0.0 {
+            facet_normal = -1.0 * facet_normal;
+        }
+        let cos_view_facet = view_dir.dot(&facet_normal);
+        let cos_scattered_facet = scattered_dir.dot(&facet_normal);
+        if cos_view_facet <= 0.0 || (cos_scattered_facet > 0.0) != is_reflection {
+            return (0.0, 0.0);
+        }
+
+        let cos_facet = facet_normal.dot(&normal);
+        let distribution = ggx_distribution(alpha, cos_facet);
+        let shadowing = ggx_shadowing(alpha, view_dir, &facet_normal, &normal)
+            * ggx_shadowing(alpha, &scattered_dir, &facet_normal, &normal);
+        let fresnel = reflectance(cos_view_facet, eta_view, eta_other);
+        // facet normals are sampled with density D(m) cos(theta_m)
+        let facet_pdf = distribution * cos_facet;
+        if is_reflection {
+            (
+                fresnel * shadowing * distribution / (4.0 * cos_view),
+                fresnel * facet_pdf / (4.0 * cos_view_facet),
+            )
+        } else {
+            let denom = eta_view * cos_view_facet + eta_scattered * cos_scattered_facet;
+            if denom == 0.0 {
+                return (0.0, 0.0);
+            }
+            // change of variables from the facet normal to the refracted direction
+            let jacobian =
+                eta_scattered * eta_scattered * cos_scattered_facet.abs() / (denom * denom);
+            (
+                cos_view_facet * (1.0 - fresnel) * shadowing * distribution * jacobian / cos_view,
+                (1.0 - fresnel) * facet_pdf * jacobian,
+            )
+        }
+    }
+
+    fn sample_smooth(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Vec3 {
+        let normal = facing_normal(hit_info);
+        let ref_idx = self.ior.at(incoming_ray.wavelength_nm);
+        let reflected_ray_dir = reflect(&incoming_ray.direction, &normal);
+        // the normal points back along the ray, the ray enters through the front face
+        let cos_incident = -incoming_ray.direction.normalize().dot(&normal);
+        let (ni_over_nt, cosine) = if hit_info.front_face {
+            (1.0 / ref_idx, cos_incident)
+        } else {
+            (ref_idx, ref_idx * cos_incident)
+        };
 
         let mut refracted_ray_dir = Vec3::zero();
         let reflect_prob = if refract(
             &incoming_ray.direction,
-            &outward_normal,
+            &normal,
             ni_over_nt,
             &mut refracted_ray_dir,
         ) {
-            schlick(cosine, self.ref_idx)
+            schlick(cosine, ref_idx)
         } else {
             1.0
         };
         if rand::random::<f32>() < reflect_prob {
-            *scattered_ray = Ray {
-                origin: hit_info.hit_point,
-                direction: reflected_ray_dir,
-            };
+            reflected_ray_dir
+        } else {
+            refracted_ray_dir
+        }
+    }
+
+    /// draws a microfacet normal from D(m) cos(theta_m) and reflects or refracts at it
+    fn sample_rough(incoming_ray: &Ray, alpha: f32, sides: (Vec3, f32, f32)) -> Option<Vec3> {
+        let (normal, eta_view, eta_other) = sides;
+        let r1 = rand::random::<f32>();
+        let tan_sq = alpha * alpha * r1 / (1.0 - r1);
+        let cos_facet = 1.0 / (1.0 + tan_sq).sqrt();
+        let sin_facet = (1.0 - cos_facet * cos_facet).max(0.0).sqrt();
+        let phi = 2.0 * PI * rand::random::<f32>();
+        let facet_normal = local_to_world(
+            &Vec3::new(sin_facet * phi.cos(), sin_facet * phi.sin(), cos_facet),
+            &normal,
+        )
+        .normalize();
+
+        let direction = incoming_ray.direction.normalize();
+        let cos_view_facet = -direction.dot(&facet_normal);
+        // the facet faces away from the ray, which only happens at grazing angles
+        if cos_view_facet <= 0.0 {
+            return None;
+        }
+        let mut refracted_ray_dir = Vec3::zero();
+        if rand::random::<f32>() < reflectance(cos_view_facet, eta_view, eta_other)
+            || !refract(
+                &direction,
+                &facet_normal,
+                eta_view / eta_other,
+                &mut refracted_ray_dir,
+            )
+        {
+            Some(reflect(&direction, &facet_normal))
         } else {
-            *scattered_ray = Ray {
+            Some(refracted_ray_dir)
+        }
+    }
+}
+
+impl RayScattering for Dielectric {
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        let absorbed = self.absorbed(hit_info);
+        let alpha = self.roughness_at(hit_info);
+        if alpha <= 0.0 {
+            return Some(ScatterRecord {
+                scattered_ray: Ray {
+                    origin: hit_info.hit_point,
+                    direction: self.sample_smooth(incoming_ray, hit_info),
+                    wavelength_nm: incoming_ray.wavelength_nm,
+                },
+                attentuation: absorbed,
+                pdf: 0.0,
+                is_specular: true,
+            });
+        }
+
+        let sides = self.sides(hit_info, incoming_ray.wavelength_nm);
+        let direction = Dielectric::sample_rough(incoming_ray, alpha, sides)?;
+        let view_dir = -1.0 * incoming_ray.direction.normalize();
+        let (value, pdf) = Dielectric::rough_eval_and_pdf(alpha, &view_dir, &direction, sides);
+        // directions that end up on the wrong side of the macro surface get absorbed
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            scattered_ray: Ray {
                 origin: hit_info.hit_point,
-                direction: refracted_ray_dir,
-            };
+                direction,
+                wavelength_nm: incoming_ray.wavelength_nm,
+            },
+            attentuation: value / pdf * absorbed,
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    /// directions do not carry wavelengths, rough dispersive dielectrics are evaluated at the
+    /// d-line
+    fn eval(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        let alpha = self.roughness_at(hit_info);
+        if alpha <= 0.0 {
+            return Vec3::zero();
         }
-        true
+        let view_dir = -1.0 * incoming_dir.normalize();
+        let sides = self.sides(hit_info, None);
+        let (value, _pdf) = Dielectric::rough_eval_and_pdf(alpha, &view_dir, scattered_dir, sides);
+        value * self.absorbed(hit_info)
+    }
+
+    fn pdf(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+        let alpha = self.roughness_at(hit_info);
+        if alpha <= 0.0 {
+            return 0.0;
+        }
+        let view_dir = -1.0 * incoming_dir.normalize();
+        let sides = self.sides(hit_info, None);
+        let (_value, pdf) = Dielectric::rough_eval_and_pdf(alpha, &view_dir, scattered_dir, sides);
+        pdf
     }
 }
 
@@ -86,8 +363,16 @@ pub fn refract(
 
 #[cfg(test)]
 mod test {
-    use crate::dielectric::refract;
+    use crate::dielectric::{
+        absorption_from_transmission_color, beer_lambert, refract, Dielectric, RefractiveIndex,
+    };
+    use crate::materials::hit_on;
+    use crate::procedural_texture::CheckerTexture;
     use crate::vec3::Vec3;
+    use crate::{Ray, RayScattering};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f32::consts::PI;
 
     #[test]
     fn test_refraction() {
@@ -113,4 +398,138 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn test_beer_lambert_absorption() {
+        let color = Vec3::new(0.8, 0.5, 1.0);
+        let absorption = absorption_from_transmission_color(color, 2.0);
+
+        let transmitted = beer_lambert(absorption, 2.0);
+        assert!((transmitted - color).length() < 1e-6);
+
+        // twice the distance, twice the absorption
+        let transmitted = beer_lambert(absorption, 4.0);
+        assert!((transmitted - color * color).length() < 1e-6);
+
+        assert_eq!(beer_lambert(absorption, 0.0), Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_transmission_color_texture() {
+        let material = Dielectric {
+            transmission_color: Box::new(CheckerTexture {
+                scale: 1.0,
+                even: Vec3::new(0.5, 0.5, 0.5).into(),
+                odd: Vec3::new(0.8, 0.4, 0.2).into(),
+            }),
+            transmission_distance: 2.0,
+            ..Dielectric::new(1.5)
+        };
+        let mut hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), false);
+        hit_info.dist_from_ray_orig = 2.0;
+        let incoming_ray = Ray::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let odd_point = Vec3::new(0.5, 0.0, 0.5);
+        let even_point = Vec3::new(1.5, 0.0, 0.5);
+        assert!(
+            material.transmission_color.value((0.0, 0.0), &odd_point)
+                != material.transmission_color.value((0.0, 0.0), &even_point)
+        );
+        for point in [odd_point, even_point] {
+            hit_info.hit_point = point;
+            let color = material.transmission_color.value(hit_info.uv, &point);
+            let rec = material.sample(&incoming_ray, &hit_info).unwrap();
+            assert!((rec.attentuation - color).length() < 1e-5);
+        }
+
+        // light that enters the glass is not absorbed yet
+        let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), true);
+        let incoming_ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = material.sample(&incoming_ray, &hit_info).unwrap();
+        assert_eq!(rec.attentuation, Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_dispersion() {
+        // BK7 crown glass
+        let bk7 = RefractiveIndex::Sellmeier {
+            b: [1.039_612, 0.231_792_3, 1.010_469_4],
+            c: [0.006_000_699, 0.020_017_914, 103.560_65],
+        };
+        assert!((bk7.at(None) - 1.5168).abs() < 1e-3);
+        assert!((bk7.at(Some(486.1)) - 1.5224).abs() < 1e-3);
+        // normal dispersion: blue light is refracted more than red light
+        assert!(bk7.at(Some(450.0)) > bk7.at(Some(650.0)));
+
+        let cauchy = RefractiveIndex::Cauchy {
+            a: 1.5046,
+            b: 0.00420,
+            c: 0.0,
+        };
+        assert!((cauchy.at(Some(500.0)) - 1.5214).abs() < 1e-4);
+        assert!(cauchy.at(Some(400.0)) > cauchy.at(Some(700.0)));
+
+        assert_eq!(RefractiveIndex::Constant(1.8).at(Some(400.0)), 1.8);
+    }
+
+    #[test]
+    fn test_rough_dielectric_sample_matches_eval_and_pdf() {
+        let material = Dielectric {
+            roughness: 0.3.into(),
+            ..Dielectric::new(1.5)
+        };
+        for (front_face, direction) in [
+            (true, Vec3::new(0.0, -1.0, -1.0)),
+            (false, Vec3::new(0.0, 1.0, -0.3)),
+        ] {
+            let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), front_face);
+            let incoming_ray = Ray::new(-1.0 * direction, direction);
+            let (mut num_reflected, mut num_refracted) = (0, 0);
+            for _i in 0..1000 {
+                if let Some(rec) = material.sample(&incoming_ray, &hit_info) {
+                    assert!(!rec.is_specular);
+                    let scattered = rec.scattered_ray.direction;
+                    if scattered.y * direction.y < 0.0 {
+                        num_reflected += 1;
+                    } else {
+                        num_refracted += 1;
+                    }
+                    let pdf = material.pdf(&incoming_ray.direction, &scattered, &hit_info);
+                    assert!((pdf - rec.pdf).abs() <= 1e-3 * pdf.max(1.0));
+                    let weight =
+                        material.eval(&incoming_ray.direction, &scattered, &hit_info) * (1.0 / pdf);
+                    assert!((weight - rec.attentuation).length() < 1e-3);
+                }
+            }
+            assert!(num_reflected > 0 && num_refracted > num_reflected);
+        }
+    }
+
+    #[test]
+    fn test_rough_dielectric_pdf_integrates_to_one() {
+        let material = Dielectric {
+            roughness: 0.2.into(),
+            ..Dielectric::new(1.5)
+        };
+        let mut rng = StdRng::seed_from_u64(26);
+        for (front_face, incoming_dir) in [
+            (true, Vec3::new(0.3, -1.0, 0.2).normalize()),
+            (false, Vec3::new(0.3, 1.0, 0.2).normalize()),
+        ] {
+            let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), front_face);
+            // uniform directions on the sphere have density 1 / (4 pi)
+            let num_samples = 400_000;
+            let mut integral = 0.0;
+            for _i in 0..num_samples {
+                let z = 1.0 - 2.0 * rng.gen::<f32>();
+                let phi = 2.0 * PI * rng.gen::<f32>();
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let direction = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                integral += material.pdf(&incoming_dir, &direction, &hit_info);
+            }
+            integral *= 4.0 * PI / num_samples as f32;
+            // facets that face away from the incoming ray are not sampled, scattering into the
+            // wrong hemisphere is absorbed
+            assert!(integral > 0.95 && integral < 1.02, "{}", integral);
+        }
+    }
 }
@@ -0,0 +1,36 @@
+use crate::materials::ScatterRecord;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Ray, RayScattering};
+
+/// emits light from the front side of a surface and does not scatter any
+#[derive(Copy, Clone, Debug)]
+pub struct DiffuseLight {
+    pub emission: Vec3,
+}
+
+impl RayScattering for DiffuseLight {
+    fn sample(&self, _incoming_ray: &Ray, _hit_info: &HitInformation) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn eval(
+        &self,
+        _incoming_dir: &Vec3,
+        _scattered_dir: &Vec3,
+        _hit_info: &HitInformation,
+    ) -> Vec3 {
+        Vec3::zero()
+    }
+
+    fn pdf(&self, _incoming_dir: &Vec3, _scattered_dir: &Vec3, _hit_info: &HitInformation) -> f32 {
+        0.0
+    }
+
+    fn emitted(&self, _incoming_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        if hit_info.front_face {
+            self.emission
+        } else {
+            Vec3::zero()
+        }
+    }
+}
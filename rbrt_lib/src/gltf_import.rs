@@ -0,0 +1,598 @@
+use std::path::Path;
+
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+
+use crate::cam::Camera;
+use crate::dielectric::{Dielectric, RefractiveIndex};
+use crate::diffuse_light::DiffuseLight;
+use crate::lambertian::Lambertian;
+use crate::materials::RayScattering;
+use crate::mesh::{compute_vertex_normals, transform_mesh_data, MeshData, TriangleMesh};
+use crate::metal::Metal;
+use crate::scene::{Light, Scene};
+use crate::surface_detail::{DetailMap, SurfaceDetail};
+use crate::texture::{srgb_to_linear, ImageTexture, Texture};
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+/// perspective camera of a gltf file
+pub struct GltfCamera {
+    pub position: Vec3,
+    /// viewing direction
+    pub look_at: Vec3,
+    pub up: Vec3,
+    /// vertical field of view
+    pub yfov_rad: f32,
+}
+
+impl GltfCamera {
+    pub fn to_camera(&self, img_height_pix: u32, img_width_pix: u32) -> Camera {
+        // the sensor of Camera is 35mm wide
+        let img_height_mm = 35.0 * img_height_pix as f32 / img_width_pix as f32;
+        let focal_len_mm = 0.5 * img_height_mm / (0.5 * self.yfov_rad).tan();
+        Camera::new(
+            self.position,
+            self.look_at,
+            self.up,
+            img_height_pix,
+            img_width_pix,
+            focal_len_mm,
+        )
+    }
+}
+
+/// everything rbrt supports of a gltf file
+pub struct GltfScene {
+    pub scene: Scene,
+    /// first perspective camera of the file
+    pub camera: Option<GltfCamera>,
+}
+
+pub fn is_gltf_file(filepath: &str) -> bool {
+    let extension = Path::new(filepath)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    matches!(extension.as_deref(), Some("gltf") | Some("glb"))
+}
+
+/// linear rgb values of a gltf image, channels that are missing are zero,
+/// gray images are copied to all channels
+fn image_pixels(image: &gltf::image::Data, is_srgb: bool) -> Vec<Vec3> {
+    let (num_channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let decode = |bytes: &[u8]| {
+        let value = match bytes_per_channel {
+            1 => bytes[0] as f32 / 255.0,
+            2 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+            _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        };
+        // float images are linear already
+        if is_srgb && bytes_per_channel < 4 {
+            srgb_to_linear(value)
+        } else {
+            value
+        }
+    };
+    image
+        .pixels
+        .chunks_exact(num_channels * bytes_per_channel)
+        .map(|pixel| {
+            let channel = |idx: usize| {
+                if num_channels == 1 {
+                    decode(&pixel[0..bytes_per_channel])
+                } else if idx < num_channels {
+                    decode(&pixel[idx * bytes_per_channel..(idx + 1) * bytes_per_channel])
+                } else {
+                    0.0
+                }
+            };
+            Vec3::new(channel(0), channel(1), channel(2))
+        })
+        .collect()
+}
+
+/// image of a gltf texture with each pixel transformed by map_pixel
+fn image_texture(
+    info_texture: gltf::Texture,
+    images: &[gltf::image::Data],
+    is_srgb: bool,
+    map_pixel: impl Fn(Vec3) -> Vec3,
+) -> Box<dyn Texture> {
+    let image = &images[info_texture.source().index()];
+    let pixels = image_pixels(image, is_srgb)
+        .into_iter()
+        .map(map_pixel)
+        .collect();
+    Box::new(ImageTexture::new(image.width, image.height, pixels))
+}
+
+/// mean of the blue channel of a metallic roughness texture, which stores the metalness
+fn mean_metalness(image: &gltf::image::Data) -> f32 {
+    let pixels = image_pixels(image, false);
+    pixels.iter().map(|pixel| pixel.z).sum::<f32>() / pixels.len().max(1) as f32
+}
+
+/// texture coordinate set read by the textures of the material, meshes only keep one set,
+/// so the base color texture decides if the textures name different sets
+fn tex_coord_set(material: &gltf::Material) -> u32 {
+    let pbr = material.pbr_metallic_roughness();
+    pbr.base_color_texture()
+        .map(|info| info.tex_coord())
+        .or_else(|| {
+            pbr.metallic_roughness_texture()
+                .map(|info| info.tex_coord())
+        })
+        .or_else(|| material.normal_texture().map(|info| info.tex_coord()))
+        .unwrap_or(0)
+}
+
+/// closest rbrt material to a pbr metallic roughness material
+/// * emissive materials become lights
+/// * transmissive materials (KHR_materials_transmission) become dielectrics
+/// * metallic materials become metals, all others lambertian, a metalness texture
+///   scales the metallic factor by its mean
+fn material_from_gltf(
+    material: &gltf::Material,
+    images: &[gltf::image::Data],
+) -> Box<dyn RayScattering + Sync> {
+    let pbr = material.pbr_metallic_roughness();
+    let base_factor = pbr.base_color_factor();
+    let base_factor = Vec3::new(base_factor[0], base_factor[1], base_factor[2]);
+    let base_color = || -> Box<dyn Texture> {
+        match pbr.base_color_texture() {
+            Some(info) => image_texture(info.texture(), images, true, |pixel| pixel * base_factor),
+            None => base_factor.into(),
+        }
+    };
+
+    let emission = material.emissive_strength().unwrap_or(1.0) * {
+        let emissive = material.emissive_factor();
+        Vec3::new(emissive[0], emissive[1], emissive[2])
+    };
+    if emission.sum() > 0.0 {
+        return Box::new(DiffuseLight { emission });
+    }
+
+    let transmission = material
+        .transmission()
+        .map_or(0.0, |transmission| transmission.transmission_factor());
+    // gltf roughness is perceptual, the roughness of the lobe is its square,
+    // roughness is stored in the green channel
+    let roughness = || -> Box<dyn Texture> {
+        let roughness_factor = pbr.roughness_factor();
+        match pbr.metallic_roughness_texture() {
+            Some(info) => image_texture(info.texture(), images, false, |pixel| {
+                let roughness = (pixel.y * roughness_factor).powi(2);
+                Vec3::new(roughness, roughness, roughness)
+            }),
+            None => roughness_factor.powi(2).into(),
+        }
+    };
+    let metallic = pbr.metallic_factor()
+        * pbr.metallic_roughness_texture().map_or(1.0, |info| {
+            mean_metalness(&images[info.texture().source().index()])
+        });
+    let base_material: Box<dyn RayScattering + Sync> = if transmission >= 0.5 {
+        // the base color tints the light that travelled a unit distance through the volume
+        Box::new(Dielectric {
+            ior: RefractiveIndex::Constant(material.ior().unwrap_or(1.5)),
+            transmission_color: base_color(),
+            transmission_distance: 1.0,
+            roughness: roughness(),
+        })
+    } else if metallic >= 0.5 {
+        Box::new(Metal {
+            albedo: base_color(),
+            roughness: roughness(),
+        })
+    } else {
+        Box::new(Lambertian {
+            albedo: base_color(),
+        })
+    };
+
+    match material.normal_texture() {
+        Some(normal_texture) => Box::new(SurfaceDetail {
+            material: base_material,
+            detail: DetailMap::Normal(image_texture(
+                normal_texture.texture(),
+                images,
+                false,
+                |pixel| pixel,
+            )),
+        }),
+        None => base_material,
+    }
+}
+
+/// appends the triangles of a gltf mesh, transformed by the world matrix of its node,
+/// the winding of mirrored nodes is flipped, so that their face normals keep pointing outwards
+fn append_mesh(
+    mesh: &gltf::Mesh,
+    world: &Transform,
+    buffers: &[gltf::buffer::Data],
+    mesh_data: &mut MeshData,
+) {
+    for primitive in mesh.primitives() {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            println!("Skipping gltf primitive that does not consist of triangles!");
+            continue;
+        }
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let positions: Vec<Vec3> = match reader.read_positions() {
+            Some(positions) => positions
+                .map(|position| {
+                    world.transform_point(&Vec3::new(position[0], position[1], position[2]))
+                })
+                .collect(),
+            None => continue,
+        };
+        let normals: Option<Vec<Vec3>> = reader.read_normals().map(|normals| {
+            normals
+                .map(|normal| world.transform_normal(&Vec3::new(normal[0], normal[1], normal[2])))
+                .collect()
+        });
+        // gltf texture coordinates start at the top of the image
+        let tex_coords: Option<Vec<(f32, f32)>> = reader
+            .read_tex_coords(tex_coord_set(&primitive.material()))
+            .map(|tex_coords| {
+                tex_coords
+                    .into_f32()
+                    .map(|uv| (uv[0], 1.0 - uv[1]))
+                    .collect()
+            });
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|idx| idx as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let is_mirrored = world.determinant() < 0.0;
+        let first_triangle_idx = mesh_data.vertices.len();
+        for triangle in indices.chunks_exact(3) {
+            let corners = if is_mirrored {
+                [triangle[0], triangle[2], triangle[1]]
+            } else {
+                [triangle[0], triangle[1], triangle[2]]
+            };
+            mesh_data
+                .vertices
+                .push(corners.map(|vertex_idx| positions[vertex_idx]));
+            mesh_data.tex_coords.push(match &tex_coords {
+                Some(tex_coords) => corners.map(|vertex_idx| tex_coords[vertex_idx]),
+                None => [(0.0, 0.0); 3],
+            });
+            if let Some(normals) = &normals {
+                mesh_data
+                    .vertex_normals
+                    .push(corners.map(|vertex_idx| normals[vertex_idx]));
+            }
+            mesh_data.material_ids.push(primitive.material().index());
+        }
+        if normals.is_none() {
+            mesh_data.vertex_normals.extend(compute_vertex_normals(
+                &mesh_data.vertices[first_triangle_idx..],
+            ));
+        }
+    }
+}
+
+/// visits the node and its children with their world matrices
+fn visit_nodes(
+    node: &gltf::Node,
+    parent_world: &Transform,
+    visit: &mut impl FnMut(&gltf::Node, &Transform),
+) {
+    let local = match Transform::try_from_matrix(node.transform().matrix()) {
+        Some(local) => local,
+        None => {
+            println!("Skipping gltf node with a transform that is not invertible!");
+            return;
+        }
+    };
+    let world = local.then(parent_world);
+    visit(node, &world);
+    for child in node.children() {
+        visit_nodes(&child, &world, visit);
+    }
+}
+
+fn import(
+    filepath: &str,
+) -> (
+    gltf::Document,
+    Vec<gltf::buffer::Data>,
+    Vec<gltf::image::Data>,
+) {
+    match gltf::import(filepath) {
+        Ok(imported) => imported,
+        Err(error) => panic!("Failed to load gltf file {}: {:?}", filepath, error),
+    }
+}
+
+fn default_scene(document: &gltf::Document) -> gltf::Scene<'_> {
+    document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .expect("gltf file does not contain a scene")
+}
+
+/// meshes, materials and cameras of the default scene of a gltf file
+fn load_gltf_file(filepath: &str) -> (MeshData, Vec<Light>, Option<GltfCamera>) {
+    let (document, buffers, images) = import(filepath);
+    let mut mesh_data = MeshData {
+        vertices: vec![],
+        tex_coords: vec![],
+        vertex_normals: vec![],
+        material_ids: vec![],
+        vertex_colors: None,
+        materials: document
+            .materials()
+            .map(|material| material_from_gltf(&material, &images))
+            .collect(),
+    };
+    let mut lights = vec![];
+    let mut camera = None;
+
+    for root in default_scene(&document).nodes() {
+        visit_nodes(&root, &Transform::identity(), &mut |node, world| {
+            if let Some(mesh) = node.mesh() {
+                append_mesh(&mesh, world, &buffers, &mut mesh_data);
+            }
+            if let Some(light) = node.light() {
+                let color = light.intensity() * {
+                    let color = light.color();
+                    Vec3::new(color[0], color[1], color[2])
+                };
+                match light.kind() {
+                    Kind::Point => {}
+                    Kind::Spot { .. } => println!("Spot lights are rendered as point lights!"),
+                    Kind::Directional => {
+                        println!("Skipping directional light, it is not supported!");
+                        return;
+                    }
+                }
+                lights.push(Light {
+                    position: world.transform_point(&Vec3::zero()),
+                    radius: 0.0,
+                    color,
+                    edges: None,
+                });
+            }
+            if let (Some(node_camera), None) = (node.camera(), &camera) {
+                match node_camera.projection() {
+                    gltf::camera::Projection::Perspective(perspective) => {
+                        // gltf cameras look along -z
+                        camera = Some(GltfCamera {
+                            position: world.transform_point(&Vec3::zero()),
+                            look_at: -1.0 * world.transform_vector(&Vec3::new(0.0, 0.0, 1.0)),
+                            up: world.transform_vector(&Vec3::new(0.0, 1.0, 0.0)),
+                            yfov_rad: perspective.yfov(),
+                        })
+                    }
+                    gltf::camera::Projection::Orthographic(_) => {
+                        println!("Skipping orthographic camera, it is not supported!")
+                    }
+                }
+            }
+        });
+    }
+    println!(
+        "Successfully loaded {} triangles from file {}!",
+        mesh_data.vertices.len(),
+        filepath
+    );
+    (mesh_data, lights, camera)
+}
+
+/// Loads all meshes of a gltf or glb file, scales, rotates and translates them
+/// after applying the node transforms of the file
+pub fn load_mesh_from_gltf_file(
+    filepath: &str,
+    translation: Vec3,
+    rotation: Vec3,
+    scale: f32,
+) -> MeshData {
+    let (mut mesh_data, _lights, _camera) = load_gltf_file(filepath);
+    transform_mesh_data(
+        &mut mesh_data,
+        &Transform::from_scale_rotation_translation(scale, rotation, translation),
+    );
+    mesh_data
+}
+
+/// scene with the meshes, materials, point lights and the camera of a gltf or glb file
+pub fn load_scene_from_gltf_file(filepath: &str) -> GltfScene {
+    let (mesh_data, lights, camera) = load_gltf_file(filepath);
+    let triangle_meshes = if mesh_data.vertices.is_empty() {
+        vec![]
+    } else {
+        vec![TriangleMesh::from_mesh_data(mesh_data, None, true)]
+    };
+    GltfScene {
+        scene: Scene {
+            elements: vec![],
+            triangle_meshes,
+            lights,
+            background_color: None,
+            fog: None,
+        },
+        camera,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_gltf_file, load_mesh_from_gltf_file, load_scene_from_gltf_file};
+    use crate::materials::hit_on;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    /// one triangle scaled by mesh_scale below a translated parent node, a point light
+    /// and a camera
+    fn write_test_gltf(name: &str, mesh_scale: [f32; 3]) -> String {
+        let dir = std::env::temp_dir().join("rbrt_test_gltf");
+        std::fs::create_dir_all(&dir).unwrap();
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let bytes: Vec<u8> = positions.iter().flat_map(|x| x.to_le_bytes()).collect();
+        std::fs::write(dir.join(format!("{}.bin", name)), bytes).unwrap();
+        let gltf = r#"{
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": {"KHR_lights_punctual": {"lights": [
+                {"type": "point", "color": [1.0, 0.5, 0.25], "intensity": 4.0}
+            ]}},
+            "scene": 0,
+            "scenes": [{"nodes": [0, 2, 3]}],
+            "nodes": [
+                {"translation": [0.0, 0.0, -5.0], "children": [1]},
+                {"mesh": 0, "scale": MESH_SCALE},
+                {"translation": [0.0, 3.0, 0.0], "extensions": {"KHR_lights_punctual": {"light": 0}}},
+                {"camera": 0, "translation": [0.0, 0.0, 1.0]}
+            ],
+            "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "znear": 0.1}}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]}],
+            "materials": [{"pbrMetallicRoughness": {
+                "baseColorFactor": [0.9, 0.8, 0.7, 1.0], "metallicFactor": 1.0, "roughnessFactor": 0.5
+            }}],
+            "buffers": [{"uri": "NAME.bin", "byteLength": 36}],
+            "bufferViews": [{"buffer": 0, "byteOffset": 0, "byteLength": 36}],
+            "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]}]
+        }"#
+        .replace("NAME", name)
+        .replace("MESH_SCALE", &format!("{:?}", mesh_scale));
+        let filepath = dir.join(format!("{}.gltf", name));
+        std::fs::write(&filepath, gltf).unwrap();
+        filepath.to_str().unwrap().to_string()
+    }
+
+    /// triangle with two sets of texture coordinates and a metallic roughness texture,
+    /// which reads the second set and is a quarter metallic on average
+    fn write_textured_gltf() -> String {
+        let dir = std::env::temp_dir().join("rbrt_test_gltf");
+        std::fs::create_dir_all(&dir).unwrap();
+        let floats: [f32; 21] = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, // positions
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, // first set of texture coordinates
+            0.25, 0.5, 0.75, 0.5, 0.5, 0.0, // second set of texture coordinates
+        ];
+        let bytes: Vec<u8> = floats.iter().flat_map(|x| x.to_le_bytes()).collect();
+        std::fs::write(dir.join("textured.bin"), bytes).unwrap();
+        let metalness = image::RgbImage::from_fn(2, 1, |x, _y| image::Rgb([0, 0, 128 * x as u8]));
+        metalness.save(dir.join("metalness.png")).unwrap();
+        let gltf = r#"{
+            "asset": {"version": "2.0"},
+            "scene": 0,
+            "scenes": [{"nodes": [0]}],
+            "nodes": [{"mesh": 0}],
+            "meshes": [{"primitives": [{
+                "attributes": {"POSITION": 0, "TEXCOORD_0": 1, "TEXCOORD_1": 2}, "material": 0
+            }]}],
+            "materials": [{"pbrMetallicRoughness": {
+                "metallicFactor": 1.0, "roughnessFactor": 0.0,
+                "metallicRoughnessTexture": {"index": 0, "texCoord": 1}
+            }}],
+            "textures": [{"source": 0}],
+            "images": [{"uri": "metalness.png"}],
+            "buffers": [{"uri": "textured.bin", "byteLength": 84}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 24},
+                {"buffer": 0, "byteOffset": 60, "byteLength": 24}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]},
+                {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2"},
+                {"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2"}
+            ]
+        }"#;
+        let filepath = dir.join("textured.gltf");
+        std::fs::write(&filepath, gltf).unwrap();
+        filepath.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_load_gltf_scene() {
+        let gltf_scene = load_scene_from_gltf_file(&write_test_gltf("triangle", [2.0, 2.0, 2.0]));
+
+        let scene = gltf_scene.scene;
+        assert_eq!(scene.triangle_meshes.len(), 1);
+        let mesh = &scene.triangle_meshes[0];
+        // the material of the file, plus the default material
+        assert_eq!(mesh.materials.len(), 2);
+        assert_eq!(mesh.material_indices[0], 0);
+        // node transforms are applied: scaled by 2, then moved to z = -5
+        let ray = Ray::new(Vec3::new(1.5, 0.4, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = mesh.intersect_with_ray(&ray, 0.001, 100.0).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 5.0).abs() < 1e-5);
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.lights[0].position, Vec3::new(0.0, 3.0, 0.0));
+        assert_eq!(scene.lights[0].color, Vec3::new(4.0, 2.0, 1.0));
+        assert!(scene.lights[0].is_point_light());
+
+        let camera = gltf_scene.camera.unwrap();
+        assert_eq!(camera.position, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(camera.look_at, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(camera.up, Vec3::new(0.0, 1.0, 0.0));
+        let cam = camera.to_camera(600, 800);
+        // field of view of the rays through the top and bottom of the image
+        let yfov_rad = 2.0 * (0.5 * cam.img_height_mm / cam.focal_len_mm).atan();
+        assert!((yfov_rad - 0.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_load_gltf_mesh_with_blueprint_transform() {
+        let mesh_data = load_mesh_from_gltf_file(
+            &write_test_gltf("blueprint_transform", [2.0, 2.0, 2.0]),
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::zero(),
+            0.5,
+        );
+        assert_eq!(mesh_data.vertices.len(), 1);
+        assert_eq!(mesh_data.vertices[0][0], Vec3::new(0.0, 0.0, 2.5));
+        assert_eq!(mesh_data.vertices[0][1], Vec3::new(1.0, 0.0, 2.5));
+        assert_eq!(mesh_data.material_ids, vec![Some(0)]);
+    }
+
+    #[test]
+    fn test_mirrored_gltf_nodes_keep_their_winding() {
+        let gltf_scene = load_scene_from_gltf_file(&write_test_gltf("mirrored", [-1.0, 1.0, 1.0]));
+        let mesh = &gltf_scene.scene.triangle_meshes[0];
+        // the triangle now spans negative x, its front still faces +z
+        let ray = Ray::new(Vec3::new(-0.25, 0.25, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = mesh.intersect_with_ray(&ray, 0.001, 100.0).unwrap();
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+        assert!((hit_info.shading_normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+        assert!(hit_info.front_face);
+    }
+
+    #[test]
+    fn test_gltf_metalness_texture_and_tex_coord_set() {
+        let (mesh_data, _lights, _camera) = load_gltf_file(&write_textured_gltf());
+        assert_eq!(
+            mesh_data.tex_coords,
+            vec![[(0.25, 0.5), (0.75, 0.5), (0.5, 1.0)]]
+        );
+
+        // the metallic factor of 1.0 is scaled down, the material is not a smooth mirror
+        let material = &mesh_data.materials[0];
+        let hit_info = hit_on(material.as_ref(), Vec3::new(0.0, 1.0, 0.0), true);
+        let incoming_ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+        let rec = material.sample(&incoming_ray, &hit_info).unwrap();
+        assert!(!rec.is_specular);
+    }
+}
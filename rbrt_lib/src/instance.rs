@@ -0,0 +1,554 @@
+use crate::aabbox::BoundingBox;
+use crate::csg::SolidInterval;
+use crate::mesh::TriangleMesh;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+/// the ray in the coordinates of the object with a unit direction, and the factor by which
+/// distances along it are stretched
+fn object_ray(transform: &Transform, ray: &Ray) -> (Ray, f32) {
+    let object_direction = transform.inverse_transform_vector(&ray.direction);
+    let stretch = object_direction.length() / ray.direction.length();
+    let object_ray = Ray {
+        origin: transform.inverse_transform_point(&ray.origin),
+        direction: object_direction.normalize(),
+        ..*ray
+    };
+    (object_ray, stretch)
+}
+
+/// moves a hit of the object ray back into world coordinates
+fn hit_to_world<'a>(
+    hit_info: HitInformation<'a>,
+    transform: &Transform,
+    ray: &Ray,
+) -> HitInformation<'a> {
+    let hit_point = transform.transform_point(&hit_info.hit_point);
+    HitInformation {
+        hit_point,
+        // the inverse transpose keeps the side of the surface the ray hit, front_face stays valid
+        geometric_normal: transform.transform_normal(&hit_info.geometric_normal),
+        shading_normal: transform.transform_normal(&hit_info.shading_normal),
+        tangent: transform.transform_vector(&hit_info.tangent),
+        bitangent: transform.transform_vector(&hit_info.bitangent),
+        dist_from_ray_orig: (ray.origin - hit_point).length(),
+        ..hit_info
+    }
+}
+
+/// intersects the object with the ray after moving the ray into the coordinates of the
+/// object, the hit is moved back into world coordinates
+pub fn intersect_transformed<'a>(
+    object: &'a dyn Intersectable,
+    transform: &Transform,
+    ray: &Ray,
+    min_dist: f32,
+    max_dist: f32,
+) -> Option<HitInformation<'a>> {
+    let (object_ray, stretch) = object_ray(transform, ray);
+    let hit_info =
+        object.intersect_with_ray(&object_ray, stretch * min_dist, stretch * max_dist)?;
+    Some(hit_to_world(hit_info, transform, ray))
+}
+
+/// bounding box of the transformed corners of a bounding box
+pub fn transform_bbox(bbox: &BoundingBox, transform: &Transform) -> BoundingBox {
+    let mut lower_bound = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut upper_bound = Vec3::new(-f32::MAX, -f32::MAX, -f32::MAX);
+    for corner_idx in 0..8 {
+        let corner = Vec3::new(
+            if corner_idx & 1 == 0 {
+                bbox.lower_bound.x
+            } else {
+                bbox.upper_bound.x
+            },
+            if corner_idx & 2 == 0 {
+                bbox.lower_bound.y
+            } else {
+                bbox.upper_bound.y
+            },
+            if corner_idx & 4 == 0 {
+                bbox.lower_bound.z
+            } else {
+                bbox.upper_bound.z
+            },
+        );
+        let corner = transform.transform_point(&corner);
+        lower_bound = Vec3::new(
+            lower_bound.x.min(corner.x),
+            lower_bound.y.min(corner.y),
+            lower_bound.z.min(corner.z),
+        );
+        upper_bound = Vec3::new(
+            upper_bound.x.max(corner.x),
+            upper_bound.y.max(corner.y),
+            upper_bound.z.max(corner.z),
+        );
+    }
+    BoundingBox::new(lower_bound, upper_bound)
+}
+
+/// scene object placed with an affine transform, e.g. a sphere stretched into an ellipsoid
+pub struct Transformed {
+    pub object: Box<dyn Intersectable + Sync>,
+    pub transform: Transform,
+}
+
+impl Intersectable for Transformed {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        intersect_transformed(&*self.object, &self.transform, ray, min_dist, max_dist)
+    }
+
+    fn solid_intervals(&self, ray: &Ray, min_dist: f32) -> Vec<SolidInterval<'_>> {
+        let (object_ray, stretch) = object_ray(&self.transform, ray);
+        self.object
+            .solid_intervals(&object_ray, stretch * min_dist)
+            .into_iter()
+            .map(|interval| SolidInterval {
+                entry: interval
+                    .entry
+                    .map(|hit_info| hit_to_world(hit_info, &self.transform, ray)),
+                exit: interval
+                    .exit
+                    .map(|hit_info| hit_to_world(hit_info, &self.transform, ray)),
+            })
+            .collect()
+    }
+}
+
+/// most instances in a leaf of the instance hierarchy
+const MAX_INSTANCES_PER_LEAF: usize = 4;
+
+/// node of the bounding volume hierarchy over the instances, the nodes are stored depth first,
+/// so the first child of an inner node directly follows it
+enum InstanceNode {
+    /// holds the instances instance_order[start..end]
+    Leaf {
+        bbox: BoundingBox,
+        start: usize,
+        end: usize,
+    },
+    /// children split along axis, the second child holds the upper instances
+    Inner {
+        bbox: BoundingBox,
+        axis: usize,
+        second_child: usize,
+    },
+}
+
+/// appends the hierarchy over the instances in order to nodes, order is sorted in place and
+/// starts at offset in the complete instance order
+fn build_instance_nodes(
+    bboxes: &[BoundingBox],
+    order: &mut [usize],
+    offset: usize,
+    nodes: &mut Vec<InstanceNode>,
+) {
+    let empty = BoundingBox::new(
+        Vec3::new(f32::MAX, f32::MAX, f32::MAX),
+        Vec3::new(-f32::MAX, -f32::MAX, -f32::MAX),
+    );
+    let bbox = order.iter().fold(empty, |bbox, instance_idx| {
+        bbox.enclosing(&bboxes[*instance_idx])
+    });
+    if order.len() <= MAX_INSTANCES_PER_LEAF {
+        nodes.push(InstanceNode::Leaf {
+            bbox,
+            start: offset,
+            end: offset + order.len(),
+        });
+        return;
+    }
+
+    // median split of the box centers along the longest side of the node
+    let extent = bbox.upper_bound - bbox.lower_bound;
+    let axis = (0..3)
+        .max_by(|a, b| extent.axis(*a).total_cmp(&extent.axis(*b)))
+        .unwrap_or(0);
+    let center = |instance_idx: &usize| {
+        bboxes[*instance_idx].lower_bound.axis(axis) + bboxes[*instance_idx].upper_bound.axis(axis)
+    };
+    order.sort_by(|a, b| center(a).total_cmp(&center(b)));
+
+    let node_idx = nodes.len();
+    nodes.push(InstanceNode::Inner {
+        bbox,
+        axis,
+        second_child: 0,
+    });
+    let mid = order.len() / 2;
+    let (lower, upper) = order.split_at_mut(mid);
+    build_instance_nodes(bboxes, lower, offset, nodes);
+    let upper_idx = nodes.len();
+    if let InstanceNode::Inner { second_child, .. } = &mut nodes[node_idx] {
+        *second_child = upper_idx;
+    }
+    build_instance_nodes(bboxes, upper, offset + mid, nodes);
+}
+
+/// placement of a copy of an instanced mesh
+pub struct MeshInstance {
+    /// from the coordinates of the mesh file to world coordinates
+    pub transform: Transform,
+    /// replaces the materials of the mesh for this copy
+    pub material_override: Option<Box<dyn RayScattering + Sync>>,
+}
+
+/// mesh that is loaded once in object coordinates and placed many times in the scene,
+/// all copies share its triangles and acceleration structure, rays find the copies through
+/// a bounding volume hierarchy over their boxes
+pub struct InstancedMesh {
+    pub mesh: TriangleMesh,
+    pub instances: Vec<MeshInstance>,
+    /// world space bounding box of each instance
+    bboxes: Vec<BoundingBox>,
+    nodes: Vec<InstanceNode>,
+    /// indices of the instances in the order of the leaves of the hierarchy
+    instance_order: Vec<usize>,
+}
+
+impl InstancedMesh {
+    pub fn new(mesh: TriangleMesh, instances: Vec<MeshInstance>) -> InstancedMesh {
+        let bboxes: Vec<BoundingBox> = instances
+            .iter()
+            .map(|instance| transform_bbox(&mesh.bbox, &instance.transform))
+            .collect();
+        let mut instance_order: Vec<usize> = (0..instances.len()).collect();
+        let mut nodes = vec![];
+        build_instance_nodes(&bboxes, &mut instance_order, 0, &mut nodes);
+        InstancedMesh {
+            mesh,
+            instances,
+            bboxes,
+            nodes,
+            instance_order,
+        }
+    }
+
+    fn intersect_instance(
+        &self,
+        instance_idx: usize,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let instance = &self.instances[instance_idx];
+        let hit_info =
+            intersect_transformed(&self.mesh, &instance.transform, ray, min_dist, max_dist)?;
+        Some(HitInformation {
+            hit_material: match &instance.material_override {
+                Some(material) => &**material,
+                None => hit_info.hit_material,
+            },
+            ..hit_info
+        })
+    }
+}
+
+impl Intersectable for InstancedMesh {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let mut closest_hit_rec = None;
+        let mut closest_so_far = max_dist;
+        // boxes that the ray enters behind the closest hit so far are skipped
+        let direction_length = ray.direction.length();
+        let is_hit = |bbox: &BoundingBox, closest_so_far: f32| {
+            bbox.hit_range(ray)
+                .is_some_and(|(entry, _exit)| entry * direction_length < closest_so_far)
+        };
+
+        let mut stack = vec![0];
+        while let Some(node_idx) = stack.pop() {
+            match &self.nodes[node_idx] {
+                InstanceNode::Leaf { bbox, start, end } => {
+                    if !is_hit(bbox, closest_so_far) {
+                        continue;
+                    }
+                    for instance_idx in &self.instance_order[*start..*end] {
+                        if !is_hit(&self.bboxes[*instance_idx], closest_so_far) {
+                            continue;
+                        }
+                        if let Some(hit_info) =
+                            self.intersect_instance(*instance_idx, ray, min_dist, closest_so_far)
+                        {
+                            closest_so_far = hit_info.dist_from_ray_orig;
+                            closest_hit_rec = Some(hit_info);
+                        }
+                    }
+                }
+                InstanceNode::Inner {
+                    bbox,
+                    axis,
+                    second_child,
+                } => {
+                    if !is_hit(bbox, closest_so_far) {
+                        continue;
+                    }
+                    // the child the ray reaches first is visited first
+                    if ray.direction.axis(*axis) < 0.0 {
+                        stack.push(node_idx + 1);
+                        stack.push(*second_child);
+                    } else {
+                        stack.push(*second_child);
+                        stack.push(node_idx + 1);
+                    }
+                }
+            }
+        }
+        closest_hit_rec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InstancedMesh, MeshInstance, Transformed};
+    use crate::lambertian::Lambertian;
+    use crate::mesh::TriangleMesh;
+    use crate::metal::Metal;
+    use crate::sphere::Sphere;
+    use crate::transform::Transform;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray, RayScattering};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn write_quad() -> String {
+        let filepath = std::env::temp_dir().join("rbrt_test_instance.obj");
+        std::fs::write(
+            &filepath,
+            "v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             f 1/1 2/2 3/3\nf 1/1 3/3 4/4\n",
+        )
+        .unwrap();
+        filepath.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_instance_matches_transformed_mesh() {
+        let filepath = write_quad();
+        let translation = Vec3::new(0.5, -0.3, -6.0);
+        let rotation = Vec3::new(0.3, 0.4, -0.2);
+        let scale = 2.5;
+        let new_mesh = |translation, rotation, scale| {
+            TriangleMesh::new(
+                &filepath,
+                translation,
+                rotation,
+                scale,
+                Some(Box::new(Lambertian { albedo: 0.5.into() })),
+                true,
+            )
+        };
+        let transformed_mesh = new_mesh(translation, rotation, scale);
+        let instanced_mesh = InstancedMesh::new(
+            new_mesh(Vec3::zero(), Vec3::zero(), 1.0),
+            vec![
+                // behind the camera
+                MeshInstance {
+                    transform: Transform::from_scale_rotation_translation(
+                        1.0,
+                        Vec3::zero(),
+                        Vec3::new(0.0, 0.0, 5.0),
+                    ),
+                    material_override: None,
+                },
+                MeshInstance {
+                    transform: Transform::from_scale_rotation_translation(
+                        scale,
+                        rotation,
+                        translation,
+                    ),
+                    material_override: None,
+                },
+            ],
+        );
+
+        for direction in [
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.1, 0.05, -1.0).normalize(),
+            Vec3::new(-0.2, 0.15, -1.0).normalize(),
+        ] {
+            let ray = Ray::new(Vec3::zero(), direction);
+            let expected = transformed_mesh
+                .intersect_with_ray(&ray, 1e-3, f32::MAX)
+                .unwrap();
+            let hit_info = instanced_mesh
+                .intersect_with_ray(&ray, 1e-3, f32::MAX)
+                .unwrap();
+            assert!((hit_info.hit_point - expected.hit_point).length() < 1e-4);
+            assert!((hit_info.geometric_normal - expected.geometric_normal).length() < 1e-4);
+            assert!((hit_info.tangent - expected.tangent).length() < 1e-4);
+            assert!((hit_info.dist_from_ray_orig - expected.dist_from_ray_orig).abs() < 1e-4);
+            assert!((hit_info.uv.0 - expected.uv.0).abs() < 1e-4);
+            assert!((hit_info.uv.1 - expected.uv.1).abs() < 1e-4);
+        }
+        // the scaled distances respect max_dist in world units
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let dist = transformed_mesh
+            .intersect_with_ray(&ray, 1e-3, f32::MAX)
+            .unwrap()
+            .dist_from_ray_orig;
+        assert!(instanced_mesh
+            .intersect_with_ray(&ray, 1e-3, dist - 1e-2)
+            .is_none());
+        assert!(instanced_mesh
+            .intersect_with_ray(&ray, dist + 1e-2, f32::MAX)
+            .is_none());
+    }
+
+    #[test]
+    fn test_instance_hierarchy_matches_linear_search() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let mesh = TriangleMesh::new(&write_quad(), Vec3::zero(), Vec3::zero(), 1.0, None, false);
+        let mut random_vec = |range: std::ops::Range<f32>| {
+            Vec3::new(
+                rng.gen_range(range.clone()),
+                rng.gen_range(range.clone()),
+                rng.gen_range(range),
+            )
+        };
+        // a forest of 500 copies, every third one with its own material
+        let instances: Vec<MeshInstance> = (0..500)
+            .map(|instance_idx| MeshInstance {
+                transform: Transform::from_scale_rotation_translation(
+                    0.5 + random_vec(0.0..1.0).x,
+                    random_vec(-3.0..3.0),
+                    random_vec(-20.0..20.0),
+                ),
+                material_override: (instance_idx % 3 == 0).then(|| {
+                    Box::new(Lambertian { albedo: 0.5.into() }) as Box<dyn RayScattering + Sync>
+                }),
+            })
+            .collect();
+        let instanced_mesh = InstancedMesh::new(mesh, instances);
+
+        let mut num_hits = 0;
+        for _i in 0..2000 {
+            let ray = Ray::new(random_vec(-30.0..30.0), random_vec(-1.0..1.0));
+            let (min_dist, max_dist) = (1e-3, 5.0 + 50.0 * random_vec(0.0..1.0).x);
+            let mut expected = None;
+            let mut closest_so_far = max_dist;
+            for instance_idx in 0..instanced_mesh.instances.len() {
+                if let Some(hit_info) =
+                    instanced_mesh.intersect_instance(instance_idx, &ray, min_dist, closest_so_far)
+                {
+                    closest_so_far = hit_info.dist_from_ray_orig;
+                    expected = Some(hit_info);
+                }
+            }
+
+            let hit_info = instanced_mesh.intersect_with_ray(&ray, min_dist, max_dist);
+            match (hit_info, expected) {
+                (Some(hit_info), Some(expected)) => {
+                    num_hits += 1;
+                    assert_eq!(hit_info.dist_from_ray_orig, expected.dist_from_ray_orig);
+                    assert_eq!(
+                        hit_info.hit_material as *const _ as *const u8,
+                        expected.hit_material as *const _ as *const u8
+                    );
+                }
+                (None, None) => (),
+                (hit_info, expected) => panic!(
+                    "{:?}: {:?} instead of {:?}",
+                    ray,
+                    hit_info.map(|hit_info| hit_info.dist_from_ray_orig),
+                    expected.map(|expected| expected.dist_from_ray_orig)
+                ),
+            }
+        }
+        // the misses alone would not test much
+        assert!(num_hits > 200, "{}", num_hits);
+    }
+
+    #[test]
+    fn test_closest_instance_and_material_override() {
+        let mesh = TriangleMesh::new(&write_quad(), Vec3::zero(), Vec3::zero(), 1.0, None, false);
+        let instanced_mesh = InstancedMesh::new(
+            mesh,
+            vec![
+                MeshInstance {
+                    transform: Transform::from_scale_rotation_translation(
+                        1.0,
+                        Vec3::zero(),
+                        Vec3::new(0.0, 0.0, -8.0),
+                    ),
+                    material_override: None,
+                },
+                MeshInstance {
+                    transform: Transform::from_scale_rotation_translation(
+                        0.5,
+                        Vec3::zero(),
+                        Vec3::new(0.0, 0.0, -4.0),
+                    ),
+                    material_override: Some(Box::new(Metal {
+                        albedo: 0.9.into(),
+                        roughness: 0.0.into(),
+                    })),
+                },
+            ],
+        );
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = instanced_mesh
+            .intersect_with_ray(&ray, 1e-3, f32::MAX)
+            .unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-5);
+        let overridden: *const u8 = &**instanced_mesh.instances[1]
+            .material_override
+            .as_ref()
+            .unwrap() as *const _ as *const u8;
+        assert_eq!(hit_info.hit_material as *const _ as *const u8, overridden);
+
+        // the smaller copy does not cover the ray anymore, the far one does
+        let ray = Ray::new(Vec3::new(0.75, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = instanced_mesh
+            .intersect_with_ray(&ray, 1e-3, f32::MAX)
+            .unwrap();
+        assert!((hit_info.dist_from_ray_orig - 8.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_ellipsoid_from_transformed_sphere() {
+        // unit sphere stretched to an ellipsoid with half axes 2, 1 and 0.5 at z = -10
+        let ellipsoid = Transformed {
+            object: Box::new(Sphere {
+                center: Vec3::zero(),
+                radius: 1.0,
+                material: Box::new(Lambertian { albedo: 0.5.into() }),
+            }),
+            transform: Transform::scale(Vec3::new(2.0, 1.0, 0.5))
+                .then(&Transform::translation(Vec3::new(0.0, 0.0, -10.0))),
+        };
+        let hit_info = ellipsoid
+            .intersect_with_ray(
+                &Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0)),
+                1e-3,
+                f32::MAX,
+            )
+            .unwrap();
+        assert!((hit_info.dist_from_ray_orig - 9.5).abs() < 1e-5);
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+
+        // along x the ellipsoid reaches out to 2
+        let ray = Ray::new(Vec3::new(10.0, 0.0, -10.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit_info = ellipsoid.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.hit_point - Vec3::new(2.0, 0.0, -10.0)).length() < 1e-5);
+        assert!(ellipsoid.intersect_with_ray(&ray, 1e-3, 7.9).is_none());
+
+        // the normal of the point (sqrt(2), 0, -10 + sqrt(0.125)) is (x / 4, 0, z / 0.25)
+        let point = Vec3::new(2.0f32.sqrt(), 0.0, -10.0 + 0.125f32.sqrt());
+        let ray = Ray::new(point + Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = ellipsoid.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        let expected_normal = Vec3::new(point.x / 4.0, 0.0, 0.125f32.sqrt() / 0.25).normalize();
+        assert!((hit_info.geometric_normal - expected_normal).length() < 1e-4);
+    }
+}
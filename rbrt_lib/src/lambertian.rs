@@ -1,25 +1,87 @@
-use crate::materials::random_point_in_unit_sphere;
+use std::f32::consts::PI;
+
+use crate::materials::{facing_normal, local_to_world, random_cosine_direction, ScatterRecord};
+use crate::texture::Texture;
 use crate::vec3::Vec3;
 use crate::{HitInformation, Ray, RayScattering};
 
-#[derive(Copy, Clone, Debug)]
 pub struct Lambertian {
-    pub albedo: Vec3,
+    pub albedo: Box<dyn Texture>,
 }
 
 impl RayScattering for Lambertian {
-    fn scatter(
-        &self,
-        _incoming_ray: &Ray,
-        hit_info: &HitInformation,
-        attentuation: &mut Vec3,
-        scattered_ray: &mut Ray,
-    ) -> bool {
-        let scattered_ray_target_point =
-            hit_info.hit_point + hit_info.hit_normal.normalize() + random_point_in_unit_sphere();
-        scattered_ray.direction = (scattered_ray_target_point - hit_info.hit_point).normalize();
-        scattered_ray.origin = hit_info.hit_point;
-        *attentuation = self.albedo;
-        true
+    /// cosine weighted sampling of the hemisphere, so that the pdf cancels out the bsdf
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        let normal = facing_normal(hit_info);
+        let direction = local_to_world(&random_cosine_direction(), &normal).normalize();
+        Some(ScatterRecord {
+            scattered_ray: Ray {
+                origin: hit_info.hit_point,
+                direction,
+                wavelength_nm: incoming_ray.wavelength_nm,
+            },
+            attentuation: self.albedo.value(hit_info.uv, &hit_info.hit_point),
+            pdf: direction.dot(&normal).max(0.0) / PI,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, _incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        let normal = facing_normal(hit_info);
+        let cosine = scattered_dir.normalize().dot(&normal);
+        if cosine > 0.0 {
+            self.albedo.value(hit_info.uv, &hit_info.hit_point) * (cosine / PI)
+        } else {
+            Vec3::zero()
+        }
+    }
+
+    fn pdf(&self, _incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+        let normal = facing_normal(hit_info);
+        scattered_dir.normalize().dot(&normal).max(0.0) / PI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lambertian;
+    use crate::materials::hit_on;
+    use crate::vec3::Vec3;
+    use crate::{Ray, RayScattering};
+
+    #[test]
+    fn test_lambertian_sample_matches_eval_and_pdf() {
+        let material = Lambertian {
+            albedo: Vec3::new(0.5, 0.2, 0.8).into(),
+        };
+        let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), true);
+        let incoming_ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+
+        for _i in 0..100 {
+            let rec = material.sample(&incoming_ray, &hit_info).unwrap();
+            let direction = rec.scattered_ray.direction;
+            assert!(direction.y >= 0.0);
+            assert!(!rec.is_specular);
+
+            let pdf = material.pdf(&incoming_ray.direction, &direction, &hit_info);
+            assert!((pdf - rec.pdf).abs() < 1e-5);
+            if pdf > 1e-3 {
+                let weight =
+                    material.eval(&incoming_ray.direction, &direction, &hit_info) * (1.0 / pdf);
+                assert!((weight - Vec3::new(0.5, 0.2, 0.8)).length() < 1e-4);
+                assert_eq!(rec.attentuation, Vec3::new(0.5, 0.2, 0.8));
+            }
+        }
+
+        // no light is scattered into the surface
+        let below = Vec3::new(0.0, -1.0, 0.0);
+        assert_eq!(
+            material.eval(&incoming_ray.direction, &below, &hit_info),
+            Vec3::zero()
+        );
+        assert_eq!(
+            material.pdf(&incoming_ray.direction, &below, &hit_info),
+            0.0
+        );
     }
 }
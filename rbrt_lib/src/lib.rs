@@ -3,25 +3,54 @@ extern crate rand;
 extern crate rayon;
 
 pub mod aabbox;
+pub mod alpha;
 pub mod blueprints;
 pub mod cam;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
 pub mod dielectric;
+pub mod diffuse_light;
+pub mod gltf_import;
+pub mod instance;
 pub mod lambertian;
 pub mod materials;
+pub mod medium;
 pub mod mesh;
 pub mod metal;
+pub mod mtl;
+pub mod perlin;
+pub mod plane;
+pub mod ply;
+pub mod polynomial;
+pub mod procedural_texture;
+pub mod quad;
 pub mod ray;
 pub mod scene;
+pub mod sdf;
+pub mod sidedness;
+pub mod spectrum;
 pub mod sphere;
+pub mod stl;
+pub mod subsurface;
+pub mod surface_detail;
+pub mod texture;
+pub mod torus;
+pub mod transform;
 pub mod triangle;
 pub mod vec3;
 pub mod vec3_avx;
 pub mod vec3_sse;
+pub mod volume;
 
 use cam::Camera;
+use csg::SolidInterval;
 use image::Rgb;
 use materials::RayScattering;
+use medium::{Medium, MediumSample};
 use ray::Ray;
+use ray::{hit_point_error, offset_ray_origin};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use scene::Scene;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -30,51 +59,313 @@ use vec3::Vec3;
 #[derive(Copy, Clone)]
 pub struct HitInformation<'a> {
     pub hit_point: Vec3,
-    pub hit_normal: Vec3,
+    /// unit normal of the geometry, pointing out of the object, or towards the side from which
+    /// the corners of triangles and quads are counter clockwise
+    pub geometric_normal: Vec3,
+    /// unit normal used for shading, e.g. interpolated from vertex normals, it is not flipped
+    /// towards the ray and may deviate from the geometric normal
+    pub shading_normal: Vec3,
+    /// the ray arrived on the side the geometric normal points to
+    pub front_face: bool,
+    /// texture coordinates of the hit point
+    pub uv: (f32, f32),
+    /// derivatives of the hit point w.r.t. the texture coordinates u and v,
+    /// they span the tangent frame used by normal and bump maps
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
     pub hit_material: &'a dyn RayScattering,
+    /// color interpolated from the vertex colors of a mesh, it tints the material
+    pub vertex_color: Option<Vec3>,
     pub dist_from_ray_orig: f32,
 }
 
-pub trait Intersectable: Sync {
-    fn intersect_with_ray(&self, ray: &Ray, min_dist: f32, max_dist: f32)
-        -> Option<HitInformation>;
+impl HitInformation<'_> {
+    /// origin for rays that leave the surface along direction, it lies just outside of the
+    /// error bounds of the hit point, on the side of the surface the direction points to
+    pub fn spawn_origin(&self, direction: &Vec3) -> Vec3 {
+        offset_ray_origin(
+            &self.hit_point,
+            &hit_point_error(&self.hit_point, self.dist_from_ray_orig),
+            &self.geometric_normal,
+            direction,
+        )
+    }
 }
 
-pub fn colorize(ray: &Ray, scene: &Scene, bg_color: &Vec3, current_depth: u32) -> Vec3 {
-    let min_dist = 0.001;
-    let max_dist = 2000.0;
-
-    let hit_opt = scene.hit(ray, min_dist, max_dist);
-
-    match hit_opt {
-        Some(closest_hit_info) => {
-            let mut scattered_ray = Ray::zero();
-            let mut attentuation = Vec3::zero();
-
-            if current_depth > 0
-                && closest_hit_info.hit_material.scatter(
-                    ray,
-                    &closest_hit_info,
-                    &mut attentuation,
-                    &mut scattered_ray,
-                )
-            {
-                attentuation * colorize(&scattered_ray, scene, bg_color, current_depth - 1)
-            } else {
-                // ray was completely attentuated
-                Vec3::zero()
+pub trait Intersectable: Sync {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>>;
+
+    /// spans of the ray inside of the object after min_dist, in ascending order,
+    /// found by stepping through all crossings of the surface
+    fn solid_intervals(&self, ray: &Ray, min_dist: f32) -> Vec<SolidInterval<'_>> {
+        let mut crossings = vec![];
+        let mut next_crossing = self.intersect_with_ray(ray, min_dist, f32::MAX);
+        while let Some(hit_info) = next_crossing {
+            if crossings.len() == csg::MAX_CROSSINGS {
+                break;
             }
+            next_crossing = csg::hit_behind(self, ray, &hit_info, f32::MAX);
+            crossings.push(hit_info);
         }
+        csg::intervals_from_crossings(crossings, ray)
+    }
+}
+
+/// power heuristic (beta = 2) weight of a sample drawn with pdf_a,
+/// when it could also have been drawn with pdf_b,
+/// see Veach, "Robust Monte Carlo Methods for Light Transport Simulation"
+pub fn power_heuristic(pdf_a: f32, pdf_b: f32) -> f32 {
+    let a_sq = pdf_a * pdf_a;
+    let b_sq = pdf_b * pdf_b;
+    if a_sq + b_sq > 0.0 {
+        a_sq / (a_sq + b_sq)
+    } else {
+        0.0
+    }
+}
+
+/// next event estimation: light arriving directly from a randomly chosen light source
+/// and scattered towards the origin of the ray, weighted against bsdf sampling by MIS
+/// scatter returns the bsdf (or phase function) value and pdf of a direction at point,
+/// the light is attenuated by the media between the point and the light
+/// shadow rays from surfaces start at the spawn origin of the hit
+fn sample_direct_light<F>(
+    point: &Vec3,
+    surface: Option<&HitInformation>,
+    ray: &Ray,
+    scene: &Scene,
+    medium: Option<&dyn Medium>,
+    scatter: F,
+) -> Vec3
+where
+    F: Fn(&Vec3) -> (Vec3, f32),
+{
+    if scene.lights.is_empty() {
+        return Vec3::zero();
+    }
+    let light_idx =
+        ((rand::random::<f32>() * scene.lights.len() as f32) as usize).min(scene.lights.len() - 1);
+    let light = &scene.lights[light_idx];
+
+    let light_sample = match light.sample(point) {
+        Some(light_sample) => light_sample,
+        None => return Vec3::zero(),
+    };
+
+    let (bsdf, bsdf_pdf) = scatter(&light_sample.direction);
+    if bsdf.sum() <= 0.0 {
+        return Vec3::zero();
+    }
+
+    let shadow_ray = Ray {
+        origin: surface.map_or(*point, |hit_info| {
+            hit_info.spawn_origin(&light_sample.direction)
+        }),
+        direction: light_sample.direction,
+        wavelength_nm: ray.wavelength_nm,
+    };
+    let transmittance = scene.transmittance(
+        &shadow_ray,
+        0.0,
+        light_sample.distance * (1.0 - 1e-4),
+        medium,
+    );
+    if transmittance.sum() <= 0.0 {
+        return Vec3::zero();
+    }
+
+    let num_lights = scene.lights.len() as f32;
+    if light.is_point_light() {
+        // delta light, cannot be hit by bsdf sampling
+        return num_lights * transmittance * bsdf * light_sample.radiance;
+    }
+    let light_pdf = light_sample.pdf / num_lights;
+    power_heuristic(light_pdf, bsdf_pdf) / light_pdf * transmittance * bsdf * light_sample.radiance
+}
+
+fn background(ray: &Ray, scene: &Scene, bg_color: &Vec3) -> Vec3 {
+    match scene.background_color {
+        Some(color) => color,
         None => {
-            let t = 0.5 * (ray.direction.y + 1.0); // t=[0,1]
+            let t = 0.5 * (ray.direction.normalize().y + 1.0); // t=[0,1]
             t * Vec3::new(1.0, 1.0, 1.0) + (1.0 - t) * *bg_color
         }
     }
 }
 
+/// upper limit of the scattering events in media along a path, they do not count towards the
+/// maximum number of bounces, since random walks below surfaces take many steps
+const MAX_MEDIUM_SCATTERS: u32 = 1024;
+
+/// path tracer that combines bsdf sampling and light sampling with multiple importance sampling
+/// rays start in the fog of the scene, the medium changes where they cross medium boundaries
+/// rays leaving surfaces start at offset origins, so hits are searched from distance 0.0 on,
+/// surfaces beyond max_dist are not hit
+pub fn colorize(ray: &Ray, scene: &Scene, bg_color: &Vec3, max_depth: u32, max_dist: f32) -> Vec3 {
+    let mut radiance = Vec3::zero();
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+    let mut ray = *ray;
+    // the camera ray counts as specular: emitters that are seen directly get full weight
+    let mut prev_is_specular = true;
+    let mut prev_bsdf_pdf = 0.0;
+    // where the ray was last scattered, it differs from the ray origin after crossing medium
+    // boundaries
+    let mut prev_scatter_point = ray.origin;
+    let mut medium = scene.fog_medium();
+    let mut depth = 0;
+    let mut num_medium_scatters = 0;
+
+    loop {
+        // distances along the ray are lengths in the media
+        ray.direction = ray.direction.normalize();
+        let hit_info_op = scene.hit(&ray, 0.0, max_dist);
+
+        if let Some(current_medium) = medium {
+            let surface_dist = hit_info_op.map_or(max_dist, |hit_info| hit_info.dist_from_ray_orig);
+            match current_medium.sample_distance(&ray, surface_dist) {
+                MediumSample::Scatter { dist, weight } => {
+                    throughput = throughput * weight;
+                    num_medium_scatters += 1;
+                    if num_medium_scatters > MAX_MEDIUM_SCATTERS || throughput.sum() <= 0.0 {
+                        break;
+                    }
+                    let scatter_point = ray.point_at(dist);
+                    radiance += throughput
+                        * sample_direct_light(
+                            &scatter_point,
+                            None,
+                            &ray,
+                            scene,
+                            medium,
+                            |direction| {
+                                let phase = current_medium.phase(&ray.direction, direction);
+                                (Vec3::new(phase, phase, phase), phase)
+                            },
+                        );
+
+                    // sampling the phase function leaves the throughput unchanged
+                    let direction = current_medium.sample_phase(&ray.direction);
+                    prev_is_specular = false;
+                    prev_bsdf_pdf = current_medium.phase(&ray.direction, &direction);
+                    prev_scatter_point = scatter_point;
+                    ray = Ray {
+                        origin: scatter_point,
+                        direction,
+                        wavelength_nm: ray.wavelength_nm,
+                    };
+                    continue;
+                }
+                MediumSample::Pass { weight } => throughput = throughput * weight,
+                MediumSample::Absorb => break,
+            }
+        }
+
+        let mut hit_info = match hit_info_op {
+            Some(hit_info) => hit_info,
+            None => {
+                radiance += throughput * background(&ray, scene, bg_color);
+                break;
+            }
+        };
+
+        if hit_info.hit_material.is_invisible() {
+            // continue straight through the boundary, in the medium on the other side
+            medium = scene.medium_behind(&hit_info, &ray.direction);
+            ray.origin = hit_info.spawn_origin(&ray.direction);
+            continue;
+        }
+        hit_info.shading_normal = hit_info.hit_material.shading_normal(&hit_info);
+
+        let emitted = hit_info.hit_material.emitted(&ray.direction, &hit_info);
+        if emitted.sum() > 0.0 {
+            let mis_weight = if prev_is_specular {
+                1.0
+            } else {
+                let light_pdf = scene.light_pdf(&prev_scatter_point, &hit_info.hit_point);
+                power_heuristic(prev_bsdf_pdf, light_pdf)
+            };
+            radiance += mis_weight * throughput * emitted;
+        }
+
+        if depth == max_depth {
+            break;
+        }
+        depth += 1;
+
+        let material = hit_info.hit_material;
+        // vertex colors tint the light scattered by the surface, not its emission
+        if let Some(vertex_color) = hit_info.vertex_color {
+            throughput = throughput * vertex_color;
+        }
+        // lights lie outside of the media enclosed by surfaces
+        let light_medium = match material.medium() {
+            Some(_) => scene.fog_medium(),
+            None => medium,
+        };
+        radiance += throughput
+            * sample_direct_light(
+                &hit_info.hit_point,
+                Some(&hit_info),
+                &ray,
+                scene,
+                light_medium,
+                |direction| {
+                    (
+                        material.eval(&ray.direction, direction, &hit_info),
+                        material.pdf(&ray.direction, direction, &hit_info),
+                    )
+                },
+            );
+
+        match hit_info.hit_material.sample(&ray, &hit_info) {
+            Some(rec) => {
+                throughput = throughput * rec.attentuation;
+                prev_is_specular = rec.is_specular;
+                prev_bsdf_pdf = rec.pdf;
+                prev_scatter_point = hit_info.hit_point;
+                ray = rec.scattered_ray;
+                ray.origin = hit_info.spawn_origin(&ray.direction);
+                if material.medium().is_some() {
+                    medium = scene.medium_behind(&hit_info, &ray.direction);
+                }
+            }
+            // ray was completely attentuated
+            None => break,
+        }
+    }
+    radiance
+}
+
+pub struct RenderSettings {
+    /// number of rays per pixel
+    pub num_samples: u32,
+    /// maximum number of bounces of a ray
+    pub max_depth: u32,
+    /// trace a single wavelength per ray instead of rgb, needed for dispersion
+    pub spectral: bool,
+    /// far clip distance, surfaces beyond it are not rendered, infinite by default
+    pub max_dist: f32,
+}
+
+impl RenderSettings {
+    pub fn new(num_samples: u32) -> RenderSettings {
+        RenderSettings {
+            num_samples,
+            max_depth: 50,
+            spectral: false,
+            max_dist: f32::INFINITY,
+        }
+    }
+}
+
 pub fn render_scene(
     cam: Camera,
-    num_samples: u32,
+    settings: &RenderSettings,
     scene: Scene,
 ) -> image::ImageBuffer<Rgb<u8>, Vec<u8>> {
     println!("Starting rendering...");
@@ -93,12 +384,34 @@ pub fn render_scene(
                     };
 
                     let mut color = Vec3::zero();
-                    for _s in 0..num_samples {
-                        let ray = cam.get_ray_through_pixel(row_idx, col_idx);
+                    for sample_idx in 0..settings.num_samples {
+                        let mut ray = cam.get_ray_through_pixel(row_idx, col_idx);
 
-                        color += colorize(&ray, &scene, &bg_color, 50);
+                        if settings.spectral {
+                            let wavelength_nm = spectrum::sample_stratified_wavelength(
+                                sample_idx,
+                                settings.num_samples,
+                            );
+                            ray.wavelength_nm = Some(wavelength_nm);
+                            color += spectrum::wavelength_to_rgb_weight(wavelength_nm)
+                                * colorize(
+                                    &ray,
+                                    &scene,
+                                    &bg_color,
+                                    settings.max_depth,
+                                    settings.max_dist,
+                                );
+                        } else {
+                            color += colorize(
+                                &ray,
+                                &scene,
+                                &bg_color,
+                                settings.max_depth,
+                                settings.max_dist,
+                            );
+                        }
                     }
-                    color = color * (1.0 / num_samples as f32);
+                    color = color * (1.0 / settings.num_samples as f32);
                     color
                 })
                 .collect();
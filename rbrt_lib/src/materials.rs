@@ -1,14 +1,101 @@
+use std::f32::consts::PI;
+
+use crate::medium::Medium;
 use crate::vec3::Vec3;
 use crate::{HitInformation, Ray};
 
+/// outcome of sampling a scattered direction from a material
+#[derive(Copy, Clone, Debug)]
+pub struct ScatterRecord {
+    pub scattered_ray: Ray,
+    /// bsdf * cos / pdf of the sampled direction
+    pub attentuation: Vec3,
+    /// solid angle pdf of the sampled direction, zero for specular scattering
+    pub pdf: f32,
+    /// the direction was drawn from a delta distribution (mirror, glass),
+    /// which eval and pdf cannot represent
+    pub is_specular: bool,
+}
+
+/// Directions follow the ray: incoming_dir points towards the surface,
+/// scattered_dir points away from it.
 pub trait RayScattering {
-    fn scatter(
-        &self,
-        incoming_ray: &Ray,
-        hit_info: &HitInformation,
-        attentuation: &mut Vec3,
-        scattered_ray: &mut Ray,
-    ) -> bool;
+    /// draws a scattered direction for the incoming ray
+    /// None means the ray was absorbed
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord>;
+
+    /// bsdf times cosine foreshortening for light that leaves along scattered_dir
+    /// and is seen from incoming_dir, zero for specular materials
+    fn eval(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3;
+
+    /// solid angle pdf of sample() producing scattered_dir, zero for specular materials
+    fn pdf(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32;
+
+    /// radiance emitted by the surface towards -incoming_dir
+    fn emitted(&self, _incoming_dir: &Vec3, _hit_info: &HitInformation) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// normal used for shading the hit, materials with surface detail perturb the
+    /// shading normal of the hit
+    fn shading_normal(&self, hit_info: &HitInformation) -> Vec3 {
+        hit_info.shading_normal
+    }
+
+    /// medium enclosed by the surface, rays that scatter into the surface enter it
+    fn medium(&self) -> Option<&dyn Medium> {
+        None
+    }
+
+    /// invisible surfaces only bound a medium, rays pass through them unchanged
+    fn is_invisible(&self) -> bool {
+        false
+    }
+
+    /// probability that a ray hitting the surface stops there instead of passing through
+    fn opacity(&self, _hit_info: &HitInformation) -> f32 {
+        1.0
+    }
+}
+
+/// shading normal on the side of the surface that the ray hit, the side is decided by the
+/// geometric normal, so that shading normals do not let rays leak through the surface
+pub fn facing_normal(hit_info: &HitInformation) -> Vec3 {
+    if hit_info.front_face {
+        hit_info.shading_normal
+    } else {
+        -1.0 * hit_info.shading_normal
+    }
+}
+
+/// two unit vectors that form an orthonormal basis together with the unit vector n
+/// see Duff et al., "Building an Orthonormal Basis, Revisited"
+pub fn orthonormal_basis(n: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0_f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    (
+        Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        Vec3::new(b, sign + n.y * n.y * a, -n.y),
+    )
+}
+
+/// expresses the direction given in the local frame (z pointing along n) in world coordinates
+pub fn local_to_world(local_dir: &Vec3, n: &Vec3) -> Vec3 {
+    let (t, b) = orthonormal_basis(n);
+    local_dir.x * t + local_dir.y * b + local_dir.z * *n
+}
+
+/// random direction in the hemisphere around z, with pdf cos(theta) / pi
+pub fn random_cosine_direction() -> Vec3 {
+    cosine_direction(rand::random::<f32>(), rand::random::<f32>())
+}
+
+/// maps two uniform numbers in [0,1) to a cosine distributed direction around z
+pub fn cosine_direction(r1: f32, r2: f32) -> Vec3 {
+    let phi = 2.0 * PI * r1;
+    let r = r2.sqrt();
+    Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - r2).max(0.0).sqrt())
 }
 
 pub fn random_point_in_unit_sphere() -> Vec3 {
@@ -36,9 +123,30 @@ pub fn reflect(incoming_ray_dir: &Vec3, normal: &Vec3) -> Vec3 {
     reflected_dir.normalize()
 }
 
+/// hit at the origin on a surface with the unit normal, for testing materials
+#[cfg(test)]
+pub fn hit_on(material: &dyn RayScattering, normal: Vec3, front_face: bool) -> HitInformation<'_> {
+    let (tangent, bitangent) = orthonormal_basis(&normal);
+    HitInformation {
+        hit_point: Vec3::zero(),
+        geometric_normal: normal,
+        shading_normal: normal,
+        front_face,
+        uv: (0.0, 0.0),
+        tangent,
+        bitangent,
+        hit_material: material,
+        vertex_color: None,
+        dist_from_ray_orig: 1.0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{random_point_in_unit_sphere, reflect, Vec3};
+    use super::{
+        local_to_world, orthonormal_basis, random_cosine_direction, random_point_in_unit_sphere,
+        reflect, Vec3,
+    };
     #[test]
     fn test_random_points_in_unit_sphere() {
         for _i in 0..20 {
@@ -46,6 +154,7 @@ mod tests {
         }
     }
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_reflection() {
         let incoming = Vec3::new(1.0, 1.0, 1.0);
         let normal = Vec3::new(1.0, 1.0, 1.0);
@@ -57,4 +166,30 @@ mod tests {
         let refl = reflect(&incoming, &normal);
         assert_eq!(refl, Vec3::new(-0.7071068, 0.7071068, 0.0));
     }
+
+    #[test]
+    fn test_orthonormal_basis() {
+        for n in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, 3.0).normalize(),
+            Vec3::new(-0.3, 0.1, -0.9).normalize(),
+        ] {
+            let (t, b) = orthonormal_basis(&n);
+            assert!((t.length() - 1.0).abs() < 1e-5);
+            assert!((b.length() - 1.0).abs() < 1e-5);
+            assert!(t.dot(&n).abs() < 1e-5);
+            assert!(b.dot(&n).abs() < 1e-5);
+            assert!(t.dot(&b).abs() < 1e-5);
+            assert!((local_to_world(&Vec3::new(0.0, 0.0, 1.0), &n) - n).length() < 1e-5);
+        }
+    }
+    #[test]
+    fn test_random_cosine_direction() {
+        for _i in 0..20 {
+            let dir = random_cosine_direction();
+            assert!((dir.length() - 1.0).abs() < 1e-5);
+            assert!(dir.z >= 0.0);
+        }
+    }
 }
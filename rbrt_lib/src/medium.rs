@@ -0,0 +1,254 @@
+use std::f32::consts::PI;
+
+use crate::materials::{local_to_world, ScatterRecord};
+use crate::vec3::Vec3;
+use crate::{HitInformation, Ray, RayScattering};
+
+/// e^(-coefficients * dist) per color channel
+fn exp_falloff(coefficients: &Vec3, dist: f32) -> Vec3 {
+    // channels without extinction stay clear up to infinite distances
+    let channel = |coefficient: f32| {
+        if coefficient > 0.0 {
+            (-coefficient * dist).exp()
+        } else {
+            1.0
+        }
+    };
+    Vec3::new(
+        channel(coefficients.x),
+        channel(coefficients.y),
+        channel(coefficients.z),
+    )
+}
+
+/// Henyey-Greenstein phase function of the angle between the direction of travel and the
+/// scattered direction, g > 0 scatters forward, g < 0 backward, g = 0 uniformly
+pub fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.max(1e-12).sqrt())
+}
+
+/// draws a scattered direction from the Henyey-Greenstein phase function around direction
+pub fn sample_henyey_greenstein(direction: &Vec3, g: f32) -> Vec3 {
+    let r1 = rand::random::<f32>();
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * r1
+    } else {
+        let sq_term = (1.0 - g * g) / (1.0 - g + 2.0 * g * r1);
+        ((1.0 + g * g - sq_term * sq_term) / (2.0 * g)).clamp(-1.0, 1.0)
+    };
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * rand::random::<f32>();
+    local_to_world(
+        &Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta),
+        &direction.normalize(),
+    )
+    .normalize()
+}
+
+/// outcome of sampling the distance a ray travels through a medium
+pub enum MediumSample {
+    /// the ray scatters in the medium at dist, weight is transmittance * scattering / pdf
+    Scatter { dist: f32, weight: Vec3 },
+    /// the ray reaches the surface, weight is transmittance / probability of getting there
+    Pass { weight: Vec3 },
+    /// the ray was absorbed by the medium
+    Absorb,
+}
+
+/// volume that absorbs and scatters light, distances are measured along rays with unit
+/// length directions
+pub trait Medium: Sync {
+    /// draws where the ray scatters before reaching a surface at surface_dist
+    fn sample_distance(&self, ray: &Ray, surface_dist: f32) -> MediumSample;
+
+    /// fraction of light that travels along the ray from min_dist to max_dist without
+    /// being absorbed or scattered away, media with varying density may return an
+    /// unbiased estimate
+    fn transmittance(&self, ray: &Ray, min_dist: f32, max_dist: f32) -> Vec3;
+
+    /// mean cosine of the scattering angle of the Henyey-Greenstein phase function
+    fn anisotropy(&self) -> f32;
+
+    /// phase function value, which is also the pdf of sample_phase
+    fn phase(&self, direction: &Vec3, scattered_dir: &Vec3) -> f32 {
+        let cos_theta = direction.normalize().dot(&scattered_dir.normalize());
+        henyey_greenstein(cos_theta, self.anisotropy())
+    }
+
+    fn sample_phase(&self, direction: &Vec3) -> Vec3 {
+        sample_henyey_greenstein(direction, self.anisotropy())
+    }
+}
+
+/// medium with constant absorption and scattering coefficients (per unit length and
+/// color channel) that scatters light according to the Henyey-Greenstein phase function
+#[derive(Copy, Clone, Debug)]
+pub struct HomogeneousMedium {
+    pub absorption: Vec3,
+    pub scattering: Vec3,
+    /// mean cosine of the scattering angle, between -1 and 1
+    pub anisotropy: f32,
+}
+
+impl HomogeneousMedium {
+    fn extinction(&self) -> Vec3 {
+        self.absorption + self.scattering
+    }
+}
+
+impl Medium for HomogeneousMedium {
+    /// the distance is sampled for a randomly chosen color channel, weighted by the average
+    /// over all channels
+    fn sample_distance(&self, _ray: &Ray, surface_dist: f32) -> MediumSample {
+        let extinction = self.extinction();
+        let channels = [extinction.x, extinction.y, extinction.z];
+        let channel = ((rand::random::<f32>() * 3.0) as usize).min(2);
+        let dist = if channels[channel] > 0.0 {
+            -(1.0 - rand::random::<f32>()).ln() / channels[channel]
+        } else {
+            f32::INFINITY
+        };
+
+        if dist < surface_dist {
+            let transmittance = exp_falloff(&extinction, dist);
+            let pdf = (extinction * transmittance).sum() / 3.0;
+            let weight = if pdf > 0.0 {
+                (1.0 / pdf) * transmittance * self.scattering
+            } else {
+                Vec3::zero()
+            };
+            MediumSample::Scatter { dist, weight }
+        } else {
+            let transmittance = exp_falloff(&extinction, surface_dist);
+            let probability = transmittance.sum() / 3.0;
+            let weight = if probability > 0.0 {
+                (1.0 / probability) * transmittance
+            } else {
+                Vec3::zero()
+            };
+            MediumSample::Pass { weight }
+        }
+    }
+
+    fn transmittance(&self, _ray: &Ray, min_dist: f32, max_dist: f32) -> Vec3 {
+        exp_falloff(&self.extinction(), max_dist - min_dist)
+    }
+
+    fn anisotropy(&self) -> f32 {
+        self.anisotropy
+    }
+}
+
+/// invisible surface that encloses a medium, e.g. a sphere or a closed mesh
+/// rays that cross it enter the medium against the normal and leave it along the normal
+pub struct MediumBoundary {
+    pub medium: Box<dyn Medium>,
+}
+
+impl RayScattering for MediumBoundary {
+    /// boundaries are skipped by the path tracer, rays pass straight through
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            scattered_ray: Ray {
+                origin: hit_info.hit_point,
+                ..*incoming_ray
+            },
+            attentuation: Vec3::new(1.0, 1.0, 1.0),
+            pdf: 0.0,
+            is_specular: true,
+        })
+    }
+
+    fn eval(
+        &self,
+        _incoming_dir: &Vec3,
+        _scattered_dir: &Vec3,
+        _hit_info: &HitInformation,
+    ) -> Vec3 {
+        Vec3::zero()
+    }
+
+    fn pdf(&self, _incoming_dir: &Vec3, _scattered_dir: &Vec3, _hit_info: &HitInformation) -> f32 {
+        0.0
+    }
+
+    fn medium(&self) -> Option<&dyn Medium> {
+        Some(&*self.medium)
+    }
+
+    fn is_invisible(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        henyey_greenstein, sample_henyey_greenstein, HomogeneousMedium, Medium, MediumSample,
+    };
+    use crate::vec3::Vec3;
+    use crate::Ray;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_henyey_greenstein() {
+        for g in [-0.7, 0.0, 0.3, 0.9] {
+            // integrates to one over the sphere
+            let steps = 20000;
+            let integral: f32 = (0..steps)
+                .map(|step| {
+                    let cos_theta = -1.0 + 2.0 * (step as f32 + 0.5) / steps as f32;
+                    2.0 * PI * henyey_greenstein(cos_theta, g) * 2.0 / steps as f32
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 1e-2, "g = {}: {}", g, integral);
+
+            // the mean cosine of sampled directions is g
+            let direction = Vec3::new(0.0, 0.6, -0.8);
+            let num_samples = 20000;
+            let mean_cos = (0..num_samples)
+                .map(|_| sample_henyey_greenstein(&direction, g).dot(&direction))
+                .sum::<f32>()
+                / num_samples as f32;
+            assert!((mean_cos - g).abs() < 0.03, "g = {}: {}", g, mean_cos);
+        }
+    }
+
+    #[test]
+    fn test_distance_sampling_is_unbiased() {
+        // the expected weight of passing equals the transmittance to the surface
+        let medium = HomogeneousMedium {
+            absorption: Vec3::new(0.1, 0.3, 0.0),
+            scattering: Vec3::new(0.2, 0.2, 0.5),
+            anisotropy: 0.0,
+        };
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let surface_dist = 2.0;
+        let num_samples = 40000;
+        let mut passed = Vec3::zero();
+        let mut scattered = Vec3::zero();
+        for _i in 0..num_samples {
+            match medium.sample_distance(&ray, surface_dist) {
+                MediumSample::Pass { weight } => passed += weight,
+                MediumSample::Scatter { dist, weight } => {
+                    assert!(dist < surface_dist);
+                    scattered += weight;
+                }
+                MediumSample::Absorb => panic!("homogeneous media weight instead of absorbing"),
+            }
+        }
+        let passed = passed * (1.0 / num_samples as f32);
+        let expected = medium.transmittance(&ray, 0.0, surface_dist);
+        assert!((passed - expected).length() < 0.02, "{:?}", passed);
+        // single scattering: the scattered weight integrates scattering * transmittance
+        let scattered = scattered * (1.0 / num_samples as f32);
+        let extinction = medium.absorption + medium.scattering;
+        let expected = Vec3::new(
+            medium.scattering.x / extinction.x * (1.0 - expected.x),
+            medium.scattering.y / extinction.y * (1.0 - expected.y),
+            medium.scattering.z / extinction.z * (1.0 - expected.z),
+        );
+        assert!((scattered - expected).length() < 0.02, "{:?}", scattered);
+    }
+}
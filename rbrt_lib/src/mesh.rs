@@ -1,10 +1,19 @@
 extern crate tobj;
+use std::collections::HashMap;
 use std::path::Path;
 
 use crate::aabbox::{compute_min_max_3d, BoundingBox};
+use crate::gltf_import::load_mesh_from_gltf_file;
+use crate::lambertian::Lambertian;
+use crate::mtl::{material_from_mtl, DEFAULT_MTL_ALBEDO};
+use crate::ply::load_mesh_from_ply_file;
+use crate::stl::load_mesh_from_stl_file;
+use crate::transform::Transform;
 use crate::triangle::{
-    get_triangle_normal, triangle_soa_avx_intersect_with_ray, triangle_soa_intersect_with_ray,
-    triangle_soa_sse_intersect_with_ray,
+    barycentric_coords, get_triangle_normal, interpolate_tex_coords, tangent_frame,
+    triangle_soa_avx_intersect_with_ray, triangle_soa_avx_watertight_intersect_with_ray,
+    triangle_soa_intersect_with_ray, triangle_soa_sse_intersect_with_ray,
+    triangle_soa_sse_watertight_intersect_with_ray, triangle_soa_watertight_intersect_with_ray,
 };
 use crate::vec3::Vec3;
 use crate::{HitInformation, Intersectable, Ray, RayScattering};
@@ -16,12 +25,21 @@ pub struct TriangleMesh {
     pub edges: [[Vec<f32>; 3]; 2],
     /// 1 normal with 3 coords (x,y,z) each
     pub normals: [Vec<f32>; 3],
+    /// 3 texture coordinates with 2 coords (u,v) each
+    pub tex_coords: [[Vec<f32>; 2]; 3],
+    /// 3 vertex normals with 3 coords (x,y,z) each, None for flat shading
+    pub vertex_normals: Option<[[Vec<f32>; 3]; 3]>,
+    /// 3 vertex colors with 3 channels (r,g,b) each, None if the mesh has no vertex colors
+    pub vertex_colors: Option<[[Vec<f32>; 3]; 3]>,
     /// 1 flag to set padding elements
     pub is_padding_triangle: Vec<bool>,
     /// axis aligned bounding box of the Mesh
     pub bbox: BoundingBox,
-    /// one material for the whole mesh
-    pub material: Box<dyn RayScattering + Sync>,
+    pub materials: Vec<Box<dyn RayScattering + Sync>>,
+    /// 1 index into materials per triangle
+    pub material_indices: Vec<usize>,
+    /// use the watertight intersection test, so that no rays slip through shared edges
+    pub watertight: bool,
 }
 
 pub fn determine_num_vector_lanes() -> usize {
@@ -43,9 +61,24 @@ impl TriangleMesh {
         translation: Vec3,
         rotation: Vec3,
         scale: f32,
-        material: Box<dyn RayScattering + Sync>,
+        material_override: Option<Box<dyn RayScattering + Sync>>,
+        smooth_shading: bool,
     ) -> TriangleMesh {
-        let mut pre_vertices = load_mesh_vertices_from_file(filepath, translation, rotation, scale);
+        let mesh_data = load_mesh_from_file(filepath, translation, rotation, scale);
+        TriangleMesh::from_mesh_data(mesh_data, material_override, smooth_shading)
+    }
+
+    /// the material override replaces the materials of the mesh data
+    pub fn from_mesh_data(
+        mesh_data: MeshData,
+        material_override: Option<Box<dyn RayScattering + Sync>>,
+        smooth_shading: bool,
+    ) -> TriangleMesh {
+        let (materials, pre_material_indices) = match material_override {
+            Some(material) => (vec![material], vec![0; mesh_data.vertices.len()]),
+            None => assign_materials(mesh_data.materials, &mesh_data.material_ids),
+        };
+        let mut pre_vertices = mesh_data.vertices;
 
         let mut pre_normals = vec![];
         for triangle_vertices in &pre_vertices {
@@ -62,54 +95,231 @@ impl TriangleMesh {
         let (lower_bound, upper_bound) = compute_min_max_3d(&pre_vertices);
         let (vertices, edges, normals, is_padding_triangle) =
             convert_to_soa_mesh(&mut pre_vertices, &mut pre_edges, &mut pre_normals);
+        let tex_coords =
+            convert_corner_attributes_to_soa(&mesh_data.tex_coords, is_padding_triangle.len());
+        let vertex_normals = if smooth_shading {
+            Some(convert_corner_vectors_to_soa(
+                &mesh_data.vertex_normals,
+                is_padding_triangle.len(),
+            ))
+        } else {
+            None
+        };
+        let vertex_colors = mesh_data.vertex_colors.as_ref().map(|vertex_colors| {
+            convert_corner_vectors_to_soa(vertex_colors, is_padding_triangle.len())
+        });
+
+        let material_indices = (0..is_padding_triangle.len())
+            .map(|triangle_idx| {
+                *pre_material_indices
+                    .get(triangle_idx)
+                    .unwrap_or(&pre_material_indices[0])
+            })
+            .collect();
 
         TriangleMesh {
             is_padding_triangle,
             vertices,
             normals,
+            tex_coords,
+            vertex_normals,
+            vertex_colors,
             edges,
             bbox: BoundingBox::new(lower_bound, upper_bound),
-            material,
+            materials,
+            material_indices,
+            watertight: false,
+        }
+    }
+}
+
+/// index of the material of each triangle, triangles without material get
+/// a default lambertian material
+fn assign_materials(
+    mut materials: Vec<Box<dyn RayScattering + Sync>>,
+    material_ids: &[Option<usize>],
+) -> (Vec<Box<dyn RayScattering + Sync>>, Vec<usize>) {
+    let default_material_idx = materials.len();
+    materials.push(Box::new(Lambertian {
+        albedo: DEFAULT_MTL_ALBEDO.into(),
+    }));
+    let material_indices = material_ids
+        .iter()
+        .map(|material_id| match material_id {
+            Some(material_id) if *material_id < default_material_idx => *material_id,
+            _ => default_material_idx,
+        })
+        .collect();
+    (materials, material_indices)
+}
+
+/// triangles of a mesh file, transformed into world coordinates
+pub struct MeshData {
+    pub vertices: Vec<[Vec3; 3]>,
+    /// texture coordinates of the corners of each triangle, (0,0) if the file has none
+    pub tex_coords: Vec<[(f32, f32); 3]>,
+    /// normals of the corners of each triangle, computed if the file has none
+    pub vertex_normals: Vec<[Vec3; 3]>,
+    /// colors of the corners of each triangle, None if the file has none
+    pub vertex_colors: Option<Vec<[Vec3; 3]>>,
+    /// index into materials of each triangle
+    pub material_ids: Vec<Option<usize>>,
+    /// materials defined by the mesh file, e.g. in .mtl files
+    pub materials: Vec<Box<dyn RayScattering + Sync>>,
+}
+
+/// selects the loader by the file extension: .gltf, .glb, .ply, .stl or .obj otherwise
+pub fn load_mesh_from_file(
+    filepath: &str,
+    translation: Vec3,
+    rotation: Vec3,
+    scale: f32,
+) -> MeshData {
+    let extension = Path::new(filepath)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("gltf") | Some("glb") => {
+            load_mesh_from_gltf_file(filepath, translation, rotation, scale)
+        }
+        Some("ply") => load_mesh_from_ply_file(filepath, translation, rotation, scale),
+        Some("stl") => load_mesh_from_stl_file(filepath, translation, rotation, scale),
+        _ => load_mesh_from_obj_file(filepath, translation, rotation, scale),
+    }
+}
+
+/// transforms the triangles and normals of the mesh, the corners of mirrored
+/// triangles are reordered, so that their face normals keep pointing outwards
+pub fn transform_mesh_data(mesh_data: &mut MeshData, transform: &Transform) {
+    for triangle in mesh_data.vertices.iter_mut() {
+        *triangle = triangle.map(|vertex| transform.transform_point(&vertex));
+    }
+    for vertex_normals in mesh_data.vertex_normals.iter_mut() {
+        *vertex_normals = vertex_normals.map(|normal| transform.transform_normal(&normal));
+    }
+    if transform.determinant() < 0.0 {
+        for triangle in mesh_data.vertices.iter_mut() {
+            triangle.swap(1, 2);
+        }
+        for tex_coords in mesh_data.tex_coords.iter_mut() {
+            tex_coords.swap(1, 2);
+        }
+        for vertex_normals in mesh_data.vertex_normals.iter_mut() {
+            vertex_normals.swap(1, 2);
+        }
+        for vertex_colors in mesh_data.vertex_colors.iter_mut().flatten() {
+            vertex_colors.swap(1, 2);
         }
     }
 }
 
-/// Loads mesh from obj file, scales and translates it
-pub fn load_mesh_vertices_from_file(
+/// area weighted average of the normals of the triangles sharing a vertex, for each corner
+/// vertices are identified by their position, so that split vertices (e.g. at texture
+/// seams) are still shaded smoothly
+pub fn compute_vertex_normals(vertices: &[[Vec3; 3]]) -> Vec<[Vec3; 3]> {
+    let position_key = |vertex: &Vec3| [vertex.x.to_bits(), vertex.y.to_bits(), vertex.z.to_bits()];
+    let mut summed_normals: HashMap<[u32; 3], Vec3> = HashMap::new();
+    for triangle in vertices {
+        // the length of the cross product is twice the area of the triangle
+        let weighted_normal =
+            (triangle[1] - triangle[0]).cross_product(&(triangle[2] - triangle[0]));
+        for vertex in triangle {
+            let summed_normal = summed_normals
+                .entry(position_key(vertex))
+                .or_insert_with(Vec3::zero);
+            *summed_normal += weighted_normal;
+        }
+    }
+    vertices
+        .iter()
+        .map(|triangle| {
+            let face_normal = get_triangle_normal(triangle);
+            triangle.map(|vertex| {
+                let summed_normal = summed_normals[&position_key(&vertex)];
+                if summed_normal.length() > 0.0 {
+                    summed_normal.normalize()
+                } else {
+                    face_normal
+                }
+            })
+        })
+        .collect()
+}
+
+/// Loads mesh from obj file, scales, rotates and translates it
+pub fn load_mesh_from_obj_file(
     filepath: &str,
     translation: Vec3,
     rotation: Vec3,
     scale: f32,
-) -> Vec<[Vec3; 3]> {
+) -> MeshData {
     let mut model_vertices: Vec<[Vec3; 3]> = Vec::new();
+    let mut model_tex_coords: Vec<[(f32, f32); 3]> = Vec::new();
+    let mut model_vertex_normals: Vec<[Vec3; 3]> = Vec::new();
+    let mut model_material_ids: Vec<Option<usize>> = Vec::new();
+    // single index: positions and texture coordinates share the same index
     let load_options = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
         ..Default::default()
     };
     let loaded_mesh = tobj::load_obj(Path::new(filepath), &load_options);
     assert!(loaded_mesh.is_ok());
-    let (models, _materials) = loaded_mesh.unwrap();
+    let (models, mtl_materials) = loaded_mesh.unwrap();
+    let mtl_materials = mtl_materials.unwrap_or_else(|error| {
+        println!("Failed to load materials of {}: {:?}", filepath, error);
+        vec![]
+    });
 
     for m in models.iter() {
         let mesh = &m.mesh;
         assert!(mesh.positions.len() % 3 == 0);
+        let has_tex_coords = !mesh.texcoords.is_empty();
+        let has_normals = !mesh.normals.is_empty();
+        let first_triangle_idx = model_vertices.len();
         let mut triangle_vertices: Vec<Vec3> = vec![Vec3::zero(); 3];
+        let mut triangle_tex_coords = [(0.0, 0.0); 3];
+        let mut triangle_normals = [Vec3::zero(); 3];
         for f in 0..mesh.indices.len() / 3 {
-            for idx in 0..3 {
-                let x_idx = 3 * mesh.indices[3 * f + idx];
-                let y_idx = 3 * mesh.indices[3 * f + idx] + 1;
-                let z_idx = 3 * mesh.indices[3 * f + idx] + 2;
-
-                triangle_vertices[idx] = Vec3::new(
-                    mesh.positions[x_idx as usize] * scale,
-                    mesh.positions[y_idx as usize] * scale,
-                    mesh.positions[z_idx as usize] * scale,
+            for (idx, triangle_vertex) in triangle_vertices.iter_mut().enumerate() {
+                let vertex_idx = mesh.indices[3 * f + idx] as usize;
+                let x_idx = 3 * vertex_idx;
+                let y_idx = 3 * vertex_idx + 1;
+                let z_idx = 3 * vertex_idx + 2;
+
+                *triangle_vertex = Vec3::new(
+                    mesh.positions[x_idx],
+                    mesh.positions[y_idx],
+                    mesh.positions[z_idx],
                 );
+                if has_tex_coords {
+                    triangle_tex_coords[idx] = (
+                        mesh.texcoords[2 * vertex_idx],
+                        mesh.texcoords[2 * vertex_idx + 1],
+                    );
+                }
+                if has_normals {
+                    triangle_normals[idx] = Vec3::new(
+                        mesh.normals[x_idx],
+                        mesh.normals[y_idx],
+                        mesh.normals[z_idx],
+                    )
+                    .normalize();
+                }
             }
             model_vertices.push([
-                triangle_vertices[0].rotate_point(rotation) + translation,
-                triangle_vertices[1].rotate_point(rotation) + translation,
-                triangle_vertices[2].rotate_point(rotation) + translation,
+                triangle_vertices[0],
+                triangle_vertices[1],
+                triangle_vertices[2],
             ]);
+            model_tex_coords.push(triangle_tex_coords);
+            model_vertex_normals.push(triangle_normals);
+            model_material_ids.push(mesh.material_id);
+        }
+        if !has_normals {
+            let computed_normals = compute_vertex_normals(&model_vertices[first_triangle_idx..]);
+            model_vertex_normals.truncate(first_triangle_idx);
+            model_vertex_normals.extend(computed_normals);
         }
     }
     println!(
@@ -117,23 +327,87 @@ pub fn load_mesh_vertices_from_file(
         model_vertices.len(),
         filepath
     );
-    model_vertices
+    let texture_dir = Path::new(filepath)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let mut mesh_data = MeshData {
+        vertices: model_vertices,
+        tex_coords: model_tex_coords,
+        vertex_normals: model_vertex_normals,
+        material_ids: model_material_ids,
+        vertex_colors: None,
+        materials: mtl_materials
+            .iter()
+            .map(|mtl| material_from_mtl(mtl, texture_dir))
+            .collect(),
+    };
+    transform_mesh_data(
+        &mut mesh_data,
+        &Transform::from_scale_rotation_translation(scale, rotation, translation),
+    );
+    mesh_data
 }
 
-pub fn convert_to_soa_mesh(
-    pre_vertices: &mut std::vec::Vec<[Vec3; 3]>,
-    pre_edges: &mut std::vec::Vec<[Vec3; 2]>,
-    pre_normals: &mut std::vec::Vec<Vec3>,
-) -> (
+/// splits 2d attributes of the triangle corners (e.g. texture coordinates) into SoA layout,
+/// padded with the attributes of the first triangle to num_padded_triangles
+pub fn convert_corner_attributes_to_soa(
+    pre_attributes: &[[(f32, f32); 3]],
+    num_padded_triangles: usize,
+) -> [[Vec<f32>; 2]; 3] {
+    let mut attributes: [[Vec<f32>; 2]; 3] = [[vec![], vec![]], [vec![], vec![]], [vec![], vec![]]];
+    for triangle_idx in 0..num_padded_triangles {
+        let triangle_attributes = pre_attributes
+            .get(triangle_idx)
+            .unwrap_or(&pre_attributes[0]);
+        for (corner, corner_attribute) in attributes.iter_mut().zip(triangle_attributes.iter()) {
+            corner[0].push(corner_attribute.0);
+            corner[1].push(corner_attribute.1);
+        }
+    }
+    attributes
+}
+
+/// splits 3d attributes of the triangle corners (e.g. vertex normals) into SoA layout,
+/// padded with the attributes of the first triangle to num_padded_triangles
+pub fn convert_corner_vectors_to_soa(
+    pre_attributes: &[[Vec3; 3]],
+    num_padded_triangles: usize,
+) -> [[Vec<f32>; 3]; 3] {
+    let mut attributes: [[Vec<f32>; 3]; 3] = [
+        [vec![], vec![], vec![]],
+        [vec![], vec![], vec![]],
+        [vec![], vec![], vec![]],
+    ];
+    for triangle_idx in 0..num_padded_triangles {
+        let triangle_attributes = pre_attributes
+            .get(triangle_idx)
+            .unwrap_or(&pre_attributes[0]);
+        for (corner, corner_attribute) in attributes.iter_mut().zip(triangle_attributes.iter()) {
+            corner[0].push(corner_attribute.x);
+            corner[1].push(corner_attribute.y);
+            corner[2].push(corner_attribute.z);
+        }
+    }
+    attributes
+}
+
+/// vertices, edges, normals and padding flags of a mesh in SoA layout
+pub type SoaMesh = (
     [[Vec<f32>; 3]; 3],
     [[Vec<f32>; 3]; 2],
     [Vec<f32>; 3],
     std::vec::Vec<bool>,
-) {
-    // use padding for simd
-    let num_vec_lanes = determine_num_vector_lanes();
+);
+
+pub fn convert_to_soa_mesh(
+    pre_vertices: &mut std::vec::Vec<[Vec3; 3]>,
+    pre_edges: &mut std::vec::Vec<[Vec3; 2]>,
+    pre_normals: &mut std::vec::Vec<Vec3>,
+) -> SoaMesh {
+    // use padding for simd, so that the number of triangles is a multiple of the vector width
+    let num_vec_lanes = determine_num_vector_lanes().max(1);
     let num_triangles = pre_vertices.len();
-    let num_padding_vals_required = num_triangles % num_vec_lanes;
+    let num_padding_vals_required = (num_vec_lanes - num_triangles % num_vec_lanes) % num_vec_lanes;
 
     let mut is_padding_triangle = vec![false; num_triangles];
     for _i in 0..num_padding_vals_required {
@@ -222,40 +496,197 @@ pub fn do_intersection_soa(
     }
 }
 
+/// like do_intersection_soa, but with the watertight intersection test
+pub fn do_watertight_intersection_soa(
+    ray: &Ray,
+    vertices: &[[std::vec::Vec<f32>; 3]; 3],
+    is_padding_triangle: &[bool],
+    min_dist: f32,
+    max_dist: f32,
+) -> (Option<f32>, Option<usize>) {
+    if is_x86_feature_detected!("avx") {
+        unsafe {
+            triangle_soa_avx_watertight_intersect_with_ray(
+                ray,
+                vertices,
+                is_padding_triangle,
+                min_dist,
+                max_dist,
+            )
+        }
+    } else if is_x86_feature_detected!("sse") {
+        unsafe {
+            triangle_soa_sse_watertight_intersect_with_ray(
+                ray,
+                vertices,
+                is_padding_triangle,
+                min_dist,
+                max_dist,
+            )
+        }
+    } else {
+        triangle_soa_watertight_intersect_with_ray(
+            ray,
+            vertices,
+            is_padding_triangle,
+            min_dist,
+            max_dist,
+        )
+    }
+}
+
+impl TriangleMesh {
+    fn vertex(&self, corner_idx: usize, triangle_idx: usize) -> Vec3 {
+        Vec3::new(
+            self.vertices[corner_idx][0][triangle_idx],
+            self.vertices[corner_idx][1][triangle_idx],
+            self.vertices[corner_idx][2][triangle_idx],
+        )
+    }
+
+    /// barycentric coordinates of a point on the triangle with index triangle_idx
+    pub fn barycentric_coords(&self, point: &Vec3, triangle_idx: usize) -> (f32, f32) {
+        let vertex_a = self.vertex(0, triangle_idx);
+        let edges = [
+            self.vertex(1, triangle_idx) - vertex_a,
+            self.vertex(2, triangle_idx) - vertex_a,
+        ];
+        barycentric_coords(point, &vertex_a, &edges)
+    }
+
+    fn corner_tex_coords(&self, triangle_idx: usize) -> [(f32, f32); 3] {
+        [0, 1, 2].map(|corner_idx| {
+            (
+                self.tex_coords[corner_idx][0][triangle_idx],
+                self.tex_coords[corner_idx][1][triangle_idx],
+            )
+        })
+    }
+
+    /// interpolated texture coordinates of a point on the triangle with index triangle_idx
+    pub fn tex_coords_at(&self, point: &Vec3, triangle_idx: usize) -> (f32, f32) {
+        interpolate_tex_coords(
+            &self.corner_tex_coords(triangle_idx),
+            self.barycentric_coords(point, triangle_idx),
+        )
+    }
+
+    /// unit normal of the triangle with index triangle_idx
+    pub fn face_normal(&self, triangle_idx: usize) -> Vec3 {
+        Vec3::new(
+            self.normals[0][triangle_idx],
+            self.normals[1][triangle_idx],
+            self.normals[2][triangle_idx],
+        )
+    }
+
+    /// face normal or, with smooth shading, interpolated vertex normal at a point on the
+    /// triangle with index triangle_idx
+    pub fn normal_at(&self, point: &Vec3, triangle_idx: usize) -> Vec3 {
+        let face_normal = self.face_normal(triangle_idx);
+        let vertex_normals = match &self.vertex_normals {
+            Some(vertex_normals) => vertex_normals,
+            None => return face_normal,
+        };
+        let (u, v) = self.barycentric_coords(point, triangle_idx);
+        let weights = [1.0 - u - v, u, v];
+        let mut normal = Vec3::zero();
+        for (corner_normal, weight) in vertex_normals.iter().zip(weights) {
+            normal += weight
+                * Vec3::new(
+                    corner_normal[0][triangle_idx],
+                    corner_normal[1][triangle_idx],
+                    corner_normal[2][triangle_idx],
+                );
+        }
+        if normal.length() > 0.0 {
+            normal.normalize()
+        } else {
+            face_normal
+        }
+    }
+
+    /// vertex colors interpolated at a point on the triangle with index triangle_idx
+    pub fn color_at(&self, point: &Vec3, triangle_idx: usize) -> Option<Vec3> {
+        let vertex_colors = self.vertex_colors.as_ref()?;
+        let (u, v) = self.barycentric_coords(point, triangle_idx);
+        let weights = [1.0 - u - v, u, v];
+        let mut color = Vec3::zero();
+        for (corner_color, weight) in vertex_colors.iter().zip(weights) {
+            color += weight
+                * Vec3::new(
+                    corner_color[0][triangle_idx],
+                    corner_color[1][triangle_idx],
+                    corner_color[2][triangle_idx],
+                );
+        }
+        Some(color)
+    }
+
+    /// derivatives (dp/du, dp/dv) of the triangle with index triangle_idx
+    pub fn tangent_frame(&self, triangle_idx: usize) -> (Vec3, Vec3) {
+        let edges = [0, 1].map(|edge_idx| {
+            Vec3::new(
+                self.edges[edge_idx][0][triangle_idx],
+                self.edges[edge_idx][1][triangle_idx],
+                self.edges[edge_idx][2][triangle_idx],
+            )
+        });
+        let normal = Vec3::new(
+            self.normals[0][triangle_idx],
+            self.normals[1][triangle_idx],
+            self.normals[2][triangle_idx],
+        );
+        tangent_frame(&edges, &self.corner_tex_coords(triangle_idx), &normal)
+    }
+}
+
 impl Intersectable for TriangleMesh {
     fn intersect_with_ray(
         &self,
         ray: &Ray,
         min_dist: f32,
         max_dist: f32,
-    ) -> Option<HitInformation> {
+    ) -> Option<HitInformation<'_>> {
         // first check if bounding box is hit
         if !self.bbox.hit(ray) {
             return None;
         }
-        let (hit_info_op, hit_idx_op) = do_intersection_soa(
-            ray,
-            &self.vertices,
-            &self.edges,
-            &self.is_padding_triangle,
-            min_dist,
-            max_dist,
-        );
+        let (hit_info_op, hit_idx_op) = if self.watertight {
+            do_watertight_intersection_soa(
+                ray,
+                &self.vertices,
+                &self.is_padding_triangle,
+                min_dist,
+                max_dist,
+            )
+        } else {
+            do_intersection_soa(
+                ray,
+                &self.vertices,
+                &self.edges,
+                &self.is_padding_triangle,
+                min_dist,
+                max_dist,
+            )
+        };
 
-        if hit_info_op.is_some() && hit_idx_op.is_some() {
-            let ray_param_cand = hit_info_op.unwrap();
+        if let (Some(ray_param_cand), Some(hit_idx)) = (hit_info_op, hit_idx_op) {
             let hit_point = ray.point_at(ray_param_cand);
             let dist_from_ray_orig = (ray.origin - hit_point).length();
             if dist_from_ray_orig > min_dist && dist_from_ray_orig < max_dist {
-                let hit_idx = hit_idx_op.unwrap();
+                let (tangent, bitangent) = self.tangent_frame(hit_idx);
+                let geometric_normal = self.face_normal(hit_idx);
                 Some(HitInformation {
                     hit_point,
-                    hit_normal: Vec3::new(
-                        self.normals[0][hit_idx],
-                        self.normals[1][hit_idx],
-                        self.normals[2][hit_idx],
-                    ),
-                    hit_material: &*self.material,
+                    uv: self.tex_coords_at(&hit_point, hit_idx),
+                    tangent,
+                    bitangent,
+                    geometric_normal,
+                    shading_normal: self.normal_at(&hit_point, hit_idx),
+                    front_face: ray.direction.dot(&geometric_normal) < 0.0,
+                    hit_material: &*self.materials[self.material_indices[hit_idx]],
+                    vertex_color: self.color_at(&hit_point, hit_idx),
                     dist_from_ray_orig,
                 })
             } else {
@@ -266,3 +697,193 @@ impl Intersectable for TriangleMesh {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compute_vertex_normals, convert_to_soa_mesh, determine_num_vector_lanes,
+        load_mesh_from_obj_file, transform_mesh_data, MeshData, TriangleMesh,
+    };
+    use crate::lambertian::Lambertian;
+    use crate::transform::Transform;
+    use crate::triangle::get_triangle_normal;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_soa_padding() {
+        let num_vec_lanes = determine_num_vector_lanes().max(1);
+        for num_triangles in 1..20 {
+            let triangle = [
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ];
+            let mut pre_vertices = vec![triangle; num_triangles];
+            let mut pre_edges =
+                vec![[triangle[1] - triangle[0], triangle[2] - triangle[0]]; num_triangles];
+            let mut pre_normals = vec![Vec3::new(0.0, 0.0, 1.0); num_triangles];
+
+            let (vertices, edges, normals, is_padding_triangle) =
+                convert_to_soa_mesh(&mut pre_vertices, &mut pre_edges, &mut pre_normals);
+
+            let num_padded = is_padding_triangle.len();
+            assert_eq!(num_padded % num_vec_lanes, 0);
+            assert!(num_padded >= num_triangles && num_padded < num_triangles + num_vec_lanes);
+            assert_eq!(
+                is_padding_triangle
+                    .iter()
+                    .filter(|is_pad| !**is_pad)
+                    .count(),
+                num_triangles
+            );
+            assert_eq!(vertices[2][2].len(), num_padded);
+            assert_eq!(edges[1][2].len(), num_padded);
+            assert_eq!(normals[2].len(), num_padded);
+        }
+    }
+
+    #[test]
+    fn test_computed_vertex_normals() {
+        // two triangles folded along the z axis like a roof, the left one has twice the area
+        let ridge = [Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0)];
+        let left = [
+            ridge[0],
+            Vec3::new(-2.0, -1.0, 0.0),
+            Vec3::new(-2.0, -1.0, 2.0),
+        ];
+        let right = [Vec3::new(1.0, 0.0, 0.0), ridge[0], ridge[1]];
+        let vertex_normals = compute_vertex_normals(&[left, right]);
+
+        let left_normal = (left[1] - left[0]).cross_product(&(left[2] - left[0]));
+        let right_normal = (right[1] - right[0]).cross_product(&(right[2] - right[0]));
+        assert!(left_normal.y > 0.0 && right_normal.y > 0.0);
+        // not shared with the other triangle
+        assert!((vertex_normals[0][1] - left_normal.normalize()).length() < 1e-6);
+        assert!((vertex_normals[1][0] - right_normal.normalize()).length() < 1e-6);
+        // shared, area weighted
+        let shared_normal = (left_normal + right_normal).normalize();
+        assert!((vertex_normals[0][0] - shared_normal).length() < 1e-6);
+        assert!((vertex_normals[1][1] - shared_normal).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_obj_is_transformed_like_other_meshes() {
+        let filepath = std::env::temp_dir().join("rbrt_test_transform.obj");
+        std::fs::write(
+            &filepath,
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 1 0 1\nf 1//1 2//1 3//1\n",
+        )
+        .unwrap();
+        let (scale, rotation, translation) =
+            (-2.0, Vec3::new(0.3, 1.1, -0.4), Vec3::new(1.0, 2.0, 3.0));
+        let mesh_data =
+            load_mesh_from_obj_file(filepath.to_str().unwrap(), translation, rotation, scale);
+
+        let transform = Transform::from_scale_rotation_translation(scale, rotation, translation);
+        // the negative scale mirrors, the last two corners are swapped to keep the winding
+        let expected = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        ]
+        .map(|vertex| transform.transform_point(&vertex));
+        for (vertex, expected) in mesh_data.vertices[0].iter().zip(expected) {
+            assert!((*vertex - expected).length() < 1e-5);
+        }
+        let expected_normal = transform.transform_normal(&Vec3::new(1.0, 0.0, 1.0));
+        assert!((mesh_data.vertex_normals[0][0] - expected_normal).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_smooth_shading_from_file_normals() {
+        let filepath = std::env::temp_dir().join("rbrt_test_vertex_normals.obj");
+        std::fs::write(
+            &filepath,
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn -1 0 1\nvn 1 0 1\nvn 0 0 1\nf 1//1 2//2 3//3\n",
+        )
+        .unwrap();
+        let filepath = filepath.to_str().unwrap();
+        let new_mesh = |smooth_shading| {
+            TriangleMesh::new(
+                filepath,
+                Vec3::zero(),
+                Vec3::zero(),
+                1.0,
+                Some(Box::new(Lambertian { albedo: 0.5.into() })),
+                smooth_shading,
+            )
+        };
+
+        let point = Vec3::new(0.5, 0.0, 0.0);
+        let smooth_mesh = new_mesh(true);
+        assert!((smooth_mesh.normal_at(&point, 0) - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+        let point = Vec3::new(0.0, 0.0, 0.0);
+        assert!(
+            (smooth_mesh.normal_at(&point, 0) - Vec3::new(-1.0, 0.0, 1.0).normalize()).length()
+                < 1e-6
+        );
+
+        let flat_mesh = new_mesh(false);
+        assert_eq!(flat_mesh.normal_at(&point, 0), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_per_face_mtl_materials() {
+        let dir = std::env::temp_dir().join("rbrt_test_mtl");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("faces.mtl"),
+            "newmtl red\nKd 0.8 0.1 0.1\nillum 2\nnewmtl glass\nNi 1.5\nd 0.1\nillum 7\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("faces.obj"),
+            "mtllib faces.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+             f 1 2 3\nusemtl red\nf 2 4 3\nusemtl glass\nf 1 3 4\n",
+        )
+        .unwrap();
+        let filepath = dir.join("faces.obj");
+        let filepath = filepath.to_str().unwrap();
+
+        let mesh = TriangleMesh::new(filepath, Vec3::zero(), Vec3::zero(), 1.0, None, false);
+        // red, glass and the default material
+        assert_eq!(mesh.materials.len(), 3);
+        assert_eq!(&mesh.material_indices[0..3], &[2, 0, 1]);
+        assert_eq!(mesh.material_indices.len(), mesh.is_padding_triangle.len());
+
+        let mesh = TriangleMesh::new(
+            filepath,
+            Vec3::zero(),
+            Vec3::zero(),
+            1.0,
+            Some(Box::new(Lambertian { albedo: 0.5.into() })),
+            false,
+        );
+        assert_eq!(mesh.materials.len(), 1);
+        assert!(mesh.material_indices.iter().all(|idx| *idx == 0));
+    }
+
+    #[test]
+    fn test_mirrored_mesh_keeps_orientation() {
+        let triangle = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        let mut mesh_data = MeshData {
+            vertices: vec![triangle],
+            tex_coords: vec![[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]],
+            vertex_normals: compute_vertex_normals(&[triangle]),
+            material_ids: vec![None],
+            vertex_colors: None,
+            materials: vec![],
+        };
+        // mirrors at the xy plane and stretches along x
+        transform_mesh_data(&mut mesh_data, &Transform::scale(Vec3::new(2.0, 1.0, -1.0)));
+        assert_eq!(mesh_data.vertices[0][2], Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(mesh_data.tex_coords[0][2], (1.0, 0.0));
+        let face_normal = get_triangle_normal(&mesh_data.vertices[0]);
+        assert!((face_normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-6);
+        assert!((mesh_data.vertex_normals[0][0] - face_normal).length() < 1e-6);
+    }
+}
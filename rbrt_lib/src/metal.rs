@@ -1,26 +1,197 @@
-use crate::materials::{random_point_in_unit_sphere, reflect};
+use std::f32::consts::PI;
+
+use crate::materials::{facing_normal, local_to_world, reflect, ScatterRecord};
+use crate::texture::Texture;
 use crate::vec3::Vec3;
 use crate::{HitInformation, Ray, RayScattering};
 
-#[derive(Copy, Clone, Debug)]
 pub struct Metal {
-    pub albedo: Vec3,
-    pub roughness: f32,
+    pub albedo: Box<dyn Texture>,
+    /// roughness is read from the first channel
+    pub roughness: Box<dyn Texture>,
+}
+
+/// exponent of the normalized phong lobe around the mirror direction,
+/// see Walter et al., "Microfacet Models for Refraction through Rough Surfaces"
+fn phong_exponent(roughness: f32) -> f32 {
+    2.0 / roughness.powi(2) - 2.0
+}
+
+/// density of the phong lobe around the mirror direction, integrates to 1 over the sphere
+fn lobe_pdf(roughness: f32, mirror_dir: &Vec3, scattered_dir: &Vec3) -> f32 {
+    let cos_alpha = mirror_dir.dot(&scattered_dir.normalize());
+    if cos_alpha <= 0.0 {
+        return 0.0;
+    }
+    let exponent = phong_exponent(roughness);
+    (exponent + 1.0) / (2.0 * PI) * cos_alpha.powf(exponent)
+}
+
+impl Metal {
+    /// roughness in [0,1], 0 is a mirror, 1 scatters uniformly into the hemisphere around the
+    /// mirror direction, the phong exponent of rougher lobes would be negative
+    fn roughness_at(&self, hit_info: &HitInformation) -> f32 {
+        self.roughness
+            .value(hit_info.uv, &hit_info.hit_point)
+            .x
+            .clamp(0.0, 1.0)
+    }
 }
 
 impl RayScattering for Metal {
-    fn scatter(
-        &self,
-        incoming_ray: &Ray,
-        hit_info: &HitInformation,
-        attentuation: &mut Vec3,
-        scattered_ray: &mut Ray,
-    ) -> bool {
-        let scattered_ray_target = reflect(&incoming_ray.direction, &hit_info.hit_normal);
-        scattered_ray.direction =
-            (scattered_ray_target + self.roughness * random_point_in_unit_sphere()).normalize();
-        scattered_ray.origin = hit_info.hit_point;
-        *attentuation = self.albedo;
-        scattered_ray.direction.dot(&hit_info.hit_normal) > 0.0
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        let normal = facing_normal(hit_info);
+        let mirror_dir = reflect(&incoming_ray.direction, &normal);
+        let albedo = self.albedo.value(hit_info.uv, &hit_info.hit_point);
+        let roughness = self.roughness_at(hit_info);
+
+        if roughness <= 0.0 {
+            return Some(ScatterRecord {
+                scattered_ray: Ray {
+                    origin: hit_info.hit_point,
+                    direction: mirror_dir,
+                    wavelength_nm: incoming_ray.wavelength_nm,
+                },
+                attentuation: albedo,
+                pdf: 0.0,
+                is_specular: true,
+            });
+        }
+
+        let cos_alpha = rand::random::<f32>().powf(1.0 / (phong_exponent(roughness) + 1.0));
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+        let phi = 2.0 * PI * rand::random::<f32>();
+        let direction = local_to_world(
+            &Vec3::new(sin_alpha * phi.cos(), sin_alpha * phi.sin(), cos_alpha),
+            &mirror_dir,
+        )
+        .normalize();
+
+        // the lobe reaches below the surface, these rays get absorbed
+        if direction.dot(&normal) <= 0.0 {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            scattered_ray: Ray {
+                origin: hit_info.hit_point,
+                direction,
+                wavelength_nm: incoming_ray.wavelength_nm,
+            },
+            attentuation: albedo,
+            pdf: lobe_pdf(roughness, &mirror_dir, &direction),
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        let roughness = self.roughness_at(hit_info);
+        if roughness <= 0.0 {
+            return Vec3::zero();
+        }
+        let normal = facing_normal(hit_info);
+        if scattered_dir.dot(&normal) <= 0.0 {
+            return Vec3::zero();
+        }
+        let mirror_dir = reflect(incoming_dir, &normal);
+        self.albedo.value(hit_info.uv, &hit_info.hit_point)
+            * lobe_pdf(roughness, &mirror_dir, scattered_dir)
+    }
+
+    fn pdf(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+        let roughness = self.roughness_at(hit_info);
+        if roughness <= 0.0 {
+            return 0.0;
+        }
+        let normal = facing_normal(hit_info);
+        if scattered_dir.dot(&normal) <= 0.0 {
+            return 0.0;
+        }
+        let mirror_dir = reflect(incoming_dir, &normal);
+        lobe_pdf(roughness, &mirror_dir, scattered_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metal;
+    use crate::materials::hit_on;
+    use crate::vec3::Vec3;
+    use crate::{Ray, RayScattering};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_metal_sample_matches_eval_and_pdf() {
+        let material = Metal {
+            albedo: Vec3::new(0.9, 0.8, 0.7).into(),
+            roughness: 0.3.into(),
+        };
+        let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), true);
+        let incoming_ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+
+        for _i in 0..100 {
+            if let Some(rec) = material.sample(&incoming_ray, &hit_info) {
+                let direction = rec.scattered_ray.direction;
+                assert!(direction.y > 0.0);
+                let pdf = material.pdf(&incoming_ray.direction, &direction, &hit_info);
+                assert!((pdf - rec.pdf).abs() <= 1e-4 * pdf.max(1.0));
+                let weight =
+                    material.eval(&incoming_ray.direction, &direction, &hit_info) * (1.0 / pdf);
+                assert!((weight - rec.attentuation).length() < 1e-4);
+                assert_eq!(rec.attentuation, Vec3::new(0.9, 0.8, 0.7));
+            }
+        }
+    }
+
+    #[test]
+    fn test_smooth_metal_is_a_mirror() {
+        let material = Metal {
+            albedo: Vec3::new(0.9, 0.9, 0.9).into(),
+            roughness: 0.0.into(),
+        };
+        let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), true);
+        let incoming_ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+
+        let rec = material.sample(&incoming_ray, &hit_info).unwrap();
+        assert!(rec.is_specular);
+        assert!(
+            (rec.scattered_ray.direction - Vec3::new(0.0, 1.0, -1.0).normalize()).length() < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_roughest_metal_pdf_integrates_to_one() {
+        let mut rng = StdRng::seed_from_u64(28);
+        for roughness in [1.0, 1.5, 4.0] {
+            let material = Metal {
+                albedo: Vec3::new(0.9, 0.9, 0.9).into(),
+                roughness: roughness.into(),
+            };
+            let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), true);
+            // at normal incidence the whole lobe lies above the surface
+            let incoming_dir = Vec3::new(0.0, -1.0, 0.0);
+            let num_samples = 100_000;
+            let mut integral = 0.0;
+            for _i in 0..num_samples {
+                let z = 1.0 - 2.0 * rng.gen::<f32>();
+                let phi = 2.0 * PI * rng.gen::<f32>();
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let direction = Vec3::new(r * phi.cos(), z, r * phi.sin());
+                let pdf = material.pdf(&incoming_dir, &direction, &hit_info);
+                assert!(pdf >= 0.0);
+                integral += pdf;
+            }
+            integral *= 4.0 * PI / num_samples as f32;
+            assert!((integral - 1.0).abs() < 0.02, "{}", integral);
+
+            let incoming_ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+            for _i in 0..100 {
+                if let Some(rec) = material.sample(&incoming_ray, &hit_info) {
+                    assert!(rec.pdf > 0.0 && rec.pdf.is_finite());
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use crate::alpha::AlphaCutout;
+use crate::dielectric::Dielectric;
+use crate::lambertian::Lambertian;
+use crate::materials::RayScattering;
+use crate::metal::Metal;
+use crate::texture::{ImageTexture, Texture};
+use crate::vec3::Vec3;
+
+/// albedo of faces without material and of .mtl materials without Kd
+pub const DEFAULT_MTL_ALBEDO: f32 = 0.8;
+
+fn to_vec3(color: [f32; 3]) -> Vec3 {
+    Vec3::new(color[0], color[1], color[2])
+}
+
+/// roughness of the phong lobe with exponent shininess (Ns), see Metal
+fn roughness_from_shininess(shininess: f32) -> f32 {
+    (2.0 / (shininess.max(0.0) + 2.0)).sqrt()
+}
+
+/// texture statements may contain options before the filename, e.g. "-bm 0.5 bump.png"
+fn texture_filepath(texture_statement: &str, texture_dir: &Path) -> String {
+    let filename = texture_statement
+        .split_whitespace()
+        .last()
+        .unwrap_or(texture_statement);
+    texture_dir.join(filename).to_string_lossy().into_owned()
+}
+
+/// closest rbrt material to a material of a .mtl file,
+/// texture paths are relative to texture_dir
+/// * refracting materials (illum 4, 6, 7) become clear dielectrics with ior Ni
+/// * reflective materials (illum 3, 5) and highlights brighter than the diffuse color
+///   (illum 2 with Ks above Kd and no map_Kd) become metals with albedo Ks and roughness from Ns
+/// * all other materials become lambertian with albedo Kd or map_Kd
+/// * dissolved materials (d < 1 or map_d) are cut out with the dissolve as opacity
+pub fn material_from_mtl(
+    mtl: &tobj::Material,
+    texture_dir: &Path,
+) -> Box<dyn RayScattering + Sync> {
+    let illum = mtl.illumination_model.unwrap_or(2);
+
+    if matches!(illum, 4 | 6 | 7) {
+        // exporters often write Ni 1.0 for glass, their d describes the refraction
+        let ior = mtl.optical_density.filter(|ior| *ior > 1.0).unwrap_or(1.5);
+        return Box::new(Dielectric::new(ior));
+    }
+
+    let material = opaque_material_from_mtl(mtl, illum, texture_dir);
+    let alpha: Box<dyn Texture> = match (&mtl.dissolve_texture, mtl.dissolve) {
+        (Some(texture_statement), _) => Box::new(ImageTexture::load_opacity(&texture_filepath(
+            texture_statement,
+            texture_dir,
+        ))),
+        (None, Some(dissolve)) if dissolve < 1.0 => dissolve.max(0.0).into(),
+        _ => return material,
+    };
+    Box::new(AlphaCutout {
+        material,
+        alpha,
+        threshold: None,
+    })
+}
+
+fn opaque_material_from_mtl(
+    mtl: &tobj::Material,
+    illum: u8,
+    texture_dir: &Path,
+) -> Box<dyn RayScattering + Sync> {
+    // illum 2 is the diffuse color with a highlight, e.g. glossy plastic, only the reflective
+    // models are metals
+    if matches!(illum, 3 | 5) {
+        return Box::new(Metal {
+            albedo: mtl
+                .specular
+                .map_or(Vec3::new(1.0, 1.0, 1.0), to_vec3)
+                .into(),
+            roughness: roughness_from_shininess(mtl.shininess.unwrap_or(0.0)).into(),
+        });
+    }
+
+    let albedo: Box<dyn Texture> = match &mtl.diffuse_texture {
+        Some(texture_statement) => Box::new(ImageTexture::load(
+            &texture_filepath(texture_statement, texture_dir),
+            true,
+        )),
+        None => to_vec3(mtl.diffuse.unwrap_or([DEFAULT_MTL_ALBEDO; 3])).into(),
+    };
+    Box::new(Lambertian { albedo })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{material_from_mtl, roughness_from_shininess, texture_filepath};
+    use crate::materials::hit_on;
+    use crate::vec3::Vec3;
+    use crate::Ray;
+    use std::f32::consts::PI;
+    use std::path::Path;
+
+    #[test]
+    fn test_dissolve_becomes_opacity() {
+        let mtl = tobj::Material {
+            diffuse: Some([0.5, 0.5, 0.5]),
+            dissolve: Some(0.3),
+            ..Default::default()
+        };
+        let material = material_from_mtl(&mtl, Path::new("."));
+        let hit_info = hit_on(material.as_ref(), Vec3::new(0.0, 1.0, 0.0), true);
+        assert!((material.opacity(&hit_info) - 0.3).abs() < 1e-6);
+        // the surface that is left is still the diffuse material, not glass
+        let incoming_dir = Vec3::new(0.0, -1.0, -1.0);
+        let color = material.eval(&incoming_dir, &Vec3::new(0.0, 1.0, 0.0), &hit_info);
+        assert!((color - Vec3::new(0.5, 0.5, 0.5) * (1.0 / PI)).length() < 1e-5);
+
+        let opaque = material_from_mtl(&tobj::Material::default(), Path::new("."));
+        assert_eq!(
+            opaque.opacity(&hit_on(opaque.as_ref(), Vec3::new(0.0, 1.0, 0.0), true)),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_reflective_illum_becomes_metal() {
+        let incoming_ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+        let mirror_dir = Vec3::new(0.0, 1.0, -1.0).normalize();
+
+        let shiny = tobj::Material {
+            diffuse: Some([0.1, 0.1, 0.1]),
+            specular: Some([0.9, 0.9, 0.9]),
+            shininess: Some(10000.0),
+            illumination_model: Some(3),
+            ..Default::default()
+        };
+        let material = material_from_mtl(&shiny, Path::new("."));
+        let hit_info = hit_on(material.as_ref(), Vec3::new(0.0, 1.0, 0.0), true);
+        for _i in 0..100 {
+            if let Some(rec) = material.sample(&incoming_ray, &hit_info) {
+                assert!(rec.scattered_ray.direction.normalize().dot(&mirror_dir) > 0.99);
+            }
+        }
+
+        // a highlight brighter than the diffuse color, e.g. dark glossy plastic, stays lambertian
+        let plastic = tobj::Material {
+            diffuse: Some([0.1, 0.1, 0.1]),
+            specular: Some([0.5, 0.5, 0.5]),
+            shininess: Some(10000.0),
+            illumination_model: Some(2),
+            ..Default::default()
+        };
+        let material = material_from_mtl(&plastic, Path::new("."));
+        let hit_info = hit_on(material.as_ref(), Vec3::new(0.0, 1.0, 0.0), true);
+        let color = material.eval(
+            &incoming_ray.direction,
+            &Vec3::new(0.0, 1.0, 0.0),
+            &hit_info,
+        );
+        assert!((color - Vec3::new(0.1, 0.1, 0.1) * (1.0 / PI)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_roughness_from_shininess() {
+        assert_eq!(roughness_from_shininess(0.0), 1.0);
+        let roughness = roughness_from_shininess(98.0);
+        // inverse of the phong exponent of Metal
+        assert!((2.0 / roughness.powi(2) - 2.0 - 98.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_texture_filepath() {
+        let dir = Path::new("models");
+        assert_eq!(
+            texture_filepath("wood.png", dir),
+            Path::new("models").join("wood.png").to_string_lossy()
+        );
+        assert_eq!(
+            texture_filepath("-bm 0.5 -clamp on wood.png", dir),
+            Path::new("models").join("wood.png").to_string_lossy()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::vec3::Vec3;
+
+/// Ken Perlin's improved gradient noise,
+/// see https://mrl.cs.nyu.edu/~perlin/noise/
+pub struct Perlin {
+    /// permutation of 0..256, repeated once to avoid wrapping the indices
+    permutation: Vec<usize>,
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// dot product of (x,y,z) with one of 12 gradient directions picked by the hash
+fn grad(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+impl Perlin {
+    /// the same seed always produces the same noise
+    pub fn new(seed: u64) -> Perlin {
+        let mut permutation: Vec<usize> = (0..256).collect();
+        permutation.shuffle(&mut StdRng::seed_from_u64(seed));
+        permutation.extend_from_within(..);
+        Perlin { permutation }
+    }
+
+    /// smooth noise in [-1, 1]
+    pub fn noise(&self, point: &Vec3) -> f32 {
+        let p = &self.permutation;
+        let (xf, yf, zf) = (point.x.floor(), point.y.floor(), point.z.floor());
+        let xi = (xf as i64 & 255) as usize;
+        let yi = (yf as i64 & 255) as usize;
+        let zi = (zf as i64 & 255) as usize;
+        let (x, y, z) = (point.x - xf, point.y - yf, point.z - zf);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a = p[xi] + yi;
+        let aa = p[a] + zi;
+        let ab = p[a + 1] + zi;
+        let b = p[xi + 1] + yi;
+        let ba = p[b] + zi;
+        let bb = p[b + 1] + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// fractal brownian motion: sum of octaves of noise with doubling frequency and
+    /// halving amplitude, roughly in [-1, 1]
+    pub fn fbm(&self, point: &Vec3, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut norm = 0.0;
+        for _octave in 0..octaves {
+            sum += amplitude * self.noise(&(frequency * *point));
+            norm += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / norm
+    }
+
+    /// like fbm, but sums the absolute values of the octaves, in [0, 1]
+    pub fn turbulence(&self, point: &Vec3, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut norm = 0.0;
+        for _octave in 0..octaves {
+            sum += amplitude * self.noise(&(frequency * *point)).abs();
+            norm += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / norm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Perlin;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_noise_range_and_determinism() {
+        let perlin = Perlin::new(42);
+        let same_perlin = Perlin::new(42);
+        let other_perlin = Perlin::new(7);
+        let mut differs = false;
+        for i in 0..1000 {
+            let point = Vec3::new(i as f32 * 0.137, i as f32 * -0.071, i as f32 * 0.293);
+            let noise = perlin.noise(&point);
+            assert!((-1.0..=1.0).contains(&noise));
+            assert_eq!(noise, same_perlin.noise(&point));
+            differs |= noise != other_perlin.noise(&point);
+
+            assert!((-1.0..=1.0).contains(&perlin.fbm(&point, 5)));
+            assert!((0.0..=1.0).contains(&perlin.turbulence(&point, 5)));
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_noise_vanishes_on_lattice() {
+        let perlin = Perlin::new(0);
+        assert_eq!(perlin.noise(&Vec3::new(3.0, -2.0, 7.0)), 0.0);
+        assert!(perlin.noise(&Vec3::new(3.3, -2.6, 7.8)) != 0.0);
+    }
+}
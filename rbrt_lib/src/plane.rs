@@ -0,0 +1,229 @@
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+/// in-plane axes (u, v) of a plane with the unit normal, u runs along the x axis projected
+/// onto the plane (the z axis for planes facing along x), u, v and the normal are right handed
+pub fn plane_axes(normal: &Vec3) -> (Vec3, Vec3) {
+    let reference = if normal.x.abs() > 0.999 {
+        Vec3::new(0.0, 0.0, 1.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let u_axis = (reference - reference.dot(normal) * *normal).normalize();
+    (u_axis, normal.cross_product(&u_axis))
+}
+
+/// distance along the ray and point where it crosses the plane, if within min_dist and max_dist
+fn intersect_plane(
+    ray: &Ray,
+    point: &Vec3,
+    normal: &Vec3,
+    min_dist: f32,
+    max_dist: f32,
+) -> Option<(f32, Vec3)> {
+    let direction = ray.direction.normalize();
+    let denom = direction.dot(normal);
+    if denom.abs() < 1e-8 {
+        // parallel to the plane
+        return None;
+    }
+    let dist = (*point - ray.origin).dot(normal) / denom;
+    if dist < min_dist || dist > max_dist {
+        return None;
+    }
+    Some((dist, ray.origin + dist * direction))
+}
+
+/// infinite plane through point, or a rectangle of size (along u, along v) centered at point,
+/// see plane_axes for the directions u and v
+pub struct Plane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub size: Option<(f32, f32)>,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+impl Intersectable for Plane {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let normal = self.normal.normalize();
+        let (dist, hit_point) = intersect_plane(ray, &self.point, &normal, min_dist, max_dist)?;
+        let (u_axis, v_axis) = plane_axes(&normal);
+        let offset = hit_point - self.point;
+        let (u, v) = (offset.dot(&u_axis), offset.dot(&v_axis));
+
+        // infinite planes are textured in scene units, rectangles from 0 to 1
+        let (uv, tangent, bitangent) = match self.size {
+            None => ((u, v), u_axis, v_axis),
+            Some((size_u, size_v)) => {
+                if u.abs() > 0.5 * size_u || v.abs() > 0.5 * size_v {
+                    return None;
+                }
+                (
+                    (u / size_u + 0.5, v / size_v + 0.5),
+                    size_u * u_axis,
+                    size_v * v_axis,
+                )
+            }
+        };
+        Some(HitInformation {
+            hit_point,
+            geometric_normal: normal,
+            shading_normal: normal,
+            front_face: ray.direction.dot(&normal) < 0.0,
+            uv,
+            tangent,
+            bitangent,
+            hit_material: &*self.material,
+            vertex_color: None,
+            dist_from_ray_orig: dist,
+        })
+    }
+}
+
+/// hit with a disk of radius around center with a hole of inner_radius, shared with the caps
+/// of cylinders and cones, textured like the square rectangle that encloses the disk
+#[allow(clippy::too_many_arguments)]
+pub fn intersect_disk<'a>(
+    ray: &Ray,
+    center: &Vec3,
+    normal: &Vec3,
+    radius: f32,
+    inner_radius: f32,
+    material: &'a dyn RayScattering,
+    min_dist: f32,
+    max_dist: f32,
+) -> Option<HitInformation<'a>> {
+    let normal = normal.normalize();
+    let (dist, hit_point) = intersect_plane(ray, center, &normal, min_dist, max_dist)?;
+    let offset = hit_point - *center;
+    let dist_from_center = offset.length();
+    if dist_from_center > radius || dist_from_center < inner_radius {
+        return None;
+    }
+    let (u_axis, v_axis) = plane_axes(&normal);
+    let diameter = 2.0 * radius;
+    Some(HitInformation {
+        hit_point,
+        geometric_normal: normal,
+        shading_normal: normal,
+        front_face: ray.direction.dot(&normal) < 0.0,
+        uv: (
+            offset.dot(&u_axis) / diameter + 0.5,
+            offset.dot(&v_axis) / diameter + 0.5,
+        ),
+        tangent: diameter * u_axis,
+        bitangent: diameter * v_axis,
+        hit_material: material,
+        vertex_color: None,
+        dist_from_ray_orig: dist,
+    })
+}
+
+/// disk with a hole of inner_radius (0.0 for a full disk)
+pub struct Disk {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub inner_radius: f32,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+impl Intersectable for Disk {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        intersect_disk(
+            ray,
+            &self.center,
+            &self.normal,
+            self.radius,
+            self.inner_radius,
+            &*self.material,
+            min_dist,
+            max_dist,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plane_axes, Disk, Plane};
+    use crate::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    #[test]
+    fn test_plane_axes() {
+        for normal in [
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.3, -0.4, 0.8).normalize(),
+        ] {
+            let (u_axis, v_axis) = plane_axes(&normal);
+            assert!(u_axis.dot(&normal).abs() < 1e-6 && v_axis.dot(&normal).abs() < 1e-6);
+            assert!((u_axis.cross_product(&v_axis) - normal).length() < 1e-6);
+        }
+        // the ground is textured along x and -z
+        let (u_axis, v_axis) = plane_axes(&Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(u_axis, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(v_axis, Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_ground_plane_and_rectangle() {
+        let new_plane = |size| Plane {
+            point: Vec3::new(0.0, -1.0, 0.0),
+            normal: Vec3::new(0.0, 2.0, 0.0),
+            size,
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        };
+        let ground = new_plane(None);
+        // far away hits stay exact, unlike with a huge sphere
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, -1.0, -500.0));
+        let hit_info = ground.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.hit_point - Vec3::new(0.0, -1.0, -500.0)).length() < 1e-3);
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!((hit_info.uv.1 - 500.0).abs() < 1e-3);
+        // parallel and facing away
+        let ray = Ray::new(Vec3::zero(), Vec3::new(1.0, 0.0, 0.0));
+        assert!(ground.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 1.0, 0.0));
+        assert!(ground.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+
+        let rectangle = new_plane(Some((2.0, 4.0)));
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 1.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_info = rectangle.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.uv.0 - 0.75).abs() < 1e-6 && (hit_info.uv.1 - 0.25).abs() < 1e-6);
+        let ray = Ray::new(Vec3::new(1.5, 0.0, 1.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(rectangle.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn test_annulus() {
+        let annulus = Disk {
+            center: Vec3::new(0.0, 0.0, -5.0),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            radius: 2.0,
+            inner_radius: 1.0,
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        };
+        let hit_at = |x: f32| {
+            annulus.intersect_with_ray(
+                &Ray::new(Vec3::new(x, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+                1e-3,
+                f32::MAX,
+            )
+        };
+        assert!(hit_at(0.5).is_none());
+        assert!((hit_at(1.5).unwrap().dist_from_ray_orig - 5.0).abs() < 1e-6);
+        assert!(hit_at(2.5).is_none());
+    }
+}
@@ -0,0 +1,457 @@
+use std::fs;
+
+use crate::lambertian::Lambertian;
+use crate::mesh::{compute_vertex_normals, transform_mesh_data, MeshData};
+use crate::texture::srgb_to_linear;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> ScalarType {
+        match name {
+            "char" | "int8" => ScalarType::Int8,
+            "uchar" | "uint8" => ScalarType::UInt8,
+            "short" | "int16" => ScalarType::Int16,
+            "ushort" | "uint16" => ScalarType::UInt16,
+            "int" | "int32" => ScalarType::Int32,
+            "uint" | "uint32" => ScalarType::UInt32,
+            "float" | "float32" => ScalarType::Float32,
+            "double" | "float64" => ScalarType::Float64,
+            _ => panic!("Unknown PLY property type {}", name),
+        }
+    }
+
+    fn num_bytes(&self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(ScalarType),
+    /// number of items followed by the items
+    List(ScalarType, ScalarType),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<(String, Property)>,
+}
+
+/// reads the values of the elements after the header, all values are converted to f64
+struct BodyReader<'a> {
+    format: PlyFormat,
+    body: &'a [u8],
+    position: usize,
+}
+
+impl BodyReader<'_> {
+    fn next_ascii_token(&mut self) -> &str {
+        while self.position < self.body.len() && self.body[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        let start = self.position;
+        while self.position < self.body.len() && !self.body[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.body[start..self.position])
+            .expect("PLY file contains invalid ascii data")
+    }
+
+    fn read(&mut self, scalar_type: ScalarType) -> f64 {
+        if self.format == PlyFormat::Ascii {
+            let token = self.next_ascii_token();
+            return token
+                .parse()
+                .unwrap_or_else(|_| panic!("Failed to parse PLY value {:?}", token));
+        }
+        let num_bytes = scalar_type.num_bytes();
+        let mut bytes = [0u8; 8];
+        bytes[..num_bytes].copy_from_slice(
+            self.body
+                .get(self.position..self.position + num_bytes)
+                .expect("PLY file ends unexpectedly"),
+        );
+        self.position += num_bytes;
+        if self.format == PlyFormat::BinaryBigEndian {
+            bytes[..num_bytes].reverse();
+        }
+        match scalar_type {
+            ScalarType::Int8 => bytes[0] as i8 as f64,
+            ScalarType::UInt8 => bytes[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::Int32 => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ScalarType::UInt32 => {
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ScalarType::Float32 => {
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ScalarType::Float64 => f64::from_le_bytes(bytes),
+        }
+    }
+}
+
+/// returns the format, the elements and the offset of the body
+fn parse_header(content: &[u8]) -> (PlyFormat, Vec<Element>, usize) {
+    let end_marker = b"end_header";
+    let end_idx = content
+        .windows(end_marker.len())
+        .position(|window| window == end_marker)
+        .expect("PLY file has no end_header");
+    // the body starts after the line break following end_header
+    let mut body_offset = end_idx + end_marker.len();
+    while body_offset < content.len() && content[body_offset] != b'\n' {
+        body_offset += 1;
+    }
+    body_offset += 1;
+
+    let header = String::from_utf8_lossy(&content[..end_idx]);
+    let mut lines = header.lines();
+    assert_eq!(
+        lines.next().map(|line| line.trim()),
+        Some("ply"),
+        "File is not a PLY file"
+    );
+    let mut format = PlyFormat::Ascii;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", format_name, ..] => {
+                format = match *format_name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => panic!("Unknown PLY format {}", format_name),
+                }
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().expect("Invalid PLY element count"),
+                properties: vec![],
+            }),
+            ["property", "list", count_type, item_type, name] => elements
+                .last_mut()
+                .expect("PLY property outside of element")
+                .properties
+                .push((
+                    name.to_string(),
+                    Property::List(ScalarType::parse(count_type), ScalarType::parse(item_type)),
+                )),
+            ["property", scalar_type, name] => elements
+                .last_mut()
+                .expect("PLY property outside of element")
+                .properties
+                .push((
+                    name.to_string(),
+                    Property::Scalar(ScalarType::parse(scalar_type)),
+                )),
+            _ => {} // comments, obj_info
+        }
+    }
+    (format, elements, body_offset)
+}
+
+/// vertex attributes that rbrt uses
+struct PlyVertex {
+    position: Vec3,
+    normal: Option<Vec3>,
+    color: Option<Vec3>,
+    tex_coords: Option<(f32, f32)>,
+}
+
+fn parse_vertex(element: &Element, values: &[f64]) -> PlyVertex {
+    let value = |names: &[&str]| {
+        element
+            .properties
+            .iter()
+            .position(|(name, _)| names.contains(&name.as_str()))
+            .map(|idx| values[idx] as f32)
+    };
+    let vector =
+        |names: [&str; 3]| match (value(&[names[0]]), value(&[names[1]]), value(&[names[2]])) {
+            (Some(x), Some(y), Some(z)) => Some(Vec3::new(x, y, z)),
+            _ => None,
+        };
+    // 8 bit colors are sRGB encoded, floating point colors are linear
+    let color_channel = |name: &str| {
+        let idx = element
+            .properties
+            .iter()
+            .position(|(property_name, _)| property_name == name)?;
+        Some(match element.properties[idx].1 {
+            Property::Scalar(ScalarType::UInt8) => srgb_to_linear(values[idx] as f32 / 255.0),
+            _ => values[idx] as f32,
+        })
+    };
+    PlyVertex {
+        position: vector(["x", "y", "z"]).expect("PLY vertex without position"),
+        normal: vector(["nx", "ny", "nz"]),
+        color: match (
+            color_channel("red"),
+            color_channel("green"),
+            color_channel("blue"),
+        ) {
+            (Some(red), Some(green), Some(blue)) => Some(Vec3::new(red, green, blue)),
+            _ => None,
+        },
+        tex_coords: match (
+            value(&["u", "s", "texture_u", "texture_s"]),
+            value(&["v", "t", "texture_v", "texture_t"]),
+        ) {
+            (Some(u), Some(v)) => Some((u, v)),
+            _ => None,
+        },
+    }
+}
+
+/// Loads mesh from an ascii or binary ply file, scales, rotates and translates it
+/// faces with more than three vertices are triangulated as fans
+/// vertex colors tint a white lambertian material, colorize interpolates them at the hit point
+pub fn load_mesh_from_ply_file(
+    filepath: &str,
+    translation: Vec3,
+    rotation: Vec3,
+    scale: f32,
+) -> MeshData {
+    let content = match fs::read(filepath) {
+        Ok(content) => content,
+        Err(error) => panic!("Failed to open {}: {:?}", filepath, error),
+    };
+    let (format, elements, body_offset) = parse_header(&content);
+    let mut reader = BodyReader {
+        format,
+        body: &content[body_offset.min(content.len())..],
+        position: 0,
+    };
+
+    let mut vertices: Vec<PlyVertex> = vec![];
+    let mut faces: Vec<Vec<usize>> = vec![];
+    for element in &elements {
+        for _element_idx in 0..element.count {
+            let mut values = vec![];
+            let mut indices = vec![];
+            for (name, property) in &element.properties {
+                match property {
+                    Property::Scalar(scalar_type) => values.push(reader.read(*scalar_type)),
+                    Property::List(count_type, item_type) => {
+                        let count = reader.read(*count_type) as usize;
+                        let items: Vec<f64> =
+                            (0..count).map(|_item| reader.read(*item_type)).collect();
+                        // keep the property index aligned with values
+                        values.push(0.0);
+                        if name == "vertex_indices" || name == "vertex_index" {
+                            indices = items.iter().map(|idx| *idx as usize).collect();
+                        }
+                    }
+                }
+            }
+            match element.name.as_str() {
+                "vertex" => vertices.push(parse_vertex(element, &values)),
+                "face" => faces.push(indices),
+                _ => {}
+            }
+        }
+    }
+
+    let mut mesh_data = MeshData {
+        vertices: vec![],
+        tex_coords: vec![],
+        vertex_normals: vec![],
+        material_ids: vec![],
+        vertex_colors: None,
+        materials: vec![],
+    };
+    let has_normals = vertices.iter().all(|vertex| vertex.normal.is_some());
+    let has_tex_coords = vertices.iter().all(|vertex| vertex.tex_coords.is_some());
+    let mut vertex_colors = vec![];
+    for face in &faces {
+        for fan_idx in 1..face.len().saturating_sub(1) {
+            let corners = [face[0], face[fan_idx], face[fan_idx + 1]].map(|vertex_idx| {
+                vertices
+                    .get(vertex_idx)
+                    .unwrap_or_else(|| panic!("PLY face references missing vertex {}", vertex_idx))
+            });
+            mesh_data
+                .vertices
+                .push(corners.map(|vertex| vertex.position));
+            mesh_data.tex_coords.push(if has_tex_coords {
+                corners.map(|vertex| vertex.tex_coords.unwrap())
+            } else {
+                [(0.0, 0.0); 3]
+            });
+            if has_normals {
+                mesh_data
+                    .vertex_normals
+                    .push(corners.map(|vertex| vertex.normal.unwrap().normalize()));
+            }
+            if let [Some(color_a), Some(color_b), Some(color_c)] =
+                corners.map(|vertex| vertex.color)
+            {
+                vertex_colors.push([color_a, color_b, color_c]);
+            }
+        }
+    }
+    if !has_normals {
+        mesh_data.vertex_normals = compute_vertex_normals(&mesh_data.vertices);
+    }
+
+    if !mesh_data.vertices.is_empty() && vertex_colors.len() == mesh_data.vertices.len() {
+        mesh_data.vertex_colors = Some(vertex_colors);
+        // the vertex colors are the albedo
+        mesh_data
+            .materials
+            .push(Box::new(Lambertian { albedo: 1.0.into() }));
+        mesh_data.material_ids = vec![Some(0); mesh_data.vertices.len()];
+    } else {
+        mesh_data.material_ids = vec![None; mesh_data.vertices.len()];
+    }
+
+    transform_mesh_data(
+        &mut mesh_data,
+        &Transform::from_scale_rotation_translation(scale, rotation, translation),
+    );
+    println!(
+        "Successfully loaded {} triangles from file {}!",
+        mesh_data.vertices.len(),
+        filepath
+    );
+    mesh_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load_mesh_from_ply_file;
+    use crate::mesh::TriangleMesh;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    const HEADER_PROPERTIES: &str = "element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    fn check_quad(filename: &str, content: &[u8]) {
+        let filepath = std::env::temp_dir().join(filename);
+        std::fs::write(&filepath, content).unwrap();
+        let mesh_data =
+            load_mesh_from_ply_file(filepath.to_str().unwrap(), Vec3::zero(), Vec3::zero(), 2.0);
+
+        // the quad is split into two triangles
+        assert_eq!(mesh_data.vertices.len(), 2);
+        assert_eq!(mesh_data.vertices[0][1], Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(mesh_data.vertices[1][2], Vec3::new(0.0, 2.0, 0.0));
+        assert!((mesh_data.vertex_normals[0][0] - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+        // vertex colors tint a white lambertian
+        assert!(mesh_data.vertex_colors.is_some());
+        assert_eq!(mesh_data.materials.len(), 1);
+        assert_eq!(mesh_data.material_ids, vec![Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn test_load_ascii_ply() {
+        let content = format!(
+            "ply\nformat ascii 1.0\ncomment test quad\n{}\
+             0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n",
+            HEADER_PROPERTIES
+        );
+        check_quad("rbrt_test_ascii.ply", content.as_bytes());
+    }
+
+    #[test]
+    fn test_load_binary_ply() {
+        for (format, big_endian) in [("binary_little_endian", false), ("binary_big_endian", true)] {
+            let mut content = format!("ply\nformat {} 1.0\n{}", format, HEADER_PROPERTIES)
+                .as_bytes()
+                .to_vec();
+            let positions = [
+                [0.0f32, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ];
+            for position in positions {
+                for coord in position {
+                    if big_endian {
+                        content.extend(coord.to_be_bytes());
+                    } else {
+                        content.extend(coord.to_le_bytes());
+                    }
+                }
+                content.extend([128u8, 128, 128]);
+            }
+            content.push(4);
+            for idx in [0i32, 1, 2, 3] {
+                if big_endian {
+                    content.extend(idx.to_be_bytes());
+                } else {
+                    content.extend(idx.to_le_bytes());
+                }
+            }
+            check_quad(&format!("rbrt_test_{}.ply", format), &content);
+        }
+    }
+
+    #[test]
+    fn test_ply_vertex_colors_are_interpolated() {
+        let content = format!(
+            "ply\nformat ascii 1.0\n{}\
+             0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 0 0 0\n3 0 1 2\n",
+            HEADER_PROPERTIES
+        );
+        let filepath = std::env::temp_dir().join("rbrt_test_colors.ply");
+        std::fs::write(&filepath, content).unwrap();
+        let mesh_data =
+            load_mesh_from_ply_file(filepath.to_str().unwrap(), Vec3::zero(), Vec3::zero(), 1.0);
+        let mesh = TriangleMesh::from_mesh_data(mesh_data, None, true);
+
+        let color_at = |x: f32, y: f32| {
+            let ray = Ray::new(Vec3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            mesh.intersect_with_ray(&ray, 0.0, f32::INFINITY)
+                .unwrap()
+                .vertex_color
+                .unwrap()
+        };
+        // weights 0.1, 0.8 and 0.1 of the red, green and blue corners
+        assert!((color_at(0.9, 0.1) - Vec3::new(0.1, 0.8, 0.1)).length() < 1e-5);
+        let third = 1.0 / 3.0;
+        assert!((color_at(2.0 / 3.0, third) - Vec3::new(third, third, third)).length() < 1e-5);
+    }
+}
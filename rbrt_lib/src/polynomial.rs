@@ -0,0 +1,134 @@
+// real roots of polynomials up to degree four, in double precision,
+// see Schwarze, "Cubic and Quartic Roots", Graphics Gems I
+
+use std::f64::consts::PI;
+
+const EPSILON: f64 = 1e-12;
+
+/// real roots of a*x^2 + b*x + c in ascending order
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < EPSILON {
+        if b.abs() < EPSILON {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    // avoids the cancellation of -b + sqrt(discriminant)
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return vec![0.0];
+    }
+    let (root_a, root_b) = (q / a, c / q);
+    vec![root_a.min(root_b), root_a.max(root_b)]
+}
+
+/// real roots of x^3 + a*x^2 + b*x + c, not sorted
+fn solve_normalized_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // substitute x = y - a/3 to eliminate the quadratic term: y^3 + 3*p*y + 2*q
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let roots = if discriminant.abs() < EPSILON {
+        if q.abs() < EPSILON {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        // three real roots
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_discriminant = discriminant.sqrt();
+        vec![(sqrt_discriminant - q).cbrt() - (sqrt_discriminant + q).cbrt()]
+    };
+    roots.iter().map(|root| root - a / 3.0).collect()
+}
+
+/// real roots of a*x^4 + b*x^3 + c*x^2 + d*x + e in ascending order,
+/// polished with newton iterations
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    let (b_n, c_n, d_n, e_n) = (b / a, c / a, d / a, e / a);
+    // substitute x = y - b/4 to eliminate the cubic term: y^4 + p*y^2 + q*y + r
+    let sq_b = b_n * b_n;
+    let p = -3.0 / 8.0 * sq_b + c_n;
+    let q = sq_b * b_n / 8.0 - b_n * c_n / 2.0 + d_n;
+    let r = -3.0 / 256.0 * sq_b * sq_b + sq_b * c_n / 16.0 - b_n * d_n / 4.0 + e_n;
+
+    let mut roots = if r.abs() < EPSILON {
+        let mut roots = solve_normalized_cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // any root of the resolvent cubic splits the quartic into two quadratics
+        let z = solve_normalized_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -EPSILON || v < -EPSILON {
+            return vec![];
+        }
+        let (u, v) = (u.max(0.0).sqrt(), v.max(0.0).sqrt());
+        let v = if q < 0.0 { -v } else { v };
+        let mut roots = solve_quadratic(1.0, v, z - u);
+        roots.extend(solve_quadratic(1.0, -v, z + u));
+        roots
+    };
+
+    for root in roots.iter_mut() {
+        *root -= b_n / 4.0;
+        for _iteration in 0..2 {
+            let value = (((a * *root + b) * *root + c) * *root + d) * *root + e;
+            let derivative = ((4.0 * a * *root + 3.0 * b) * *root + 2.0 * c) * *root + d;
+            if derivative.abs() > EPSILON {
+                *root -= value / derivative;
+            }
+        }
+    }
+    roots.sort_by(|a, b| a.total_cmp(b));
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{solve_quadratic, solve_quartic};
+
+    #[test]
+    fn test_solve_quadratic() {
+        assert_eq!(solve_quadratic(1.0, -3.0, 2.0), vec![1.0, 2.0]);
+        assert_eq!(solve_quadratic(0.0, 2.0, -4.0), vec![2.0]);
+        assert!(solve_quadratic(1.0, 0.0, 1.0).is_empty());
+        // no cancellation for small roots
+        let roots = solve_quadratic(1.0, 1e8, 1.0);
+        assert!((roots[1] + 1e-8).abs() < 1e-20);
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        // (x - 1)(x + 2)(x - 3)(x - 0.5)
+        let roots = solve_quartic(1.0, -2.5, -4.0, 8.5, -3.0);
+        let expected = [-2.0, 0.5, 1.0, 3.0];
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-9, "{:?}", roots);
+        }
+        // (x^2 + 1)(x - 2)(x + 4) has two real roots
+        let roots = solve_quartic(2.0, 4.0, -14.0, 4.0, -16.0);
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] + 4.0).abs() < 1e-9 && (roots[1] - 2.0).abs() < 1e-9);
+        // x^4 + 1 has none
+        assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+use std::f32::consts::PI;
+
+use crate::perlin::Perlin;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+fn mix(color_a: &Vec3, color_b: &Vec3, t: f32) -> Vec3 {
+    (1.0 - t) * *color_a + t * *color_b
+}
+
+/// 3d checkerboard of cubes with edge length 1/scale
+pub struct CheckerTexture {
+    pub scale: f32,
+    pub even: Box<dyn Texture>,
+    pub odd: Box<dyn Texture>,
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, uv: (f32, f32), point: &Vec3) -> Vec3 {
+        let p = self.scale * *point;
+        let cell_sum = p.x.floor() as i64 + p.y.floor() as i64 + p.z.floor() as i64;
+        if cell_sum.rem_euclid(2) == 0 {
+            self.even.value(uv, point)
+        } else {
+            self.odd.value(uv, point)
+        }
+    }
+}
+
+/// fractal perlin noise blending from black to color
+pub struct NoiseTexture {
+    pub perlin: Perlin,
+    pub scale: f32,
+    pub octaves: u32,
+    pub color: Vec3,
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _uv: (f32, f32), point: &Vec3) -> Vec3 {
+        let noise = self.perlin.fbm(&(self.scale * *point), self.octaves);
+        self.color * (0.5 * (1.0 + noise))
+    }
+}
+
+/// veins along the z axis, distorted by turbulence
+pub struct MarbleTexture {
+    pub perlin: Perlin,
+    pub scale: f32,
+    /// strength of the distortion of the veins
+    pub turbulence: f32,
+    pub octaves: u32,
+    pub base_color: Vec3,
+    pub vein_color: Vec3,
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _uv: (f32, f32), point: &Vec3) -> Vec3 {
+        let p = self.scale * *point;
+        let phase = p.z + self.turbulence * self.perlin.turbulence(&p, self.octaves);
+        let t = 0.5 * (1.0 + phase.sin());
+        mix(&self.vein_color, &self.base_color, t)
+    }
+}
+
+/// concentric growth rings around the y axis, slightly distorted by noise
+pub struct WoodTexture {
+    pub perlin: Perlin,
+    /// number of rings per unit distance from the axis
+    pub ring_frequency: f32,
+    /// strength of the distortion of the rings
+    pub turbulence: f32,
+    pub light_color: Vec3,
+    pub dark_color: Vec3,
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _uv: (f32, f32), point: &Vec3) -> Vec3 {
+        let radius = (point.x * point.x + point.z * point.z).sqrt();
+        let distortion = self.turbulence * self.perlin.fbm(&(4.0 * *point), 4);
+        let rings = (radius + distortion) * self.ring_frequency;
+        // sharp transition from early wood to late wood within each ring
+        let t = (0.5 * (1.0 - (2.0 * PI * rings).cos())).powi(4);
+        mix(&self.light_color, &self.dark_color, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckerTexture, MarbleTexture, WoodTexture};
+    use crate::perlin::Perlin;
+    use crate::texture::Texture;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_checker_texture() {
+        let black = Vec3::zero();
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let checker = CheckerTexture {
+            scale: 2.0,
+            even: white.into(),
+            odd: black.into(),
+        };
+        let uv = (0.0, 0.0);
+        assert_eq!(checker.value(uv, &Vec3::new(0.1, 0.1, 0.1)), white);
+        assert_eq!(checker.value(uv, &Vec3::new(0.6, 0.1, 0.1)), black);
+        assert_eq!(checker.value(uv, &Vec3::new(0.6, 0.6, 0.1)), white);
+        assert_eq!(checker.value(uv, &Vec3::new(-0.1, 0.1, 0.1)), black);
+        assert_eq!(checker.value(uv, &Vec3::new(-0.1, -0.1, -0.1)), black);
+    }
+
+    #[test]
+    fn test_marble_and_wood_stay_between_colors() {
+        let light = Vec3::new(0.9, 0.8, 0.7);
+        let dark = Vec3::new(0.3, 0.2, 0.1);
+        let marble = MarbleTexture {
+            perlin: Perlin::new(1),
+            scale: 3.0,
+            turbulence: 5.0,
+            octaves: 6,
+            base_color: light,
+            vein_color: dark,
+        };
+        let wood = WoodTexture {
+            perlin: Perlin::new(1),
+            ring_frequency: 8.0,
+            turbulence: 0.1,
+            light_color: light,
+            dark_color: dark,
+        };
+        for i in 0..200 {
+            let point = Vec3::new(i as f32 * 0.031, i as f32 * 0.017, i as f32 * -0.023);
+            for texture in [&marble as &dyn Texture, &wood as &dyn Texture] {
+                let color = texture.value((0.0, 0.0), &point);
+                assert!(color.x >= dark.x - 1e-5 && color.x <= light.x + 1e-5);
+                assert!(color.z >= dark.z - 1e-5 && color.z <= light.z + 1e-5);
+            }
+        }
+    }
+}
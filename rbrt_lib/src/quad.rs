@@ -0,0 +1,89 @@
+use crate::triangle::barycentric_coords;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+/// parallelogram spanned by two edges from a corner, textured from 0 to 1 along the edges,
+/// its normal is edges[0] x edges[1], like that of counter clockwise triangles
+pub struct Quad {
+    pub corner: Vec3,
+    pub edges: [Vec3; 2],
+    pub normal: Vec3,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, edges: [Vec3; 2], material: Box<dyn RayScattering + Sync>) -> Quad {
+        Quad {
+            corner,
+            edges,
+            normal: edges[0].cross_product(&edges[1]).normalize(),
+            material,
+        }
+    }
+}
+
+impl Intersectable for Quad {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let direction = ray.direction.normalize();
+        let denom = direction.dot(&self.normal);
+        if denom.abs() < 1e-8 {
+            // parallel to the quad
+            return None;
+        }
+        let dist = (self.corner - ray.origin).dot(&self.normal) / denom;
+        if dist < min_dist || dist > max_dist {
+            return None;
+        }
+        let hit_point = ray.origin + dist * direction;
+        // the weights of the edges are the texture coordinates
+        let uv = barycentric_coords(&hit_point, &self.corner, &self.edges);
+        if !(0.0..=1.0).contains(&uv.0) || !(0.0..=1.0).contains(&uv.1) {
+            return None;
+        }
+        Some(HitInformation {
+            hit_point,
+            geometric_normal: self.normal,
+            shading_normal: self.normal,
+            front_face: ray.direction.dot(&self.normal) < 0.0,
+            uv,
+            tangent: self.edges[0],
+            bitangent: self.edges[1],
+            hit_material: &*self.material,
+            vertex_color: None,
+            dist_from_ray_orig: dist,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quad;
+    use crate::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    #[test]
+    fn test_quad_intersection() {
+        // slanted parallelogram in the plane z = -3
+        let quad = Quad::new(
+            Vec3::new(-1.0, -1.0, -3.0),
+            [Vec3::new(2.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 0.0)],
+            Box::new(Lambertian { albedo: 0.5.into() }),
+        );
+        assert_eq!(quad.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        let ray = Ray::new(Vec3::new(1.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = quad.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 3.0).abs() < 1e-6);
+        assert!((hit_info.uv.0 - 0.875).abs() < 1e-6 && (hit_info.uv.1 - 0.75).abs() < 1e-6);
+
+        // inside of the bounding rectangle but outside of the parallelogram
+        let ray = Ray::new(Vec3::new(-0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(quad.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+    }
+}
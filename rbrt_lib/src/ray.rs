@@ -1,19 +1,97 @@
 use crate::vec3::Vec3;
 
+/// rounding steps that hit points are assumed to accumulate, from the intersection with the
+/// ray up to the transform of instances, chosen generously since the bound is cheap
+const HIT_POINT_ROUNDINGS: u32 = 32;
+
 #[derive(Copy, Clone, Debug)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    /// wavelength carried by the ray in spectral rendering mode
+    pub wavelength_nm: Option<f32>,
 }
 
 impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction,
+            wavelength_nm: None,
+        }
+    }
     pub fn point_at(&self, ray_param: f32) -> Vec3 {
         self.origin + ray_param * self.direction
     }
     pub fn zero() -> Ray {
-        Ray {
-            origin: Vec3::zero(),
-            direction: Vec3::zero(),
+        Ray::new(Vec3::zero(), Vec3::zero())
+    }
+}
+
+/// bound on the relative rounding error of n floating point operations,
+/// see Pharr et al., "Physically Based Rendering", section 3.9
+pub fn gamma(n: u32) -> f32 {
+    let n_eps = n as f32 * 0.5 * f32::EPSILON;
+    n_eps / (1.0 - n_eps)
+}
+
+/// bound on the absolute floating point error per axis of a hit point at distance dist along
+/// a ray with unit direction, the ray origin is no further than dist from the point
+pub fn hit_point_error(point: &Vec3, dist: f32) -> Vec3 {
+    let dist = dist.abs();
+    gamma(HIT_POINT_ROUNDINGS) * (point.abs() + Vec3::new(2.0 * dist, 2.0 * dist, 2.0 * dist))
+}
+
+/// origin of a ray leaving the surface at point along direction: the point is pushed along
+/// the normal out of its error box, to the side of the surface the direction points to,
+/// so that the ray cannot hit the surface it starts on again,
+/// see Pharr et al., "Physically Based Rendering", section 3.9.5
+pub fn offset_ray_origin(point: &Vec3, error: &Vec3, normal: &Vec3, direction: &Vec3) -> Vec3 {
+    let normal_dist = normal.abs().dot(error);
+    let mut offset = normal_dist * *normal;
+    if direction.dot(normal) < 0.0 {
+        offset = -1.0 * offset;
+    }
+    let origin = *point + offset;
+    // the sum is rounded as well, round it away from the point
+    let round_away = |coord: f32, offset: f32| {
+        if offset > 0.0 {
+            coord.next_up()
+        } else if offset < 0.0 {
+            coord.next_down()
+        } else {
+            coord
+        }
+    };
+    Vec3::new(
+        round_away(origin.x, offset.x),
+        round_away(origin.y, offset.y),
+        round_away(origin.z, offset.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gamma, hit_point_error, offset_ray_origin};
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_offset_ray_origin() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        for scale in [1e-3, 1.0, 45.0, 1e4] {
+            let point = Vec3::new(0.3 * scale, 0.0, -2.0 * scale);
+            let error = hit_point_error(&point, scale);
+            assert!(error.x > 0.0 && error.y > 0.0 && error.z > 0.0);
+
+            let above = offset_ray_origin(&point, &error, &normal, &Vec3::new(0.2, 1.0, 0.0));
+            assert!(above.y > error.y);
+            let below = offset_ray_origin(&point, &error, &normal, &Vec3::new(0.0, -1.0, 0.3));
+            assert!(below.y < -error.y);
+            // the offset grows with the scale of the scene, but stays tiny relative to it
+            assert!(above.y < 1e-4 * scale);
+            assert_eq!(above.x, point.x);
         }
+        assert!(gamma(2) > gamma(1));
+        assert!(gamma(1) > 0.5 * f32::EPSILON);
     }
 }
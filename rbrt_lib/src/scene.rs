@@ -1,27 +1,189 @@
+use std::f32::consts::PI;
+
+use crate::csg;
+use crate::materials::local_to_world;
+use crate::medium::{HomogeneousMedium, Medium};
 use crate::mesh::TriangleMesh;
+use crate::triangle::barycentric_coords;
 use crate::vec3::Vec3;
 use crate::HitInformation;
 use crate::Intersectable;
 use crate::Ray;
 
+/// point light (radius 0.0), spherical area light or parallelogram area light
 pub struct Light {
     pub position: Vec3,
+    /// radius of a spherical area light, 0.0 for point lights
+    pub radius: f32,
+    /// emitted radiance of area lights, intensity of point lights
     pub color: Vec3,
+    /// edges of a parallelogram light with a corner at position, it emits to the side
+    /// of edges[0] x edges[1] only
+    pub edges: Option<[Vec3; 2]>,
+}
+
+/// direction towards a light source, drawn by Light::sample
+pub struct LightSample {
+    pub direction: Vec3,
+    pub distance: f32,
+    /// radiance arriving from the light along direction
+    pub radiance: Vec3,
+    /// solid angle pdf of the direction, zero for point lights
+    pub pdf: f32,
+}
+
+impl Light {
+    pub fn is_point_light(&self) -> bool {
+        self.radius <= 0.0 && self.edges.is_none()
+    }
+
+    /// sine and cosine of the half angle of the cone the light subtends from a point at distance
+    fn cone_angle(&self, distance: f32) -> (f32, f32) {
+        let sin_theta_max_sq = (self.radius / distance).powi(2);
+        let cos_theta_max = (1.0 - sin_theta_max_sq).max(0.0).sqrt();
+        (sin_theta_max_sq, cos_theta_max)
+    }
+
+    /// solid angle pdf of the direction from the point towards light_point on the parallelogram
+    fn quad_pdf(edges: &[Vec3; 2], from: &Vec3, light_point: &Vec3) -> f32 {
+        let normal = edges[0].cross_product(&edges[1]);
+        let area = normal.length();
+        let to_light = *light_point - *from;
+        let sq_distance = to_light.dot(&to_light);
+        // cosine at the light, negative if the point is behind it
+        let cos_light = -to_light.dot(&normal) / (area * sq_distance.sqrt());
+        if cos_light <= 0.0 {
+            return 0.0;
+        }
+        sq_distance / (cos_light * area)
+    }
+
+    /// draws a direction from the point towards the light
+    /// spherical lights are sampled uniformly within the cone they subtend,
+    /// parallelogram lights uniformly by area
+    pub fn sample(&self, from: &Vec3) -> Option<LightSample> {
+        if let Some(edges) = &self.edges {
+            let light_point =
+                self.position + rand::random::<f32>() * edges[0] + rand::random::<f32>() * edges[1];
+            let pdf = Light::quad_pdf(edges, from, &light_point);
+            if pdf <= 0.0 {
+                return None;
+            }
+            let to_light = light_point - *from;
+            let distance = to_light.length();
+            return Some(LightSample {
+                direction: to_light * (1.0 / distance),
+                distance,
+                radiance: self.color,
+                pdf,
+            });
+        }
+
+        let to_light = self.position - *from;
+        let distance = to_light.length();
+        if distance <= self.radius {
+            return None;
+        }
+        let to_light = to_light * (1.0 / distance);
+
+        if self.is_point_light() {
+            return Some(LightSample {
+                direction: to_light,
+                distance,
+                radiance: self.color * (1.0 / (distance * distance)),
+                pdf: 0.0,
+            });
+        }
+
+        let (sin_theta_max_sq, cos_theta_max) = self.cone_angle(distance);
+        // 1 - cos_theta_max without cancellation for small lights
+        let one_minus_cos_theta_max = sin_theta_max_sq / (1.0 + cos_theta_max);
+        let cos_theta = 1.0 - rand::random::<f32>() * one_minus_cos_theta_max;
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rand::random::<f32>();
+        let direction = local_to_world(
+            &Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta),
+            &to_light,
+        )
+        .normalize();
+
+        // distance along direction to the near side of the sphere
+        let discr = (self.radius * self.radius - (distance * sin_theta).powi(2)).max(0.0);
+        let surface_distance = distance * cos_theta - discr.sqrt();
+
+        Some(LightSample {
+            direction,
+            distance: surface_distance,
+            radiance: self.color,
+            pdf: 1.0 / (2.0 * PI * one_minus_cos_theta_max),
+        })
+    }
+
+    /// solid angle pdf of sample() drawing the direction from the point towards light_point
+    pub fn pdf(&self, from: &Vec3, light_point: &Vec3) -> f32 {
+        if let Some(edges) = &self.edges {
+            return Light::quad_pdf(edges, from, light_point);
+        }
+        let distance = (self.position - *from).length();
+        if self.is_point_light() || distance <= self.radius {
+            return 0.0;
+        }
+        let (sin_theta_max_sq, cos_theta_max) = self.cone_angle(distance);
+        (1.0 + cos_theta_max) / (2.0 * PI * sin_theta_max_sq)
+    }
+
+    /// checks if the point lies on the surface of an area light
+    pub fn contains_surface_point(&self, point: &Vec3) -> bool {
+        if let Some(edges) = &self.edges {
+            let normal = edges[0].cross_product(&edges[1]).normalize();
+            let scale = edges[0].length().max(edges[1].length()).max(1.0);
+            let (u, v) = barycentric_coords(point, &self.position, edges);
+            return (*point - self.position).dot(&normal).abs() < 1e-3 * scale
+                && (-1e-4..=1.0 + 1e-4).contains(&u)
+                && (-1e-4..=1.0 + 1e-4).contains(&v);
+        }
+        !self.is_point_light()
+            && ((*point - self.position).length() - self.radius).abs() < 1e-3 * self.radius.max(1.0)
+    }
 }
 
 pub struct Scene {
     pub elements: Vec<Box<dyn Intersectable + Sync>>,
     pub triangle_meshes: Vec<TriangleMesh>,
     pub lights: Vec<Light>,
+    /// constant background instead of the default sky gradient
+    pub background_color: Option<Vec3>,
+    /// medium that fills the space outside of all volumetric objects
+    pub fog: Option<HomogeneousMedium>,
+}
+
+/// closest hit of the object that the ray does not pass through, hits on transparent parts
+/// of alpha cut out materials are skipped
+fn opaque_hit<'a>(
+    object: &'a dyn Intersectable,
+    ray: &Ray,
+    min_dist: f32,
+    max_dist: f32,
+) -> Option<HitInformation<'a>> {
+    let mut hit_info = object.intersect_with_ray(ray, min_dist, max_dist)?;
+    for _i in 0..csg::MAX_CROSSINGS {
+        let opacity = hit_info.hit_material.opacity(&hit_info);
+        if opacity >= 1.0 || (opacity > 0.0 && rand::random::<f32>() < opacity) {
+            return Some(hit_info);
+        }
+        hit_info = csg::hit_behind(object, ray, &hit_info, max_dist)?;
+    }
+    None
 }
 
 impl Scene {
-    pub fn hit(&self, ray: &Ray, min_dist: f32, max_dist: f32) -> Option<HitInformation> {
+    /// closest hit along the ray, for camera, scattered and shadow rays alike
+    pub fn hit(&self, ray: &Ray, min_dist: f32, max_dist: f32) -> Option<HitInformation<'_>> {
         let mut closest_hit_rec = None;
         let mut closest_so_far = f32::MAX;
 
         for sphere in &self.elements {
-            let hit_info_op = sphere.intersect_with_ray(ray, min_dist, max_dist);
+            let hit_info_op = opaque_hit(sphere.as_ref(), ray, min_dist, max_dist);
             if let Some(hit_rec) = hit_info_op {
                 if hit_rec.dist_from_ray_orig < closest_so_far {
                     closest_so_far = hit_rec.dist_from_ray_orig;
@@ -31,7 +193,7 @@ impl Scene {
         }
 
         for mesh in &self.triangle_meshes {
-            let hit_info_op = mesh.intersect_with_ray(ray, min_dist, max_dist);
+            let hit_info_op = opaque_hit(mesh, ray, min_dist, max_dist);
             if let Some(hit_rec) = hit_info_op {
                 if hit_rec.dist_from_ray_orig < closest_so_far {
                     closest_so_far = hit_rec.dist_from_ray_orig;
@@ -41,4 +203,374 @@ impl Scene {
         }
         closest_hit_rec
     }
+
+    pub fn fog_medium(&self) -> Option<&dyn Medium> {
+        self.fog.as_ref().map(|fog| fog as &dyn Medium)
+    }
+
+    /// medium that a ray travelling along direction enters at the surface of the hit, the
+    /// medium enclosed by the surface or the fog outside of it
+    pub fn medium_behind<'a>(
+        &'a self,
+        hit_info: &HitInformation<'a>,
+        direction: &Vec3,
+    ) -> Option<&'a dyn Medium> {
+        match hit_info.hit_material.medium() {
+            Some(medium) if direction.dot(&hit_info.geometric_normal) < 0.0 => Some(medium),
+            _ => self.fog_medium(),
+        }
+    }
+
+    /// fraction of light that travels along the ray from min_dist to max_dist, starting in
+    /// medium, zero if an opaque surface is in the way
+    /// invisible medium boundaries are crossed, the ray enters a medium against the normal of
+    /// its boundary and returns to the fog when it leaves
+    /// behind a boundary, the ray continues from its spawn origin
+    pub fn transmittance(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+        medium: Option<&dyn Medium>,
+    ) -> Vec3 {
+        let mut transmittance = Vec3::new(1.0, 1.0, 1.0);
+        let mut medium = medium;
+        let mut ray = *ray;
+        let mut segment_start = min_dist;
+        let mut max_dist = max_dist;
+        loop {
+            let hit_info_op = self.hit(&ray, segment_start, max_dist);
+            let segment_end = hit_info_op.map_or(max_dist, |hit_info| hit_info.dist_from_ray_orig);
+            if let Some(medium) = medium {
+                transmittance =
+                    transmittance * medium.transmittance(&ray, segment_start, segment_end);
+            }
+            let hit_info = match hit_info_op {
+                Some(hit_info) => hit_info,
+                None => return transmittance,
+            };
+            if !hit_info.hit_material.is_invisible() {
+                return Vec3::zero();
+            }
+            medium = self.medium_behind(&hit_info, &ray.direction);
+            ray.origin = hit_info.spawn_origin(&ray.direction);
+            segment_start = 0.0;
+            max_dist -= segment_end;
+        }
+    }
+
+    /// pdf of light sampling choosing the direction from the point towards light_point
+    /// zero if light_point does not lie on one of the lights
+    pub fn light_pdf(&self, from: &Vec3, light_point: &Vec3) -> f32 {
+        match self
+            .lights
+            .iter()
+            .find(|light| light.contains_surface_point(light_point))
+        {
+            Some(light) => light.pdf(from, light_point) / self.lights.len() as f32,
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Light, Scene};
+    use crate::alpha::AlphaCutout;
+    use crate::csg::{Csg, CsgOperation};
+    use crate::cuboid::Cuboid;
+    use crate::lambertian::Lambertian;
+    use crate::materials::{cosine_direction, local_to_world};
+    use crate::medium::{HomogeneousMedium, MediumBoundary};
+    use crate::mesh::{MeshData, TriangleMesh};
+    use crate::sdf::{Sdf, SdfNode, DEFAULT_MAX_STEPS};
+    use crate::sphere::Sphere;
+    use crate::torus::Torus;
+    use crate::triangle::get_triangle_normal;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray, RayScattering};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn single_object_scene(object: Box<dyn Intersectable + Sync>) -> Scene {
+        Scene {
+            elements: vec![object],
+            triangle_meshes: vec![],
+            lights: vec![],
+            background_color: None,
+            fog: None,
+        }
+    }
+
+    fn random_direction_around(rng: &mut StdRng, normal: &Vec3) -> Vec3 {
+        local_to_world(&cosine_direction(rng.gen(), rng.gen()), normal).normalize()
+    }
+
+    /// rays spawned away from a closed object enter it first if they hit it again, rays
+    /// spawned into it hit its surface from the inside, i.e. no ray hits its own origin
+    fn assert_no_self_intersections(scene: &Scene, interior_points: &[Vec3], scale: f32) {
+        let mut rng = StdRng::seed_from_u64(48);
+        for _i in 0..2000 {
+            let target = interior_points[rng.gen_range(0..interior_points.len())];
+            let origin =
+                target + 4.0 * scale * random_direction_around(&mut rng, &Vec3::new(0.3, 0.8, 0.5));
+            let ray = Ray::new(origin, (target - origin).normalize());
+            let hit_info = scene.hit(&ray, 0.0, f32::INFINITY).unwrap();
+            let normal = hit_info.geometric_normal;
+
+            let outward = random_direction_around(&mut rng, &normal);
+            let spawned = Ray::new(hit_info.spawn_origin(&outward), outward);
+            if let Some(next_hit) = scene.hit(&spawned, 0.0, f32::INFINITY) {
+                assert!(next_hit.geometric_normal.dot(&outward) < 0.0);
+            }
+
+            let inward = random_direction_around(&mut rng, &(-1.0 * normal));
+            let spawned = Ray::new(hit_info.spawn_origin(&inward), inward);
+            let far_hit = scene.hit(&spawned, 0.0, f32::INFINITY).unwrap();
+            assert!(far_hit.geometric_normal.dot(&inward) > 0.0);
+        }
+    }
+
+    /// closed box of twelve triangles
+    fn box_mesh(center: Vec3, half_size: Vec3) -> TriangleMesh {
+        let corner = |i: usize| {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            center
+                + Vec3::new(
+                    sign(1) * half_size.x,
+                    sign(2) * half_size.y,
+                    sign(4) * half_size.z,
+                )
+        };
+        // counterclockwise seen from outside
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let mut vertices = vec![];
+        for face in &faces {
+            vertices.push([corner(face[0]), corner(face[1]), corner(face[2])]);
+            vertices.push([corner(face[0]), corner(face[2]), corner(face[3])]);
+        }
+        let num_triangles = vertices.len();
+        let mesh_data = MeshData {
+            vertex_normals: vertices
+                .iter()
+                .map(|triangle_vertices| [get_triangle_normal(triangle_vertices); 3])
+                .collect(),
+            vertices,
+            tex_coords: vec![[(0.0, 0.0); 3]; num_triangles],
+            material_ids: vec![None; num_triangles],
+            vertex_colors: None,
+            materials: vec![],
+        };
+        TriangleMesh::from_mesh_data(
+            mesh_data,
+            Some(Box::new(Lambertian { albedo: 0.5.into() })),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_no_self_intersections() {
+        for scale in [1e-2, 1.0, 45.0, 1e3] {
+            let center = Vec3::new(-1.3, 2.1, -7.0) * scale;
+            let sphere = Sphere {
+                center,
+                radius: scale,
+                material: Box::new(Lambertian { albedo: 0.5.into() }),
+            };
+            assert_no_self_intersections(&single_object_scene(Box::new(sphere)), &[center], scale);
+
+            let half_size = Vec3::new(1.0, 0.5, 0.8) * scale;
+            let cuboid = Cuboid {
+                lower_bound: center - half_size,
+                upper_bound: center + half_size,
+                material: Box::new(Lambertian { albedo: 0.5.into() }),
+            };
+            assert_no_self_intersections(&single_object_scene(Box::new(cuboid)), &[center], scale);
+
+            let mesh_scene = Scene {
+                elements: vec![],
+                triangle_meshes: vec![box_mesh(center, half_size)],
+                lights: vec![],
+                background_color: None,
+                fog: None,
+            };
+            assert_no_self_intersections(&mesh_scene, &[center], scale);
+
+            // rays aim at the ring inside the tube, the hole of the torus is empty
+            let ring_points: Vec<Vec3> = (0..8)
+                .map(|i| {
+                    let phi = i as f32 * std::f32::consts::PI / 4.0;
+                    center + scale * Vec3::new(phi.cos(), 0.0, phi.sin())
+                })
+                .collect();
+            let torus = Torus {
+                center,
+                major_radius: scale,
+                minor_radius: 0.4 * scale,
+                material: Box::new(Lambertian { albedo: 0.5.into() }),
+            };
+            assert_no_self_intersections(
+                &single_object_scene(Box::new(torus)),
+                &ring_points,
+                scale,
+            );
+
+            let sdf = Sdf {
+                root: SdfNode::Sphere {
+                    center,
+                    radius: scale,
+                },
+                step_factor: 1.0,
+                max_steps: DEFAULT_MAX_STEPS,
+                material: Box::new(Lambertian { albedo: 0.5.into() }),
+            };
+            assert_no_self_intersections(&single_object_scene(Box::new(sdf)), &[center], scale);
+        }
+    }
+
+    #[test]
+    fn test_crossings_far_from_the_origin() {
+        // a fixed step behind each crossing would fall below the float spacing at these distances
+        for scale in [1e4, 1e5] {
+            let center = Vec3::new(0.0, 0.0, -1.0) * scale;
+            let ray = Ray::new(Vec3::zero(), center.normalize());
+            let radius = 0.1 * scale;
+            let tolerance = 1e-4 * scale;
+            let sphere_at = |center: Vec3, material: Box<dyn RayScattering + Sync>| Sphere {
+                center,
+                radius,
+                material,
+            };
+            let lambertian = || Box::new(Lambertian { albedo: 0.5.into() });
+
+            // a cut out sphere in front of an opaque one
+            let cut_out = sphere_at(
+                center,
+                Box::new(AlphaCutout {
+                    material: lambertian(),
+                    alpha: 0.0.into(),
+                    threshold: None,
+                }),
+            );
+            let behind = sphere_at(center + 3.0 * radius * ray.direction, lambertian());
+            let scene = Scene {
+                elements: vec![Box::new(cut_out), Box::new(behind)],
+                triangle_meshes: vec![],
+                lights: vec![],
+                background_color: None,
+                fog: None,
+            };
+            let hit_info = scene.hit(&ray, 0.0, f32::INFINITY).unwrap();
+            let expected = center.length() + 2.0 * radius;
+            assert!((hit_info.dist_from_ray_orig - expected).abs() < tolerance);
+
+            let union = Csg {
+                operation: CsgOperation::Union,
+                left: Box::new(sphere_at(center, lambertian())),
+                right: Box::new(sphere_at(center, lambertian())),
+            };
+            let intervals = union.solid_intervals(&ray, 0.0);
+            assert_eq!(intervals.len(), 1);
+            let entry = intervals[0].entry.unwrap().dist_from_ray_orig;
+            let exit = intervals[0].exit.unwrap().dist_from_ray_orig;
+            assert!((entry - (center.length() - radius)).abs() < tolerance);
+            assert!((exit - (center.length() + radius)).abs() < tolerance);
+
+            // absorbs half of the light per radius
+            let absorption = 2.0_f32.ln() / radius;
+            let medium = sphere_at(
+                center,
+                Box::new(MediumBoundary {
+                    medium: Box::new(HomogeneousMedium {
+                        absorption: Vec3::new(absorption, absorption, absorption),
+                        scattering: Vec3::zero(),
+                        anisotropy: 0.0,
+                    }),
+                }),
+            );
+            let scene = single_object_scene(Box::new(medium));
+            let transmittance = scene.transmittance(&ray, 0.0, 2.0 * center.length(), None);
+            assert!((transmittance.x - 0.25).abs() < 1e-3, "{:?}", transmittance);
+        }
+    }
+
+    #[test]
+    fn test_sphere_light_sampling() {
+        let light = Light {
+            position: Vec3::new(0.0, 10.0, 0.0),
+            radius: 2.0,
+            color: Vec3::new(4.0, 4.0, 4.0),
+            edges: None,
+        };
+        let from = Vec3::new(1.0, 0.0, 0.0);
+        for _i in 0..100 {
+            let light_sample = light.sample(&from).unwrap();
+            // sampled directions end on the light surface
+            let light_point = from + light_sample.distance * light_sample.direction;
+            assert_eq!(light_sample.pdf, light.pdf(&from, &light_point));
+            assert!(light.contains_surface_point(&light_point));
+        }
+        assert!(light.sample(&Vec3::new(0.0, 9.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_sphere_light_pdf_integrates_to_one() {
+        let light = Light {
+            position: Vec3::new(0.0, 3.0, 0.0),
+            radius: 1.0,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            edges: None,
+        };
+        // the subtended cone has solid angle 1/pdf
+        let from = Vec3::zero();
+        let cos_theta_max = (1.0 - 1.0 / 9.0_f32).sqrt();
+        let solid_angle = 2.0 * std::f32::consts::PI * (1.0 - cos_theta_max);
+        let light_point = Vec3::new(0.0, 2.0, 0.0);
+        assert!((light.pdf(&from, &light_point) * solid_angle - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_point_light_falloff() {
+        let light = Light {
+            position: Vec3::new(0.0, 2.0, 0.0),
+            radius: 0.0,
+            color: Vec3::new(8.0, 8.0, 8.0),
+            edges: None,
+        };
+        let light_sample = light.sample(&Vec3::zero()).unwrap();
+        assert_eq!(light_sample.radiance, Vec3::new(2.0, 2.0, 2.0));
+        assert_eq!(light_sample.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(light_sample.pdf, 0.0);
+    }
+
+    #[test]
+    fn test_quad_light_sampling() {
+        // 2x2 ceiling light facing down
+        let light = Light {
+            position: Vec3::new(-1.0, 2.0, -1.0),
+            radius: 0.0,
+            color: Vec3::new(5.0, 5.0, 5.0),
+            edges: Some([Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 2.0)]),
+        };
+        assert!(!light.is_point_light());
+        let from = Vec3::zero();
+        for _i in 0..100 {
+            let light_sample = light.sample(&from).unwrap();
+            let light_point = from + light_sample.distance * light_sample.direction;
+            assert!(light.contains_surface_point(&light_point));
+            assert!((light_sample.pdf - light.pdf(&from, &light_point)).abs() < 1e-4);
+        }
+        // directly below the center: distance^2 / (cos * area)
+        assert!((light.pdf(&from, &Vec3::new(0.0, 2.0, 0.0)) - 1.0).abs() < 1e-6);
+        // the back side does not emit
+        assert!(light.sample(&Vec3::new(0.0, 3.0, 0.0)).is_none());
+        assert!(!light.contains_surface_point(&Vec3::new(1.5, 2.0, 0.0)));
+    }
 }
@@ -0,0 +1,564 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aabbox::BoundingBox;
+use crate::materials::orthonormal_basis;
+use crate::ray::hit_point_error;
+use crate::sphere::sphere_uv;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+/// distance to the surface below which sphere tracing stops, relative to the travelled distance
+const SURFACE_EPSILON: f32 = 1e-4;
+/// lower bound of the tolerance in multiples of the floating point error of the point
+const PRECISION_FACTOR: f32 = 4.0;
+/// upper limit of the steps that project a hit onto the surface
+const REFINEMENT_STEPS: u32 = 8;
+/// offset of the samples of the gradient of the distance field, relative to the magnitude of
+/// the point, the samples must not round to the same coordinates
+const GRADIENT_DELTA: f32 = 1e-4;
+/// default upper limit of sphere tracing steps per ray
+pub const DEFAULT_MAX_STEPS: u32 = 256;
+
+/// signed distance field, negative inside of the shape, built from shapes and operators
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "sdf_type", rename_all = "snake_case")]
+pub enum SdfNode {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    /// axis aligned box with edges rounded by rounding
+    #[serde(rename = "box")]
+    Cuboid {
+        center: Vec3,
+        half_size: Vec3,
+        #[serde(default)]
+        rounding: f32,
+    },
+    /// torus lying in the xz plane
+    Torus {
+        center: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    /// line segment from start to end thickened by radius
+    Capsule {
+        start: Vec3,
+        end: Vec3,
+        radius: f32,
+    },
+    /// mandelbulb fractal around the origin, of radius 1.2 for power 8
+    Mandelbulb {
+        power: f32,
+        iterations: u32,
+    },
+    Union {
+        children: Vec<SdfNode>,
+    },
+    Intersection {
+        children: Vec<SdfNode>,
+    },
+    /// the first child without all others
+    Difference {
+        children: Vec<SdfNode>,
+    },
+    /// union that blends the children into each other within smoothness of their surfaces
+    SmoothUnion {
+        children: Vec<SdfNode>,
+        smoothness: f32,
+    },
+    /// child repeated infinitely with period along x, y and z, 0.0 disables an axis,
+    /// the child needs to fit into one cell around the origin
+    Repeat {
+        period: Vec3,
+        child: Box<SdfNode>,
+    },
+    /// child twisted around the y axis by rate radians per unit height,
+    /// bends distances, reduce step_factor of the sdf
+    Twist {
+        rate: f32,
+        child: Box<SdfNode>,
+    },
+}
+
+/// polynomial smooth minimum, see Quilez, "Smooth Minimum"
+fn smooth_min(a: f32, b: f32, smoothness: f32) -> f32 {
+    if smoothness <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / smoothness).clamp(0.0, 1.0);
+    b + h * (a - b) - smoothness * h * (1.0 - h)
+}
+
+fn repeat_coord(coord: f32, period: f32) -> f32 {
+    if period > 0.0 {
+        coord - period * (coord / period).round()
+    } else {
+        coord
+    }
+}
+
+/// distance estimate of the mandelbulb, see Hart et al., "Ray Tracing Deterministic 3-D Fractals"
+fn mandelbulb_distance(point: &Vec3, power: f32, iterations: u32) -> f32 {
+    let mut z = *point;
+    let mut dr = 1.0;
+    let mut r = z.length();
+    for _iteration in 0..iterations {
+        r = z.length();
+        if r > 2.0 {
+            break;
+        }
+        let theta = (z.z / r).clamp(-1.0, 1.0).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+        z = r.powf(power)
+            * Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            )
+            + *point;
+    }
+    if r < 1e-6 {
+        return 0.0;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+/// box from center - half_size to center + half_size
+fn box_around(center: Vec3, half_size: Vec3) -> BoundingBox {
+    BoundingBox::new(center - half_size, center + half_size)
+}
+
+impl SdfNode {
+    /// axis aligned box containing the shape, None if it is unbounded, e.g. repeated
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        match self {
+            SdfNode::Sphere { center, radius } => {
+                Some(box_around(*center, Vec3::new(*radius, *radius, *radius)))
+            }
+            SdfNode::Cuboid {
+                center, half_size, ..
+            } => Some(box_around(*center, *half_size)),
+            SdfNode::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let radius_xz = major_radius + minor_radius;
+                Some(box_around(
+                    *center,
+                    Vec3::new(radius_xz, *minor_radius, radius_xz),
+                ))
+            }
+            SdfNode::Capsule { start, end, radius } => {
+                let thickness = Vec3::new(*radius, *radius, *radius);
+                Some(box_around(*start, thickness).enclosing(&box_around(*end, thickness)))
+            }
+            // points beyond radius 2 escape for powers of 2 and above
+            SdfNode::Mandelbulb { power, .. } if *power >= 2.0 => {
+                Some(box_around(Vec3::zero(), Vec3::new(2.0, 2.0, 2.0)))
+            }
+            SdfNode::Mandelbulb { .. } => None,
+            SdfNode::Union { children } => children
+                .iter()
+                .map(|child| child.bounds())
+                .reduce(|a, b| Some(a?.enclosing(&b?)))
+                .flatten(),
+            SdfNode::Intersection { children } => children
+                .iter()
+                .filter_map(|child| child.bounds())
+                .reduce(|a, b| a.overlap(&b)),
+            SdfNode::Difference { children } => children.first()?.bounds(),
+            SdfNode::SmoothUnion {
+                children,
+                smoothness,
+            } => {
+                let bounds = children
+                    .iter()
+                    .map(|child| child.bounds())
+                    .reduce(|a, b| Some(a?.enclosing(&b?)))
+                    .flatten()?;
+                // blending only adds material within smoothness of the children
+                let margin = Vec3::new(*smoothness, *smoothness, *smoothness);
+                Some(BoundingBox::new(
+                    bounds.lower_bound - margin,
+                    bounds.upper_bound + margin,
+                ))
+            }
+            SdfNode::Repeat { period, child } => {
+                if period.x > 0.0 || period.y > 0.0 || period.z > 0.0 {
+                    None
+                } else {
+                    child.bounds()
+                }
+            }
+            // the twist rotates around the y axis, it keeps the distance to the axis
+            SdfNode::Twist { child, .. } => {
+                let bounds = child.bounds()?;
+                let reach = |lower: f32, upper: f32| lower.abs().max(upper.abs());
+                let radius_xz = reach(bounds.lower_bound.x, bounds.upper_bound.x)
+                    .hypot(reach(bounds.lower_bound.z, bounds.upper_bound.z));
+                Some(BoundingBox::new(
+                    Vec3::new(-radius_xz, bounds.lower_bound.y, -radius_xz),
+                    Vec3::new(radius_xz, bounds.upper_bound.y, radius_xz),
+                ))
+            }
+        }
+    }
+
+    /// signed distance from the point to the surface, or a lower bound of it
+    pub fn distance(&self, point: &Vec3) -> f32 {
+        match self {
+            SdfNode::Sphere { center, radius } => (*point - *center).length() - radius,
+            SdfNode::Cuboid {
+                center,
+                half_size,
+                rounding,
+            } => {
+                let offset = *point - *center;
+                let q = Vec3::new(
+                    offset.x.abs() - half_size.x + rounding,
+                    offset.y.abs() - half_size.y + rounding,
+                    offset.z.abs() - half_size.z + rounding,
+                );
+                let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+                let inside = q.x.max(q.y).max(q.z).min(0.0);
+                outside + inside - rounding
+            }
+            SdfNode::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let offset = *point - *center;
+                let radius_xz = (offset.x * offset.x + offset.z * offset.z).sqrt();
+                ((radius_xz - major_radius).powi(2) + offset.y * offset.y).sqrt() - minor_radius
+            }
+            SdfNode::Capsule { start, end, radius } => {
+                let axis = *end - *start;
+                let offset = *point - *start;
+                let along = (offset.dot(&axis) / axis.dot(&axis).max(1e-12)).clamp(0.0, 1.0);
+                (offset - along * axis).length() - radius
+            }
+            SdfNode::Mandelbulb { power, iterations } => {
+                mandelbulb_distance(point, *power, *iterations)
+            }
+            SdfNode::Union { children } => children
+                .iter()
+                .map(|child| child.distance(point))
+                .fold(f32::MAX, f32::min),
+            SdfNode::Intersection { children } => children
+                .iter()
+                .map(|child| child.distance(point))
+                .fold(-f32::MAX, f32::max),
+            SdfNode::Difference { children } => match children.split_first() {
+                Some((first, others)) => others
+                    .iter()
+                    .map(|child| -child.distance(point))
+                    .fold(first.distance(point), f32::max),
+                None => f32::MAX,
+            },
+            SdfNode::SmoothUnion {
+                children,
+                smoothness,
+            } => children
+                .iter()
+                .map(|child| child.distance(point))
+                .reduce(|a, b| smooth_min(a, b, *smoothness))
+                .unwrap_or(f32::MAX),
+            SdfNode::Repeat { period, child } => child.distance(&Vec3::new(
+                repeat_coord(point.x, period.x),
+                repeat_coord(point.y, period.y),
+                repeat_coord(point.z, period.z),
+            )),
+            SdfNode::Twist { rate, child } => {
+                let (sin, cos) = (rate * point.y).sin_cos();
+                child.distance(&Vec3::new(
+                    cos * point.x - sin * point.z,
+                    point.y,
+                    sin * point.x + cos * point.z,
+                ))
+            }
+        }
+    }
+
+    /// outward unit normal from the gradient of the field, by the tetrahedron technique
+    pub fn normal(&self, point: &Vec3) -> Vec3 {
+        let delta = (GRADIENT_DELTA * point.abs().sum()).max(f32::EPSILON);
+        let gradient = [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .fold(Vec3::zero(), |gradient, corner| {
+            gradient + self.distance(&(*point + delta * *corner)) * *corner
+        });
+        if gradient.length() == 0.0 {
+            return Vec3::new(0.0, 1.0, 0.0);
+        }
+        gradient.normalize()
+    }
+}
+
+/// shape given by a signed distance field, intersected by sphere tracing,
+/// see Hart, "Sphere Tracing: A Geometric Method for the Antialiased Ray Tracing of Implicit
+/// Surfaces", image textures are mapped by the direction of the normal like on spheres
+pub struct Sdf {
+    pub root: SdfNode,
+    /// scales the steps, below 1.0 for fields that overestimate distances, e.g. twists
+    pub step_factor: f32,
+    pub max_steps: u32,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+/// smallest distance to the surface that sphere tracing can resolve at the point
+fn precision_floor(point: &Vec3, dist: f32) -> f32 {
+    PRECISION_FACTOR * hit_point_error(point, dist).sum()
+}
+
+/// distance to the surface below which sphere tracing stops
+fn tolerance(point: &Vec3, dist: f32) -> f32 {
+    (SURFACE_EPSILON * dist).max(precision_floor(point, dist))
+}
+
+impl Intersectable for Sdf {
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let direction = ray.direction.normalize();
+        // marching ends where the ray leaves the bounds of the shape, rays that miss it
+        // would otherwise step on to the far clip distance
+        let max_dist = match self.root.bounds() {
+            Some(bounds) => {
+                let unit_ray = Ray { direction, ..*ray };
+                let (_entry_dist, exit_dist) = bounds.hit_range(&unit_ray)?;
+                let exit_point = unit_ray.point_at(exit_dist);
+                max_dist.min(exit_dist + tolerance(&exit_point, exit_dist))
+            }
+            None => max_dist,
+        };
+        if max_dist < min_dist {
+            return None;
+        }
+        let mut dist = min_dist;
+        let start = ray.origin + dist * direction;
+        let start_surface_dist = self.root.distance(&start);
+        // rays leaving the surface start within the tolerance of the hit, they step off the
+        // surface to the side they leave to before a hit counts
+        let mut leaving_surface = start_surface_dist.abs() < tolerance(&start, dist);
+        let mut leaving_step = tolerance(&start, dist);
+        // rays starting inside of the shape trace the negated field to where they leave
+        let side = if leaving_surface {
+            self.root.normal(&start).dot(&direction).signum()
+        } else {
+            start_surface_dist.signum()
+        };
+        for _step in 0..self.max_steps {
+            let mut point = ray.origin + dist * direction;
+            let surface_dist = side * self.root.distance(&point);
+            let tolerance = tolerance(&point, dist);
+            if leaving_surface {
+                if surface_dist.abs() < tolerance {
+                    // grazing rays stay close to the surface for long, the steps grow
+                    dist += tolerance.max(leaving_step);
+                    leaving_step *= 2.0;
+                    continue;
+                }
+                leaving_surface = false;
+            }
+            if surface_dist < tolerance {
+                // the tolerance grows with the distance, project the hit onto the surface so
+                // that rays leaving it start well within the tolerance they tell the side by
+                for _refinement in 0..REFINEMENT_STEPS {
+                    let surface_dist = self.root.distance(&point);
+                    if surface_dist.abs() < 0.25 * precision_floor(&point, 0.0) {
+                        break;
+                    }
+                    point = point - surface_dist * self.root.normal(&point);
+                }
+                let hit_normal = self.root.normal(&point);
+                let (tangent, bitangent) = orthonormal_basis(&hit_normal);
+                return Some(HitInformation {
+                    hit_point: point,
+                    geometric_normal: hit_normal,
+                    shading_normal: hit_normal,
+                    front_face: ray.direction.dot(&hit_normal) < 0.0,
+                    uv: sphere_uv(&hit_normal),
+                    tangent,
+                    bitangent,
+                    hit_material: &*self.material,
+                    vertex_color: None,
+                    dist_from_ray_orig: dist,
+                });
+            }
+            dist += self.step_factor * surface_dist;
+            if dist > max_dist {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sdf, SdfNode, DEFAULT_MAX_STEPS};
+    use crate::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    fn new_sphere(center: Vec3, radius: f32) -> SdfNode {
+        SdfNode::Sphere { center, radius }
+    }
+
+    fn new_sdf(root: SdfNode) -> Sdf {
+        Sdf {
+            root,
+            step_factor: 1.0,
+            max_steps: DEFAULT_MAX_STEPS,
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        }
+    }
+
+    #[test]
+    fn test_sdf_shapes_and_operators() {
+        let cube = SdfNode::Cuboid {
+            center: Vec3::zero(),
+            half_size: Vec3::new(1.0, 1.0, 1.0),
+            rounding: 0.0,
+        };
+        assert!((cube.distance(&Vec3::new(2.0, 0.0, 0.0)) - 1.0).abs() < 1e-6);
+        assert!((cube.distance(&Vec3::new(2.0, 2.0, 1.0)) - 2.0_f32.sqrt()).abs() < 1e-6);
+        assert!((cube.distance(&Vec3::new(0.5, 0.0, 0.0)) + 0.5).abs() < 1e-6);
+
+        let spheres = || {
+            vec![
+                new_sphere(Vec3::new(-1.0, 0.0, 0.0), 1.0),
+                new_sphere(Vec3::new(1.0, 0.0, 0.0), 1.0),
+            ]
+        };
+        let union = SdfNode::Union {
+            children: spheres(),
+        };
+        let smooth_union = SdfNode::SmoothUnion {
+            children: spheres(),
+            smoothness: 0.5,
+        };
+        // blending adds material where the spheres meet, but not far away from it
+        let between = Vec3::new(0.0, 0.5, 0.0);
+        assert!(smooth_union.distance(&between) < union.distance(&between) - 0.05);
+        let far = Vec3::new(-3.0, 0.0, 0.0);
+        assert!((smooth_union.distance(&far) - union.distance(&far)).abs() < 1e-6);
+
+        let difference = SdfNode::Difference {
+            children: spheres(),
+        };
+        assert!(difference.distance(&Vec3::new(0.5, 0.0, 0.0)) > 0.0);
+        assert!(difference.distance(&Vec3::new(-1.5, 0.0, 0.0)) < 0.0);
+
+        let repeated = SdfNode::Repeat {
+            period: Vec3::new(4.0, 0.0, 0.0),
+            child: Box::new(new_sphere(Vec3::zero(), 1.0)),
+        };
+        assert!(repeated.distance(&Vec3::new(8.0, 0.0, 0.0)) < -0.99);
+        assert!((repeated.distance(&Vec3::new(8.0, 3.0, 0.0)) - 2.0).abs() < 1e-5);
+
+        // a twisted bar is unchanged at y = 0 and turned by 90 degrees at y = 1
+        let twisted = SdfNode::Twist {
+            rate: std::f32::consts::FRAC_PI_2,
+            child: Box::new(SdfNode::Cuboid {
+                center: Vec3::zero(),
+                half_size: Vec3::new(1.0, 2.0, 0.2),
+                rounding: 0.0,
+            }),
+        };
+        assert!(twisted.distance(&Vec3::new(0.8, 0.0, 0.0)) < 0.0);
+        assert!(twisted.distance(&Vec3::new(0.8, 1.0, 0.0)) > 0.0);
+        assert!(twisted.distance(&Vec3::new(0.0, 1.0, 0.8)) < 0.0);
+    }
+
+    #[test]
+    fn test_sphere_tracing() {
+        let sdf = new_sdf(new_sphere(Vec3::new(0.0, 0.0, -5.0), 1.0));
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -2.0));
+        let hit_info = sdf.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-3);
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-3);
+        assert!(sdf.intersect_with_ray(&ray, 1e-3, 3.0).is_none());
+
+        // from the inside the ray leaves on the far side
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit_info = sdf.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 1.0).abs() < 1e-3);
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-3);
+
+        let ray = Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(sdf.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+
+        // rays leaving the surface do not hit it where they start
+        let on_surface = Vec3::new(0.0, 0.0, -4.0);
+        let away = Ray::new(on_surface, Vec3::new(0.0, 0.6, 0.8));
+        assert!(sdf.intersect_with_ray(&away, 0.0, f32::MAX).is_none());
+        let into = Ray::new(on_surface, Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = sdf.intersect_with_ray(&into, 0.0, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_mandelbulb() {
+        let sdf = new_sdf(SdfNode::Mandelbulb {
+            power: 8.0,
+            iterations: 12,
+        });
+        // the bulb lies within radius 1.2 and contains its center
+        assert!(sdf.root.distance(&Vec3::new(0.0, 0.0, 1.5)) > 0.0);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = sdf.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!(hit_info.dist_from_ray_orig > 1.8 && hit_info.dist_from_ray_orig < 3.0);
+        assert!(hit_info.geometric_normal.z > 0.0);
+    }
+
+    #[test]
+    fn test_missing_rays_stop_at_the_bounds() {
+        let twisted = SdfNode::Twist {
+            rate: 0.5,
+            child: Box::new(SdfNode::SmoothUnion {
+                children: vec![
+                    new_sphere(Vec3::new(3.0, 0.0, 0.0), 1.0),
+                    SdfNode::Capsule {
+                        start: Vec3::new(0.0, -1.0, 0.0),
+                        end: Vec3::new(0.0, 2.0, 0.0),
+                        radius: 0.5,
+                    },
+                ],
+                smoothness: 0.25,
+            }),
+        };
+        let bounds = twisted.bounds().unwrap();
+        assert_eq!(bounds.lower_bound.y, -1.75);
+        assert_eq!(bounds.upper_bound.y, 2.75);
+        assert!((bounds.upper_bound.x - 4.25_f32.hypot(1.25)).abs() < 1e-5);
+        let repeated = SdfNode::Repeat {
+            period: Vec3::new(4.0, 0.0, 0.0),
+            child: Box::new(new_sphere(Vec3::zero(), 1.0)),
+        };
+        assert!(repeated.bounds().is_none());
+
+        // without bounds, rays that miss would step on until they run out of steps
+        let mut sdf = new_sdf(new_sphere(Vec3::new(0.0, 0.0, -5.0), 1.0));
+        sdf.max_steps = u32::MAX;
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 1.0, -1.0));
+        assert!(sdf.intersect_with_ray(&ray, 0.0, f32::INFINITY).is_none());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(sdf.intersect_with_ray(&ray, 0.0, f32::INFINITY).is_none());
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = sdf.intersect_with_ray(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::materials::ScatterRecord;
+use crate::medium::Medium;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Ray, RayScattering};
+
+/// which sides of a surface take part in rendering, the front is the side the geometric
+/// normal points to
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sidedness {
+    /// both sides scatter light, the default
+    TwoSided,
+    /// the back absorbs all light, but still blocks rays and casts shadows
+    OneSided,
+    /// the back is not hit at all, rays pass through it, e.g. walls of a room that
+    /// is looked into from outside
+    BackfaceCulled,
+}
+
+/// renders the back of a material according to its sidedness,
+/// the front is delegated to the wrapped material
+pub struct Sided {
+    pub material: Box<dyn RayScattering + Sync>,
+    pub sidedness: Sidedness,
+}
+
+impl Sided {
+    fn shades(&self, hit_info: &HitInformation) -> bool {
+        hit_info.front_face || self.sidedness == Sidedness::TwoSided
+    }
+}
+
+impl RayScattering for Sided {
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        if !self.shades(hit_info) {
+            return None;
+        }
+        self.material.sample(incoming_ray, hit_info)
+    }
+
+    fn eval(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        if !self.shades(hit_info) {
+            return Vec3::zero();
+        }
+        self.material.eval(incoming_dir, scattered_dir, hit_info)
+    }
+
+    fn pdf(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+        if !self.shades(hit_info) {
+            return 0.0;
+        }
+        self.material.pdf(incoming_dir, scattered_dir, hit_info)
+    }
+
+    fn emitted(&self, incoming_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        if !self.shades(hit_info) {
+            return Vec3::zero();
+        }
+        self.material.emitted(incoming_dir, hit_info)
+    }
+
+    fn shading_normal(&self, hit_info: &HitInformation) -> Vec3 {
+        self.material.shading_normal(hit_info)
+    }
+
+    fn medium(&self) -> Option<&dyn Medium> {
+        self.material.medium()
+    }
+
+    fn is_invisible(&self) -> bool {
+        self.material.is_invisible()
+    }
+
+    /// culled back faces are fully transparent, so that the scene skips them
+    fn opacity(&self, hit_info: &HitInformation) -> f32 {
+        if !hit_info.front_face && self.sidedness == Sidedness::BackfaceCulled {
+            return 0.0;
+        }
+        self.material.opacity(hit_info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sided, Sidedness};
+    use crate::lambertian::Lambertian;
+    use crate::quad::Quad;
+    use crate::scene::Scene;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    /// unit quad at z = -1 facing +z, in front of a two sided quad at z = -2
+    fn new_scene(sidedness: Sidedness) -> Scene {
+        let quad = |z: f32, sidedness: Sidedness| {
+            Quad::new(
+                Vec3::new(0.0, 0.0, z),
+                [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+                Box::new(Sided {
+                    material: Box::new(Lambertian { albedo: 0.5.into() }),
+                    sidedness,
+                }),
+            )
+        };
+        Scene {
+            elements: vec![
+                Box::new(quad(-1.0, sidedness)) as Box<dyn Intersectable + Sync>,
+                Box::new(quad(-2.0, Sidedness::TwoSided)),
+            ],
+            triangle_meshes: vec![],
+            lights: vec![],
+            background_color: None,
+            fog: None,
+        }
+    }
+
+    #[test]
+    fn test_front_face() {
+        let scene = new_scene(Sidedness::TwoSided);
+        let front_ray = Ray::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = scene.hit(&front_ray, 1e-3, f32::MAX).unwrap();
+        assert!(hit_info.front_face);
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, 0.0, 1.0));
+
+        let back_ray = Ray::new(Vec3::new(0.5, 0.5, -1.5), Vec3::new(0.0, 0.0, 1.0));
+        let hit_info = scene.hit(&back_ray, 1e-3, f32::MAX).unwrap();
+        assert!(!hit_info.front_face);
+        // the normal is not flipped towards the ray
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, 0.0, 1.0));
+        let rec = hit_info.hit_material.sample(&back_ray, &hit_info).unwrap();
+        assert!(rec.scattered_ray.direction.z < 0.0);
+    }
+
+    #[test]
+    fn test_one_sided() {
+        let scene = new_scene(Sidedness::OneSided);
+        let back_ray = Ray::new(Vec3::new(0.5, 0.5, -1.5), Vec3::new(0.0, 0.0, 1.0));
+        let hit_info = scene.hit(&back_ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 0.5).abs() < 1e-5);
+        assert!(hit_info.hit_material.sample(&back_ray, &hit_info).is_none());
+        let scattered = Vec3::new(0.0, 0.0, -1.0);
+        assert_eq!(
+            hit_info
+                .hit_material
+                .eval(&back_ray.direction, &scattered, &hit_info),
+            Vec3::zero()
+        );
+        // the back still casts shadows
+        assert_eq!(
+            scene.transmittance(&back_ray, 1e-3, 1.0, None),
+            Vec3::zero()
+        );
+    }
+
+    #[test]
+    fn test_backface_culling() {
+        let scene = new_scene(Sidedness::BackfaceCulled);
+        let front_ray = Ray::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = scene.hit(&front_ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 1.0).abs() < 1e-5);
+
+        // rays pass through the back, also shadow rays
+        let behind_culled = Ray::new(Vec3::new(0.5, 0.5, -1.5), Vec3::new(0.0, 0.0, 1.0));
+        assert!(scene.hit(&behind_culled, 1e-3, f32::MAX).is_none());
+        assert_eq!(
+            scene.transmittance(&behind_culled, 1e-3, 10.0, None),
+            Vec3::new(1.0, 1.0, 1.0)
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::vec3::Vec3;
+use std::sync::OnceLock;
+
+/// range of visible wavelengths that are sampled in spectral rendering mode
+pub const MIN_WAVELENGTH_NM: f32 = 380.0;
+pub const MAX_WAVELENGTH_NM: f32 = 780.0;
+
+/// wavelength of the sodium d-line, at which refractive indices are usually specified
+pub const D_LINE_WAVELENGTH_NM: f32 = 587.6;
+
+/// draws a wavelength uniformly from the stratum sample_idx out of num_strata equally wide
+/// strata of the visible range, spreading the samples of a pixel over the whole spectrum
+pub fn sample_stratified_wavelength(sample_idx: u32, num_strata: u32) -> f32 {
+    let stratum_width = (MAX_WAVELENGTH_NM - MIN_WAVELENGTH_NM) / num_strata as f32;
+    MIN_WAVELENGTH_NM + (sample_idx as f32 + rand::random::<f32>()) * stratum_width
+}
+
+/// piecewise gaussian used for the analytic fit of the color matching functions
+fn piecewise_gaussian(x: f32, mu: f32, sigma_lower: f32, sigma_upper: f32) -> f32 {
+    let sigma = if x < mu { sigma_lower } else { sigma_upper };
+    (-0.5 * ((x - mu) / sigma).powi(2)).exp()
+}
+
+/// CIE 1931 color matching functions, using the multi-lobe fit from
+/// Wyman et al., "Simple Analytic Approximations to the CIE XYZ Color Matching Functions"
+pub fn cie_xyz(wavelength_nm: f32) -> Vec3 {
+    let l = wavelength_nm;
+    Vec3::new(
+        1.056 * piecewise_gaussian(l, 599.8, 37.9, 31.0)
+            + 0.362 * piecewise_gaussian(l, 442.0, 16.0, 26.7)
+            - 0.065 * piecewise_gaussian(l, 501.1, 20.4, 26.2),
+        0.821 * piecewise_gaussian(l, 568.8, 46.9, 40.5)
+            + 0.286 * piecewise_gaussian(l, 530.9, 16.3, 31.1),
+        1.217 * piecewise_gaussian(l, 437.0, 11.8, 36.0)
+            + 0.681 * piecewise_gaussian(l, 459.0, 26.0, 13.8),
+    )
+}
+
+/// converts CIE XYZ to linear sRGB
+pub fn xyz_to_rgb(xyz: Vec3) -> Vec3 {
+    Vec3::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
+
+/// average rgb response over the visible range, used to keep white light white
+fn mean_rgb_response() -> Vec3 {
+    static MEAN_RGB: OnceLock<Vec3> = OnceLock::new();
+    *MEAN_RGB.get_or_init(|| {
+        let num_steps = 4000;
+        let step = (MAX_WAVELENGTH_NM - MIN_WAVELENGTH_NM) / num_steps as f32;
+        let mut sum = Vec3::zero();
+        for i in 0..num_steps {
+            sum += xyz_to_rgb(cie_xyz(MIN_WAVELENGTH_NM + (i as f32 + 0.5) * step));
+        }
+        sum * (1.0 / num_steps as f32)
+    })
+}
+
+/// rgb weight of a single uniformly sampled wavelength
+/// the weights average to (1,1,1) over the visible range, so a path that carries
+/// a wavelength can be accumulated into the rgb framebuffer after multiplying with it
+pub fn wavelength_to_rgb_weight(wavelength_nm: f32) -> Vec3 {
+    let mean = mean_rgb_response();
+    let rgb = xyz_to_rgb(cie_xyz(wavelength_nm));
+    Vec3::new(rgb.x / mean.x, rgb.y / mean.y, rgb.z / mean.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cie_xyz, wavelength_to_rgb_weight, Vec3, MAX_WAVELENGTH_NM, MIN_WAVELENGTH_NM};
+
+    #[test]
+    fn test_color_matching_function_peaks() {
+        // luminance peaks in the green, z in the blue
+        assert!(cie_xyz(555.0).y > 0.99);
+        assert!(cie_xyz(555.0).y > cie_xyz(450.0).y);
+        assert!(cie_xyz(450.0).z > cie_xyz(650.0).z);
+        assert!(cie_xyz(600.0).x > cie_xyz(500.0).x);
+    }
+
+    #[test]
+    fn test_white_stays_white() {
+        let num_steps = 1000;
+        let step = (MAX_WAVELENGTH_NM - MIN_WAVELENGTH_NM) / num_steps as f32;
+        let mut sum = Vec3::zero();
+        for i in 0..num_steps {
+            sum += wavelength_to_rgb_weight(MIN_WAVELENGTH_NM + (i as f32 + 0.5) * step);
+        }
+        let mean = sum * (1.0 / num_steps as f32);
+        assert!((mean - Vec3::new(1.0, 1.0, 1.0)).length() < 1e-2);
+    }
+
+    #[test]
+    fn test_wavelength_hue() {
+        let red = wavelength_to_rgb_weight(650.0);
+        assert!(red.x > red.y && red.x > red.z);
+        let blue = wavelength_to_rgb_weight(450.0);
+        assert!(blue.z > blue.x && blue.z > blue.y);
+    }
+}
@@ -1,5 +1,7 @@
 use std::cmp::Ordering;
+use std::f32::consts::PI;
 
+use crate::materials::orthonormal_basis;
 use crate::vec3::Vec3;
 use crate::{HitInformation, Intersectable, Ray, RayScattering};
 
@@ -9,6 +11,31 @@ pub struct Sphere {
     pub material: Box<dyn RayScattering + Sync>,
 }
 
+/// spherical texture coordinates of a point on the unit sphere
+/// u runs around the y axis starting at -x, v from the bottom to the top pole
+pub fn sphere_uv(point: &Vec3) -> (f32, f32) {
+    let theta = (-point.y).clamp(-1.0, 1.0).acos();
+    let phi = (-point.z).atan2(point.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+/// derivatives (dp/du, dp/dv) of sphere_uv at offset from the center of the sphere
+pub fn sphere_tangent_frame(offset: &Vec3) -> (Vec3, Vec3) {
+    let radius_xz = (offset.x * offset.x + offset.z * offset.z).sqrt();
+    if radius_xz < 1e-6 * offset.length() {
+        // u is not defined at the poles
+        return orthonormal_basis(&offset.normalize());
+    }
+    (
+        2.0 * PI * Vec3::new(offset.z, 0.0, -offset.x),
+        PI * Vec3::new(
+            -offset.y * offset.x / radius_xz,
+            radius_xz,
+            -offset.y * offset.z / radius_xz,
+        ),
+    )
+}
+
 impl Intersectable for Sphere {
     ///
     /// Compute intersection of ray and sphere
@@ -22,7 +49,7 @@ impl Intersectable for Sphere {
         ray: &Ray,
         min_dist: f32,
         max_dist: f32,
-    ) -> Option<HitInformation> {
+    ) -> Option<HitInformation<'_>> {
         let a = ray.direction.dot(&ray.direction);
         let l = ray.origin - self.center;
         let b = (ray.direction * 2.0).dot(&l);
@@ -40,12 +67,12 @@ impl Intersectable for Sphere {
             None
         } else {
             let mut ray_param = (-b - sol.sqrt()) / (2.0 * a);
-            if num_hits == 2 && ray_param < 0.0 {
-                //point is behind the camera!
+            if num_hits == 2 && ray_param * ray.direction.length() < min_dist {
+                // near point is behind the camera or closer than min_dist
                 ray_param = (-b + sol.sqrt()) / (2.0 * a);
-                if ray_param < 0.0 {
-                    return None; // both points on the ray are negative
-                }
+            }
+            if ray_param < 0.0 {
+                return None; // both points on the ray are negative
             }
             let hit_point = ray.point_at(ray_param);
             let dist_from_ray_orig = (ray.origin - hit_point).length();
@@ -53,11 +80,19 @@ impl Intersectable for Sphere {
             if dist_from_ray_orig < min_dist || dist_from_ray_orig > max_dist {
                 None
             } else {
-                let hit_normal = hit_point - self.center;
+                let offset = hit_point - self.center;
+                let hit_normal = offset * (1.0 / self.radius);
+                let (tangent, bitangent) = sphere_tangent_frame(&offset);
                 let hit_info = HitInformation {
-                    hit_normal,
+                    geometric_normal: hit_normal,
+                    shading_normal: hit_normal,
+                    front_face: ray.direction.dot(&hit_normal) < 0.0,
                     hit_point,
+                    uv: sphere_uv(&hit_normal),
+                    tangent,
+                    bitangent,
                     hit_material: &*self.material,
+                    vertex_color: None,
                     dist_from_ray_orig,
                 };
                 Some(hit_info)
@@ -68,7 +103,7 @@ impl Intersectable for Sphere {
 
 #[cfg(test)]
 mod tests {
-    use super::{Ray, Sphere, Vec3};
+    use super::{sphere_tangent_frame, sphere_uv, Ray, Sphere, Vec3};
     use crate::metal::Metal;
     use crate::Intersectable;
 
@@ -79,14 +114,11 @@ mod tests {
             center: Vec3::new(0.0, 0.0, -10.0),
             radius: 1.0,
             material: Box::new(Metal {
-                albedo: Vec3::new(0.8, 0.8, 0.8),
-                roughness: 0.005,
+                albedo: Vec3::new(0.8, 0.8, 0.8).into(),
+                roughness: 0.005.into(),
             }),
         };
-        let test_ray = Ray {
-            origin: Vec3::new(0.0, 0.0, 0.0),
-            direction: Vec3::new(0.0, 0.0, -1.0),
-        };
+        let test_ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
 
         let hit_info = test_sphere.intersect_with_ray(&test_ray, 0.001, 1000.0);
 
@@ -94,13 +126,10 @@ mod tests {
 
         let hit_info = hit_info.unwrap();
         assert_eq!(hit_info.hit_point, Vec3::new(0.0, 0.0, -9.0));
-        assert_eq!(hit_info.hit_normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, 0.0, 1.0));
 
         // ray hits sphere from behind
-        let test_ray = Ray {
-            origin: Vec3::new(0.0, 0.0, -15.0),
-            direction: Vec3::new(0.0, 0.0, 1.0),
-        };
+        let test_ray = Ray::new(Vec3::new(0.0, 0.0, -15.0), Vec3::new(0.0, 0.0, 1.0));
 
         let hit_info = test_sphere.intersect_with_ray(&test_ray, 0.001, 1000.0);
 
@@ -108,6 +137,42 @@ mod tests {
 
         let hit_info = hit_info.unwrap();
         assert_eq!(hit_info.hit_point, Vec3::new(0.0, 0.0, -11.0));
-        assert_eq!(hit_info.hit_normal, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(hit_info.geometric_normal, Vec3::new(0.0, 0.0, -1.0));
+
+        // the far side is hit if the near side is closer than min_dist
+        let hit_info = test_sphere.intersect_with_ray(&test_ray, 5.0, 1000.0);
+        assert_eq!(hit_info.unwrap().hit_point, Vec3::new(0.0, 0.0, -9.0));
+    }
+
+    #[test]
+    fn test_sphere_uv() {
+        let (u, v) = sphere_uv(&Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(v, 1.0);
+        assert!((0.0..=1.0).contains(&u));
+        let (_u, v) = sphere_uv(&Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(v, 0.0);
+
+        let (u, v) = sphere_uv(&Vec3::new(1.0, 0.0, 0.0));
+        assert!((u - 0.5).abs() < 1e-6 && (v - 0.5).abs() < 1e-6);
+        let (u, _v) = sphere_uv(&Vec3::new(0.0, 0.0, 1.0));
+        assert!((u - 0.25).abs() < 1e-6);
+        let (u, _v) = sphere_uv(&Vec3::new(0.0, 0.0, -1.0));
+        assert!((u - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sphere_tangent_frame_matches_uv() {
+        let offset = Vec3::new(0.3, -1.2, 0.8);
+        let (tangent, bitangent) = sphere_tangent_frame(&offset);
+        let (u, v) = sphere_uv(&offset.normalize());
+        let delta = 1e-3;
+
+        let (u_moved, v_moved) = sphere_uv(&(offset + delta * tangent).normalize());
+        assert!((u_moved - u - delta).abs() < 1e-4);
+        assert!((v_moved - v).abs() < 1e-4);
+
+        let (u_moved, v_moved) = sphere_uv(&(offset + delta * bitangent).normalize());
+        assert!((u_moved - u).abs() < 1e-4);
+        assert!((v_moved - v - delta).abs() < 1e-4);
     }
 }
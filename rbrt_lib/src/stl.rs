@@ -0,0 +1,178 @@
+use std::fs;
+
+use crate::mesh::{compute_vertex_normals, transform_mesh_data, MeshData};
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+/// 80 byte header followed by the number of triangles
+const BINARY_HEADER_SIZE: usize = 84;
+/// normal, three vertices and the attribute byte count
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+fn parse_binary_stl(content: &[u8]) -> Vec<[Vec3; 3]> {
+    if content.len() < BINARY_HEADER_SIZE {
+        panic!("STL file is neither ascii nor binary, it is too short for a binary header");
+    }
+    let num_triangles = u32::from_le_bytes([content[80], content[81], content[82], content[83]]);
+    let triangle_bytes = &content[BINARY_HEADER_SIZE..];
+    if triangle_bytes.len() < BINARY_TRIANGLE_SIZE * num_triangles as usize {
+        panic!(
+            "Binary STL file announces {} triangles, but is too short for them",
+            num_triangles
+        );
+    }
+    let read_vec3 = |bytes: &[u8]| {
+        let coords: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|coord| f32::from_le_bytes([coord[0], coord[1], coord[2], coord[3]]))
+            .collect();
+        Vec3::new(coords[0], coords[1], coords[2])
+    };
+    // some exporters append data after the announced triangles
+    triangle_bytes
+        .chunks_exact(BINARY_TRIANGLE_SIZE)
+        .take(num_triangles as usize)
+        .map(|triangle| {
+            // the stored facet normal is skipped, it is often missing or wrong
+            [
+                read_vec3(&triangle[12..24]),
+                read_vec3(&triangle[24..36]),
+                read_vec3(&triangle[36..48]),
+            ]
+        })
+        .collect()
+}
+
+/// None if the content is not a valid ascii stl file, binary files may also start
+/// with "solid", so the whole file has to parse
+fn parse_ascii_stl(content: &[u8]) -> Option<Vec<[Vec3; 3]>> {
+    let content = std::str::from_utf8(content).ok()?;
+    if !content.trim_start().starts_with("solid") {
+        return None;
+    }
+    let mut corners = vec![];
+    let mut is_closed = false;
+    for line in content.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("vertex") => {
+                let coords = tokens
+                    .map(|token| token.parse().ok())
+                    .collect::<Option<Vec<f32>>>()?;
+                if coords.len() != 3 {
+                    return None;
+                }
+                corners.push(Vec3::new(coords[0], coords[1], coords[2]));
+            }
+            Some("endsolid") => is_closed = true,
+            _ => {}
+        }
+    }
+    if !is_closed || corners.len() % 3 != 0 {
+        return None;
+    }
+    Some(
+        corners
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect(),
+    )
+}
+
+/// Loads mesh from an ascii or binary stl file, scales, rotates and translates it
+/// stl files have neither texture coordinates nor materials, the vertex normals
+/// are computed from the faces
+pub fn load_mesh_from_stl_file(
+    filepath: &str,
+    translation: Vec3,
+    rotation: Vec3,
+    scale: f32,
+) -> MeshData {
+    let content = match fs::read(filepath) {
+        Ok(content) => content,
+        Err(error) => panic!("Failed to open {}: {:?}", filepath, error),
+    };
+    let vertices = parse_ascii_stl(&content).unwrap_or_else(|| parse_binary_stl(&content));
+
+    let mut mesh_data = MeshData {
+        tex_coords: vec![[(0.0, 0.0); 3]; vertices.len()],
+        vertex_normals: compute_vertex_normals(&vertices),
+        material_ids: vec![None; vertices.len()],
+        vertex_colors: None,
+        materials: vec![],
+        vertices,
+    };
+    transform_mesh_data(
+        &mut mesh_data,
+        &Transform::from_scale_rotation_translation(scale, rotation, translation),
+    );
+    println!(
+        "Successfully loaded {} triangles from file {}!",
+        mesh_data.vertices.len(),
+        filepath
+    );
+    mesh_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load_mesh_from_stl_file;
+    use crate::vec3::Vec3;
+
+    const TRIANGLES: [[[f32; 3]; 3]; 2] = [
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+    ];
+
+    fn check_triangles(filename: &str, content: &[u8]) {
+        let filepath = std::env::temp_dir().join(filename);
+        std::fs::write(&filepath, content).unwrap();
+        let mesh_data = load_mesh_from_stl_file(
+            filepath.to_str().unwrap(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::zero(),
+            2.0,
+        );
+
+        assert_eq!(mesh_data.vertices.len(), 2);
+        assert_eq!(mesh_data.vertices[0][1], Vec3::new(2.0, 0.0, -1.0));
+        assert_eq!(mesh_data.vertices[1][1], Vec3::new(2.0, 2.0, -1.0));
+        assert!((mesh_data.vertex_normals[1][2] - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+        assert_eq!(mesh_data.material_ids, vec![None, None]);
+        assert!(mesh_data.materials.is_empty());
+    }
+
+    #[test]
+    fn test_load_ascii_stl() {
+        let mut content = String::from("solid test\n");
+        for triangle in TRIANGLES {
+            content.push_str("  facet normal 0 0 1\n    outer loop\n");
+            for [x, y, z] in triangle {
+                content.push_str(&format!("      vertex {} {} {}\n", x, y, z));
+            }
+            content.push_str("    endloop\n  endfacet\n");
+        }
+        content.push_str("endsolid test\n");
+        check_triangles("rbrt_test_ascii.stl", content.as_bytes());
+    }
+
+    #[test]
+    fn test_load_binary_stl() {
+        // some exporters start binary files with "solid" as well
+        let mut content = b"solid exported".to_vec();
+        content.resize(80, 0);
+        content.extend((TRIANGLES.len() as u32).to_le_bytes());
+        for triangle in TRIANGLES {
+            content.extend([0.0f32; 3].iter().flat_map(|coord| coord.to_le_bytes()));
+            for corner in triangle {
+                content.extend(corner.iter().flat_map(|coord| coord.to_le_bytes()));
+            }
+            content.extend(0u16.to_le_bytes());
+        }
+        check_triangles("rbrt_test_binary.stl", &content);
+
+        // trailing bytes after the announced triangles are ignored
+        content.extend([0u8; 7]);
+        check_triangles("rbrt_test_binary_trailing.stl", &content);
+    }
+}
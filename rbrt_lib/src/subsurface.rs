@@ -0,0 +1,134 @@
+use std::f32::consts::PI;
+
+use crate::materials::{facing_normal, local_to_world, random_cosine_direction, ScatterRecord};
+use crate::medium::{HomogeneousMedium, Medium};
+use crate::vec3::Vec3;
+use crate::{HitInformation, Ray, RayScattering};
+
+/// single scattering albedo of a medium, so that a random walk through it reflects the
+/// fraction albedo of the light, see Chiang et al., "Practical and Controllable Subsurface
+/// Scattering for Production Path Tracing"
+pub fn single_scattering_albedo(albedo: f32) -> f32 {
+    let albedo = albedo.clamp(0.0, 1.0);
+    let term = 4.09712 + 4.20863 * albedo
+        - (9.59217 + 41.6808 * albedo + 17.7126 * albedo * albedo).sqrt();
+    (1.0 - term * term).clamp(0.0, 1.0)
+}
+
+/// translucent material such as skin, wax or marble: light enters and leaves the surface by
+/// diffuse transmission and random walks through the medium below it
+/// the surface must be closed, e.g. a sphere, a closed primitive or a closed mesh
+pub struct Subsurface {
+    pub medium: HomogeneousMedium,
+}
+
+impl Subsurface {
+    /// albedo is the overall color of the material, mean_free_path the average distance that
+    /// light travels below the surface between scattering events, per color channel
+    pub fn new(albedo: Vec3, mean_free_path: Vec3, anisotropy: f32) -> Subsurface {
+        let extinction = Vec3::new(
+            1.0 / mean_free_path.x,
+            1.0 / mean_free_path.y,
+            1.0 / mean_free_path.z,
+        );
+        let scattering = Vec3::new(
+            single_scattering_albedo(albedo.x) * extinction.x,
+            single_scattering_albedo(albedo.y) * extinction.y,
+            single_scattering_albedo(albedo.z) * extinction.z,
+        );
+        Subsurface {
+            medium: HomogeneousMedium {
+                absorption: extinction - scattering,
+                scattering,
+                anisotropy,
+            },
+        }
+    }
+}
+
+/// cosine of the transmitted direction, zero if it does not cross the surface
+fn transmission_cosine(scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+    let normal = facing_normal(hit_info);
+    (-scattered_dir.normalize().dot(&normal)).max(0.0)
+}
+
+impl RayScattering for Subsurface {
+    /// cosine weighted sampling of the hemisphere on the other side of the surface
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        let normal = facing_normal(hit_info);
+        let direction = local_to_world(&random_cosine_direction(), &(-1.0 * normal)).normalize();
+        Some(ScatterRecord {
+            scattered_ray: Ray {
+                origin: hit_info.hit_point,
+                direction,
+                wavelength_nm: incoming_ray.wavelength_nm,
+            },
+            attentuation: Vec3::new(1.0, 1.0, 1.0),
+            pdf: transmission_cosine(&direction, hit_info) / PI,
+            is_specular: false,
+        })
+    }
+
+    fn eval(&self, _incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        let cosine = transmission_cosine(scattered_dir, hit_info);
+        Vec3::new(1.0, 1.0, 1.0) * (cosine / PI)
+    }
+
+    fn pdf(&self, _incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+        transmission_cosine(scattered_dir, hit_info) / PI
+    }
+
+    fn medium(&self) -> Option<&dyn Medium> {
+        Some(&self.medium)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{single_scattering_albedo, Subsurface};
+    use crate::materials::hit_on;
+    use crate::vec3::Vec3;
+    use crate::{Ray, RayScattering};
+
+    #[test]
+    fn test_single_scattering_albedo() {
+        assert!(single_scattering_albedo(0.0).abs() < 1e-4);
+        assert!((single_scattering_albedo(1.0) - 1.0).abs() < 1e-4);
+        // random walks need a higher single scattering albedo to reach the same color
+        let mut prev = 0.0;
+        for step in 1..10 {
+            let albedo = step as f32 / 10.0;
+            let ss_albedo = single_scattering_albedo(albedo);
+            assert!(ss_albedo > albedo && ss_albedo > prev);
+            prev = ss_albedo;
+        }
+    }
+
+    #[test]
+    fn test_subsurface_transmits_light() {
+        let material = Subsurface::new(Vec3::new(0.9, 0.6, 0.3), Vec3::new(0.5, 0.2, 0.1), 0.0);
+        let medium = material.medium;
+        assert!((medium.absorption.x + medium.scattering.x - 2.0).abs() < 1e-4);
+        assert!((medium.absorption.z + medium.scattering.z - 10.0).abs() < 1e-4);
+        assert!(medium.scattering.x / 2.0 > medium.scattering.z / 10.0);
+
+        // from outside into the surface and from inside out of it
+        for incoming_dir in [Vec3::new(0.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 0.0)] {
+            let incoming_ray = Ray::new(Vec3::zero() - incoming_dir, incoming_dir);
+            let hit_info = hit_on(&material, Vec3::new(0.0, 1.0, 0.0), incoming_dir.y < 0.0);
+            for _i in 0..100 {
+                let rec = material.sample(&incoming_ray, &hit_info).unwrap();
+                let direction = rec.scattered_ray.direction;
+                // the scattered ray continues through the surface
+                assert!(direction.y * incoming_dir.y > 0.0);
+                let pdf = material.pdf(&incoming_dir, &direction, &hit_info);
+                assert!((rec.pdf - pdf).abs() < 1e-5);
+                assert!((material.eval(&incoming_dir, &direction, &hit_info).x - pdf).abs() < 1e-5);
+            }
+            // no light is reflected
+            let reflected = Vec3::new(incoming_dir.x, -incoming_dir.y, incoming_dir.z);
+            assert_eq!(material.pdf(&incoming_dir, &reflected, &hit_info), 0.0);
+        }
+        assert!(material.medium().is_some());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::materials::ScatterRecord;
+use crate::medium::Medium;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use crate::{HitInformation, Ray, RayScattering};
+
+/// uv offset of the finite differences of bump maps
+const BUMP_DELTA: f32 = 5e-4;
+
+pub enum DetailMap {
+    /// tangent space normal map: red along dp/du, green along dp/dv, blue along the normal
+    Normal(Box<dyn Texture>),
+    /// height map displacing the surface along the normal by strength * height
+    Bump {
+        height: Box<dyn Texture>,
+        strength: f32,
+    },
+}
+
+/// adds surface detail to a material by perturbing the shading normal,
+/// everything else is delegated to the wrapped material
+pub struct SurfaceDetail {
+    pub material: Box<dyn RayScattering + Sync>,
+    pub detail: DetailMap,
+}
+
+fn normal_mapped(normal_map: &dyn Texture, hit_info: &HitInformation) -> Vec3 {
+    let normal = hit_info.shading_normal;
+    let local = 2.0 * normal_map.value(hit_info.uv, &hit_info.hit_point) - Vec3::new(1.0, 1.0, 1.0);
+
+    // gram schmidt, the bitangent keeps the handedness of the uv mapping
+    let tangent = (hit_info.tangent - hit_info.tangent.dot(&normal) * normal).normalize();
+    let mut bitangent = normal.cross_product(&tangent);
+    if bitangent.dot(&hit_info.bitangent) < 0.0 {
+        bitangent = -1.0 * bitangent;
+    }
+    let perturbed = local.x * tangent + local.y * bitangent + local.z * normal;
+    if perturbed.length() > 0.0 {
+        perturbed.normalize()
+    } else {
+        normal
+    }
+}
+
+/// normal of the surface displaced along the normal by strength * height,
+/// see Blinn, "Simulation of Wrinkled Surfaces"
+fn bump_mapped(height: &dyn Texture, strength: f32, hit_info: &HitInformation) -> Vec3 {
+    let normal = hit_info.shading_normal;
+    let (u, v) = hit_info.uv;
+    let point = hit_info.hit_point;
+    let height_at = |uv: (f32, f32), point: &Vec3| strength * height.value(uv, point).x;
+
+    let center = height_at(hit_info.uv, &point);
+    let point_du = point + BUMP_DELTA * hit_info.tangent;
+    let point_dv = point + BUMP_DELTA * hit_info.bitangent;
+    let du_height = (height_at((u + BUMP_DELTA, v), &point_du) - center) / BUMP_DELTA;
+    let dv_height = (height_at((u, v + BUMP_DELTA), &point_dv) - center) / BUMP_DELTA;
+
+    let displaced_tangent = hit_info.tangent + du_height * normal;
+    let displaced_bitangent = hit_info.bitangent + dv_height * normal;
+    let perturbed = displaced_tangent.cross_product(&displaced_bitangent);
+    if perturbed.length() == 0.0 {
+        return normal;
+    }
+    // the uv mapping may be left handed w.r.t. the normal
+    let orientation = hit_info
+        .tangent
+        .cross_product(&hit_info.bitangent)
+        .dot(&normal);
+    if orientation < 0.0 {
+        -1.0 * perturbed.normalize()
+    } else {
+        perturbed.normalize()
+    }
+}
+
+impl RayScattering for SurfaceDetail {
+    fn sample(&self, incoming_ray: &Ray, hit_info: &HitInformation) -> Option<ScatterRecord> {
+        self.material.sample(incoming_ray, hit_info)
+    }
+
+    fn eval(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        self.material.eval(incoming_dir, scattered_dir, hit_info)
+    }
+
+    fn pdf(&self, incoming_dir: &Vec3, scattered_dir: &Vec3, hit_info: &HitInformation) -> f32 {
+        self.material.pdf(incoming_dir, scattered_dir, hit_info)
+    }
+
+    fn emitted(&self, incoming_dir: &Vec3, hit_info: &HitInformation) -> Vec3 {
+        self.material.emitted(incoming_dir, hit_info)
+    }
+
+    fn shading_normal(&self, hit_info: &HitInformation) -> Vec3 {
+        match &self.detail {
+            DetailMap::Normal(normal_map) => normal_mapped(normal_map.as_ref(), hit_info),
+            DetailMap::Bump { height, strength } => {
+                bump_mapped(height.as_ref(), *strength, hit_info)
+            }
+        }
+    }
+
+    fn medium(&self) -> Option<&dyn Medium> {
+        self.material.medium()
+    }
+
+    fn is_invisible(&self) -> bool {
+        self.material.is_invisible()
+    }
+
+    fn opacity(&self, hit_info: &HitInformation) -> f32 {
+        self.material.opacity(hit_info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DetailMap, SurfaceDetail};
+    use crate::lambertian::Lambertian;
+    use crate::materials::hit_on;
+    use crate::texture::Texture;
+    use crate::vec3::Vec3;
+    use crate::{HitInformation, RayScattering};
+
+    /// height rising linearly along u
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn value(&self, uv: (f32, f32), _point: &Vec3) -> Vec3 {
+            Vec3::new(uv.0, uv.0, uv.0)
+        }
+    }
+
+    /// the texture coordinates change at half the rate of the floor coordinates
+    fn hit_on_floor(material: &dyn RayScattering) -> HitInformation<'_> {
+        let hit_info = hit_on(material, Vec3::new(0.0, 1.0, 0.0), true);
+        HitInformation {
+            uv: (0.3, 0.6),
+            tangent: 2.0 * hit_info.tangent,
+            bitangent: 2.0 * hit_info.bitangent,
+            ..hit_info
+        }
+    }
+
+    #[test]
+    fn test_normal_map() {
+        let flat = SurfaceDetail {
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+            detail: DetailMap::Normal(Vec3::new(0.5, 0.5, 1.0).into()),
+        };
+        let normal = flat.shading_normal(&hit_on_floor(&flat));
+        assert!((normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+
+        // tilted by 45 degrees towards dp/dv
+        let tilted = SurfaceDetail {
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+            detail: DetailMap::Normal(Vec3::new(0.5, 1.0, 1.0).into()),
+        };
+        let normal = tilted.shading_normal(&hit_on_floor(&tilted));
+        assert!((normal - Vec3::new(0.0, 1.0, -1.0).normalize()).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_bump_map() {
+        let material = SurfaceDetail {
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+            detail: DetailMap::Bump {
+                height: Box::new(Ramp),
+                strength: 2.0,
+            },
+        };
+        // the surface rises by 2.0 per unit u, which is 2.0 along x: slope 1
+        let normal = material.shading_normal(&hit_on_floor(&material));
+        assert!((normal - Vec3::new(-1.0, 1.0, 0.0).normalize()).length() < 1e-3);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::vec3::Vec3;
+
+/// spatially varying material parameter, looked up by the texture coordinates
+/// and the position of a hit
+/// scalar parameters such as roughness use the first channel
+pub trait Texture: Sync + Send {
+    fn value(&self, uv: (f32, f32), point: &Vec3) -> Vec3;
+}
+
+pub struct ConstantTexture {
+    pub color: Vec3,
+}
+
+impl Texture for ConstantTexture {
+    fn value(&self, _uv: (f32, f32), _point: &Vec3) -> Vec3 {
+        self.color
+    }
+}
+
+impl From<Vec3> for Box<dyn Texture> {
+    fn from(color: Vec3) -> Box<dyn Texture> {
+        Box::new(ConstantTexture { color })
+    }
+}
+
+impl From<f32> for Box<dyn Texture> {
+    fn from(value: f32) -> Box<dyn Texture> {
+        Box::new(ConstantTexture {
+            color: Vec3::new(value, value, value),
+        })
+    }
+}
+
+/// bilinearly filtered image that repeats outside of uv = [0,1]x[0,1]
+/// v = 0 is the bottom row of the image
+pub struct ImageTexture {
+    pub width: u32,
+    pub height: u32,
+    /// linear color values, row by row starting at the top of the image
+    pub pixels: Vec<Vec3>,
+}
+
+/// converts an sRGB encoded value in [0,1] to linear intensity
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl ImageTexture {
+    pub fn new(width: u32, height: u32, pixels: Vec<Vec3>) -> ImageTexture {
+        assert_eq!(pixels.len(), (width * height) as usize);
+        ImageTexture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// loads a png or jpeg image
+    /// color textures are sRGB encoded, data textures such as roughness maps are not
+    pub fn load(filepath: &str, is_srgb: bool) -> ImageTexture {
+        let img = match image::open(filepath) {
+            Ok(img) => img.into_rgb32f(),
+            Err(error) => panic!("Failed to load texture {}: {:?}", filepath, error),
+        };
+        let decode = |value: f32| {
+            if is_srgb {
+                srgb_to_linear(value)
+            } else {
+                value
+            }
+        };
+        let pixels = img
+            .pixels()
+            .map(|pixel| Vec3::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2])))
+            .collect();
+        println!("Successfully loaded texture {}!", filepath);
+        ImageTexture::new(img.width(), img.height(), pixels)
+    }
+
+    /// loads the alpha channel of a png image as opacity mask, images without alpha channel
+    /// are used as grayscale masks
+    pub fn load_opacity(filepath: &str) -> ImageTexture {
+        let img = match image::open(filepath) {
+            Ok(img) => img,
+            Err(error) => panic!("Failed to load texture {}: {:?}", filepath, error),
+        };
+        if !img.color().has_alpha() {
+            return ImageTexture::load(filepath, false);
+        }
+        let img = img.into_rgba32f();
+        let pixels = img
+            .pixels()
+            .map(|pixel| Vec3::new(pixel[3], pixel[3], pixel[3]))
+            .collect();
+        println!("Successfully loaded opacity of texture {}!", filepath);
+        ImageTexture::new(img.width(), img.height(), pixels)
+    }
+
+    fn texel(&self, col: i64, row: i64) -> Vec3 {
+        let col = col.rem_euclid(self.width as i64) as usize;
+        let row = row.rem_euclid(self.height as i64) as usize;
+        self.pixels[row * self.width as usize + col]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, uv: (f32, f32), _point: &Vec3) -> Vec3 {
+        // continuous pixel coordinates, texel centers are at .5
+        let x = uv.0 * self.width as f32 - 0.5;
+        let y = (1.0 - uv.1) * self.height as f32 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = (1.0 - fx) * self.texel(x0, y0) + fx * self.texel(x0 + 1, y0);
+        let bottom = (1.0 - fx) * self.texel(x0, y0 + 1) + fx * self.texel(x0 + 1, y0 + 1);
+        (1.0 - fy) * top + fy * bottom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{srgb_to_linear, ImageTexture, Texture, Vec3};
+
+    #[test]
+    fn test_image_texture_lookup() {
+        let red = Vec3::new(1.0, 0.0, 0.0);
+        let green = Vec3::new(0.0, 1.0, 0.0);
+        let blue = Vec3::new(0.0, 0.0, 1.0);
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        // top row: red green, bottom row: blue white
+        let texture = ImageTexture::new(2, 2, vec![red, green, blue, white]);
+        let p = Vec3::zero();
+
+        assert_eq!(texture.value((0.25, 0.75), &p), red);
+        assert_eq!(texture.value((0.75, 0.75), &p), green);
+        assert_eq!(texture.value((0.25, 0.25), &p), blue);
+        assert_eq!(texture.value((0.75, 0.25), &p), white);
+        // repeats
+        assert_eq!(texture.value((1.25, -0.75), &p), blue);
+        // bilinear filtering in between the texel centers
+        let mixed = texture.value((0.5, 0.75), &p);
+        assert!((mixed - Vec3::new(0.5, 0.5, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_load_image_texture() {
+        let filepath = std::env::temp_dir().join("rbrt_test_texture.png");
+        let mut img = image::RgbImage::new(4, 2);
+        img.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        img.put_pixel(3, 1, image::Rgb([0, 0, 128]));
+        img.save(&filepath).unwrap();
+
+        let texture = ImageTexture::load(filepath.to_str().unwrap(), true);
+        assert_eq!(texture.width, 4);
+        assert_eq!(texture.height, 2);
+        assert_eq!(texture.pixels[0], Vec3::new(1.0, 0.0, 0.0));
+        assert!((texture.pixels[7].z - srgb_to_linear(128.0 / 255.0)).abs() < 1e-6);
+
+        let texture = ImageTexture::load(filepath.to_str().unwrap(), false);
+        assert!((texture.pixels[7].z - 128.0 / 255.0).abs() < 1e-6);
+    }
+}
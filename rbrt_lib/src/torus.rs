@@ -0,0 +1,138 @@
+use std::f32::consts::PI;
+
+use crate::materials::orthonormal_basis;
+use crate::polynomial::{solve_quadratic, solve_quartic};
+use crate::vec3::Vec3;
+use crate::{HitInformation, Intersectable, Ray, RayScattering};
+
+/// torus around the y axis through its center, the tube of minor_radius circles the axis at
+/// major_radius, u runs around the axis like on spheres, v around the tube starting outside
+pub struct Torus {
+    pub center: Vec3,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: Box<dyn RayScattering + Sync>,
+}
+
+impl Intersectable for Torus {
+    ///
+    /// (|p|^2 + R^2 - r^2)^2 = 4 R^2 (p_x^2 + p_z^2)
+    /// insert the ray for p and solve the quartic equation for t
+    fn intersect_with_ray(
+        &self,
+        ray: &Ray,
+        min_dist: f32,
+        max_dist: f32,
+    ) -> Option<HitInformation<'_>> {
+        let direction = ray.direction.normalize();
+        let to_origin = ray.origin - self.center;
+
+        // start on the bounding sphere, the quartic loses precision far away from the torus
+        let bounding_radius = (self.major_radius + self.minor_radius) as f64;
+        let o = [to_origin.x as f64, to_origin.y as f64, to_origin.z as f64];
+        let d = [direction.x as f64, direction.y as f64, direction.z as f64];
+        let o_dot_d = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let o_dot_o = o[0] * o[0] + o[1] * o[1] + o[2] * o[2];
+        let sphere_dists = solve_quadratic(1.0, 2.0 * o_dot_d, o_dot_o - bounding_radius.powi(2));
+        if sphere_dists.len() < 2 || sphere_dists[1] < min_dist as f64 {
+            return None;
+        }
+        let shift = sphere_dists[0].max(0.0);
+        // solve in units of the major radius, the coefficients of small tori would fall
+        // below the absolute epsilons of the quartic solver
+        let unit = self.major_radius as f64;
+        let o = [
+            (o[0] + shift * d[0]) / unit,
+            (o[1] + shift * d[1]) / unit,
+            (o[2] + shift * d[2]) / unit,
+        ];
+        let (sq_major, sq_minor) = (1.0, (self.minor_radius as f64 / unit).powi(2));
+
+        let m = o[0] * o[0] + o[1] * o[1] + o[2] * o[2];
+        let n = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let k = m + sq_major - sq_minor;
+        let roots = solve_quartic(
+            1.0,
+            4.0 * n,
+            4.0 * n * n + 2.0 * k - 4.0 * sq_major * (d[0] * d[0] + d[2] * d[2]),
+            4.0 * n * k - 8.0 * sq_major * (o[0] * d[0] + o[2] * d[2]),
+            k * k - 4.0 * sq_major * (o[0] * o[0] + o[2] * o[2]),
+        );
+        let dist = roots
+            .into_iter()
+            .map(|root| (root * unit + shift) as f32)
+            .find(|dist| *dist >= min_dist && *dist <= max_dist)?;
+
+        let local = to_origin + dist * direction;
+        let radius_xz = (local.x * local.x + local.z * local.z).sqrt();
+        let phi = (-local.z).atan2(local.x) + PI;
+        let mut theta = local.y.atan2(radius_xz - self.major_radius);
+        if theta < 0.0 {
+            theta += 2.0 * PI;
+        }
+        let (hit_normal, tangent, bitangent) = if radius_xz < 1e-6 {
+            // only self intersecting tori reach the axis, where u is not defined
+            let hit_normal = Vec3::new(0.0, local.y.signum(), 0.0);
+            let (tangent, bitangent) = orthonormal_basis(&hit_normal);
+            (hit_normal, tangent, bitangent)
+        } else {
+            let outward = Vec3::new(local.x / radius_xz, 0.0, local.z / radius_xz);
+            (
+                (local - self.major_radius * outward).normalize(),
+                2.0 * PI * Vec3::new(local.z, 0.0, -local.x),
+                2.0 * PI
+                    * self.minor_radius
+                    * (Vec3::new(0.0, theta.cos(), 0.0) - theta.sin() * outward),
+            )
+        };
+        Some(HitInformation {
+            hit_point: ray.origin + dist * direction,
+            geometric_normal: hit_normal,
+            shading_normal: hit_normal,
+            front_face: ray.direction.dot(&hit_normal) < 0.0,
+            uv: (phi / (2.0 * PI), theta / (2.0 * PI)),
+            tangent,
+            bitangent,
+            hit_material: &*self.material,
+            vertex_color: None,
+            dist_from_ray_orig: dist,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Torus;
+    use crate::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Intersectable, Ray};
+
+    #[test]
+    fn test_torus_intersection() {
+        let torus = Torus {
+            center: Vec3::new(0.0, 0.0, -10.0),
+            major_radius: 2.0,
+            minor_radius: 0.5,
+            material: Box::new(Lambertian { albedo: 0.5.into() }),
+        };
+        // through the hole, the near side of the tube is hit from the outside
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let hit_info = torus.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 7.5).abs() < 1e-4);
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-4);
+        assert!(hit_info.uv.1.abs() < 1e-4 || (hit_info.uv.1 - 1.0).abs() < 1e-4);
+        // and the inner side of the tube after that
+        let hit_info = torus.intersect_with_ray(&ray, 8.0, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 8.5).abs() < 1e-4);
+        assert!((hit_info.geometric_normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-4);
+
+        // looking down the axis through the hole misses
+        let ray = Ray::new(Vec3::new(0.0, 5.0, -10.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(torus.intersect_with_ray(&ray, 1e-3, f32::MAX).is_none());
+        // the top of the tube
+        let ray = Ray::new(Vec3::new(2.0, 5.0, -10.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_info = torus.intersect_with_ray(&ray, 1e-3, f32::MAX).unwrap();
+        assert!((hit_info.dist_from_ray_orig - 4.5).abs() < 1e-4);
+        assert!((hit_info.uv.1 - 0.25).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+
+use crate::vec3::Vec3;
+
+/// column major 4x4 matrix, as used by gltf
+pub type Matrix = [[f32; 4]; 4];
+
+pub const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// order in which the rotations around the fixed x, y and z axes of the scene are applied,
+/// e.g. XYZ rotates around x first and around z last
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum EulerOrder {
+    Xyz,
+    Xzy,
+    Yxz,
+    Yzx,
+    Zxy,
+    Zyx,
+}
+
+fn mat_mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0.0; 4]; 4];
+    for (col, product_col) in product.iter_mut().enumerate() {
+        for (row, value) in product_col.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    product
+}
+
+fn column(matrix: &Matrix, col: usize) -> Vec3 {
+    Vec3::new(matrix[col][0], matrix[col][1], matrix[col][2])
+}
+
+fn from_columns(columns: [Vec3; 3], translation: Vec3) -> Matrix {
+    let mut matrix = IDENTITY;
+    for (col, vector) in columns.iter().chain([translation].iter()).enumerate() {
+        matrix[col][0] = vector.x;
+        matrix[col][1] = vector.y;
+        matrix[col][2] = vector.z;
+    }
+    matrix
+}
+
+/// matrices whose determinant is smaller than this fraction of the volume spanned by
+/// orthogonal columns of the same lengths are treated as singular
+const RELATIVE_SINGULARITY: f32 = 1e-6;
+
+/// inverse of an affine matrix, None if it is singular
+/// the check is relative to the lengths of the columns, so that small uniform scales stay
+/// invertible
+fn affine_inverse(matrix: &Matrix) -> Option<Matrix> {
+    let (c0, c1, c2) = (column(matrix, 0), column(matrix, 1), column(matrix, 2));
+    let det = c0.dot(&c1.cross_product(&c2));
+    // also rejects zero and NaN columns
+    if det.is_nan() || det.abs() <= RELATIVE_SINGULARITY * c0.length() * c1.length() * c2.length() {
+        return None;
+    }
+    // the rows of the inverse are the cross products of the columns divided by det
+    let rows = [
+        (1.0 / det) * c1.cross_product(&c2),
+        (1.0 / det) * c2.cross_product(&c0),
+        (1.0 / det) * c0.cross_product(&c1),
+    ];
+    let columns = [
+        Vec3::new(rows[0].x, rows[1].x, rows[2].x),
+        Vec3::new(rows[0].y, rows[1].y, rows[2].y),
+        Vec3::new(rows[0].z, rows[1].z, rows[2].z),
+    ];
+    let translation = column(matrix, 3);
+    let inverse_translation = Vec3::new(
+        -rows[0].dot(&translation),
+        -rows[1].dot(&translation),
+        -rows[2].dot(&translation),
+    );
+    Some(from_columns(columns, inverse_translation))
+}
+
+/// affine transformation of scene objects, keeps its inverse for transforming rays
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    pub matrix: Matrix,
+    pub inverse: Matrix,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: IDENTITY,
+            inverse: IDENTITY,
+        }
+    }
+
+    /// column major affine matrix, panics if it is singular or projective
+    pub fn from_matrix(matrix: Matrix) -> Transform {
+        if (0..4).any(|col| matrix[col][3] != if col == 3 { 1.0 } else { 0.0 }) {
+            panic!(
+                "Transform matrix must be affine, the last row must be 0 0 0 1: {:?}",
+                matrix
+            );
+        }
+        Transform::try_from_matrix(matrix)
+            .unwrap_or_else(|| panic!("Transform matrix is not invertible: {:?}", matrix))
+    }
+
+    /// column major affine matrix, None if it is singular
+    pub fn try_from_matrix(matrix: Matrix) -> Option<Transform> {
+        let inverse = affine_inverse(&matrix)?;
+        Some(Transform { matrix, inverse })
+    }
+
+    pub fn translation(offset: Vec3) -> Transform {
+        Transform::from_matrix(from_columns(
+            [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ],
+            offset,
+        ))
+    }
+
+    /// scales along the x, y and z axes, negative factors mirror
+    pub fn scale(factors: Vec3) -> Transform {
+        Transform::from_matrix(from_columns(
+            [
+                Vec3::new(factors.x, 0.0, 0.0),
+                Vec3::new(0.0, factors.y, 0.0),
+                Vec3::new(0.0, 0.0, factors.z),
+            ],
+            Vec3::zero(),
+        ))
+    }
+
+    /// rotation by the unit quaternion x*i + y*j + z*k + w, it is normalized first
+    pub fn quaternion(x: f32, y: f32, z: f32, w: f32) -> Transform {
+        let norm = (x * x + y * y + z * z + w * w).sqrt();
+        let (x, y, z, w) = (x / norm, y / norm, z / norm, w / norm);
+        Transform::from_matrix(from_columns(
+            [
+                Vec3::new(
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y + w * z),
+                    2.0 * (x * z - w * y),
+                ),
+                Vec3::new(
+                    2.0 * (x * y - w * z),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z + w * x),
+                ),
+                Vec3::new(
+                    2.0 * (x * z + w * y),
+                    2.0 * (y * z - w * x),
+                    1.0 - 2.0 * (x * x + y * y),
+                ),
+            ],
+            Vec3::zero(),
+        ))
+    }
+
+    /// counter clockwise rotation around the axis when looking against it
+    pub fn axis_angle(axis: Vec3, angle_rad: f32) -> Transform {
+        let axis = axis.normalize();
+        let (sin_half, cos_half) = (0.5 * angle_rad).sin_cos();
+        Transform::quaternion(
+            sin_half * axis.x,
+            sin_half * axis.y,
+            sin_half * axis.z,
+            cos_half,
+        )
+    }
+
+    /// rotations by angles_rad.x around the x axis, angles_rad.y around y and
+    /// angles_rad.z around z, applied in the given order
+    pub fn euler(angles_rad: Vec3, order: EulerOrder) -> Transform {
+        let rotate_x = Transform::axis_angle(Vec3::new(1.0, 0.0, 0.0), angles_rad.x);
+        let rotate_y = Transform::axis_angle(Vec3::new(0.0, 1.0, 0.0), angles_rad.y);
+        let rotate_z = Transform::axis_angle(Vec3::new(0.0, 0.0, 1.0), angles_rad.z);
+        let (first, second, third) = match order {
+            EulerOrder::Xyz => (rotate_x, rotate_y, rotate_z),
+            EulerOrder::Xzy => (rotate_x, rotate_z, rotate_y),
+            EulerOrder::Yxz => (rotate_y, rotate_x, rotate_z),
+            EulerOrder::Yzx => (rotate_y, rotate_z, rotate_x),
+            EulerOrder::Zxy => (rotate_z, rotate_x, rotate_y),
+            EulerOrder::Zyx => (rotate_z, rotate_y, rotate_x),
+        };
+        first.then(&second).then(&third)
+    }
+
+    /// scale, rotation_rad and translation of blueprints,
+    /// the rotation is the Z,X,Z rotation of Vec3::rotate_point
+    pub fn from_scale_rotation_translation(
+        scale: f32,
+        rotation: Vec3,
+        translation: Vec3,
+    ) -> Transform {
+        let rotation = Transform::from_matrix(from_columns(
+            [
+                Vec3::new(1.0, 0.0, 0.0).rotate_point(rotation),
+                Vec3::new(0.0, 1.0, 0.0).rotate_point(rotation),
+                Vec3::new(0.0, 0.0, 1.0).rotate_point(rotation),
+            ],
+            Vec3::zero(),
+        ));
+        Transform::scale(Vec3::new(scale, scale, scale))
+            .then(&rotation)
+            .then(&Transform::translation(translation))
+    }
+
+    /// applies self first and next afterwards
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: mat_mul(&next.matrix, &self.matrix),
+            inverse: mat_mul(&self.inverse, &next.inverse),
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// determinant of the linear part, negative if the transform mirrors
+    pub fn determinant(&self) -> f32 {
+        let (c0, c1, c2) = (
+            column(&self.matrix, 0),
+            column(&self.matrix, 1),
+            column(&self.matrix, 2),
+        );
+        c0.dot(&c1.cross_product(&c2))
+    }
+
+    /// scale factor of transforms that only rotate, mirror, translate and scale uniformly,
+    /// they map spheres to spheres, None for transforms that distort
+    pub fn uniform_scale(&self) -> Option<f32> {
+        let columns = [
+            column(&self.matrix, 0),
+            column(&self.matrix, 1),
+            column(&self.matrix, 2),
+        ];
+        let scale = columns.iter().map(|column| column.length()).sum::<f32>() / 3.0;
+        let tolerance = 1e-4 * scale;
+        let equal_lengths = columns
+            .iter()
+            .all(|column| (column.length() - scale).abs() <= tolerance);
+        let orthogonal =
+            (0..3).all(|idx| columns[idx].dot(&columns[(idx + 1) % 3]).abs() <= tolerance * scale);
+        (equal_lengths && orthogonal).then_some(scale)
+    }
+
+    pub fn transform_point(&self, point: &Vec3) -> Vec3 {
+        self.transform_vector(point) + column(&self.matrix, 3)
+    }
+
+    /// directions and tangents are not translated
+    pub fn transform_vector(&self, vector: &Vec3) -> Vec3 {
+        vector.x * column(&self.matrix, 0)
+            + vector.y * column(&self.matrix, 1)
+            + vector.z * column(&self.matrix, 2)
+    }
+
+    /// normals are transformed with the inverse transpose to stay perpendicular to the surface
+    pub fn transform_normal(&self, normal: &Vec3) -> Vec3 {
+        Vec3::new(
+            column(&self.inverse, 0).dot(normal),
+            column(&self.inverse, 1).dot(normal),
+            column(&self.inverse, 2).dot(normal),
+        )
+        .normalize()
+    }
+
+    pub fn inverse_transform_point(&self, point: &Vec3) -> Vec3 {
+        self.inverse().transform_point(point)
+    }
+
+    pub fn inverse_transform_vector(&self, vector: &Vec3) -> Vec3 {
+        self.inverse().transform_vector(vector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EulerOrder, Transform};
+    use crate::vec3::Vec3;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_transform_normal_with_nonuniform_scale() {
+        let stretch_x = [
+            [2.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // a plane x + y = 0 becomes x / 2 + y = 0
+        let normal = Transform::from_matrix(stretch_x).transform_normal(&Vec3::new(1.0, 1.0, 0.0));
+        assert!((normal - Vec3::new(0.5, 1.0, 0.0).normalize()).length() < 1e-6);
+    }
+
+    #[test]
+    fn test_rotations_agree() {
+        let x_axis = Vec3::new(1.0, 0.0, 0.0);
+        // quarter turn around z moves x onto y
+        let about_z = Transform::axis_angle(Vec3::new(0.0, 0.0, 2.0), FRAC_PI_2);
+        assert_close(about_z.transform_vector(&x_axis), Vec3::new(0.0, 1.0, 0.0));
+        let (sin_half, cos_half) = (0.5 * FRAC_PI_2).sin_cos();
+        let quaternion = Transform::quaternion(0.0, 0.0, sin_half, cos_half);
+        assert_close(
+            quaternion.transform_vector(&x_axis),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+
+        // x first: x stays, then z turns it onto y; z first: x becomes y, then x turns it onto z
+        let angles = Vec3::new(FRAC_PI_2, 0.0, FRAC_PI_2);
+        let xyz = Transform::euler(angles, EulerOrder::Xyz);
+        assert_close(xyz.transform_vector(&x_axis), Vec3::new(0.0, 1.0, 0.0));
+        let zyx = Transform::euler(angles, EulerOrder::Zyx);
+        assert_close(zyx.transform_vector(&x_axis), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_scale_rotation_translation_matches_rotate_point() {
+        let rotation = Vec3::new(0.3, -1.1, 2.0);
+        let transform =
+            Transform::from_scale_rotation_translation(2.0, rotation, Vec3::new(1.0, 2.0, 3.0));
+        let point = Vec3::new(0.5, -0.2, 0.7);
+        let expected = (2.0 * point).rotate_point(rotation) + Vec3::new(1.0, 2.0, 3.0);
+        assert_close(transform.transform_point(&point), expected);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let transform = Transform::scale(Vec3::new(1.0, -2.0, 0.5))
+            .then(&Transform::euler(
+                Vec3::new(0.4, 0.1, -0.7),
+                EulerOrder::Yzx,
+            ))
+            .then(&Transform::translation(Vec3::new(3.0, 0.0, -1.0)));
+        assert!(transform.determinant() < 0.0);
+        let point = Vec3::new(0.3, 0.6, -0.9);
+        assert_close(
+            transform.inverse_transform_point(&transform.transform_point(&point)),
+            point,
+        );
+        assert_close(
+            transform.inverse_transform_vector(&transform.transform_vector(&point)),
+            point,
+        );
+        // normals stay perpendicular to transformed tangents
+        let tangent = Vec3::new(1.0, 2.0, 0.0);
+        let normal = Vec3::new(-2.0, 1.0, 0.5);
+        assert!(
+            transform
+                .transform_normal(&normal)
+                .dot(&transform.transform_vector(&tangent))
+                .abs()
+                < 1e-5
+        );
+    }
+
+    #[test]
+    fn test_small_scales_are_invertible() {
+        let transform = Transform::scale(Vec3::new(1e-4, 1e-4, 1e-4));
+        let point = Vec3::new(0.3, 0.6, -0.9);
+        assert_close(
+            transform.inverse_transform_point(&transform.transform_point(&point)),
+            point,
+        );
+
+        // the third column lies in the plane of the first two
+        let mut flat = Transform::identity().matrix;
+        flat[2] = [1e-4, 1e-4, 0.0, 0.0];
+        assert!(Transform::try_from_matrix(flat).is_none());
+    }
+
+    #[test]
+    fn test_uniform_scale() {
+        let similarity = Transform::scale(Vec3::new(-2.0, -2.0, -2.0))
+            .then(&Transform::axis_angle(Vec3::new(1.0, 2.0, 3.0), 0.7))
+            .then(&Transform::translation(Vec3::new(3.0, 0.0, -1.0)));
+        assert!((similarity.uniform_scale().unwrap() - 2.0).abs() < 1e-5);
+        let stretch = Transform::scale(Vec3::new(1.0, 2.0, 1.0));
+        assert_eq!(stretch.uniform_scale(), None);
+        let mut shear = Transform::identity().matrix;
+        shear[1] = [0.5, 1.0, 0.0, 0.0];
+        assert_eq!(Transform::from_matrix(shear).uniform_scale(), None);
+    }
+}
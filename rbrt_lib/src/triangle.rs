@@ -1,4 +1,5 @@
 extern crate ord_subset;
+use crate::materials::orthonormal_basis;
 use crate::vec3::Vec3;
 use crate::vec3_avx::{avx_cross_product, avx_dot_product};
 use crate::vec3_sse::{sse_cross_product, sse_dot_product};
@@ -27,12 +28,65 @@ impl BasicTriangle {
     }
 }
 
+/// barycentric coordinates (u, v) of a point in the plane of the triangle spanned by
+/// the first vertex and the two edges, u and v are the weights of the second and third vertex
+pub fn barycentric_coords(point: &Vec3, vertex_a: &Vec3, edges: &[Vec3; 2]) -> (f32, f32) {
+    let w = *point - *vertex_a;
+    let d00 = edges[0].dot(&edges[0]);
+    let d01 = edges[0].dot(&edges[1]);
+    let d11 = edges[1].dot(&edges[1]);
+    let d20 = w.dot(&edges[0]);
+    let d21 = w.dot(&edges[1]);
+    let denom = d00 * d11 - d01 * d01;
+    (
+        (d11 * d20 - d01 * d21) / denom,
+        (d00 * d21 - d01 * d20) / denom,
+    )
+}
+
+/// interpolates per corner attributes with the barycentric coordinates (u, v)
+pub fn interpolate_tex_coords(corner_uvs: &[(f32, f32); 3], bary: (f32, f32)) -> (f32, f32) {
+    let w = 1.0 - bary.0 - bary.1;
+    (
+        w * corner_uvs[0].0 + bary.0 * corner_uvs[1].0 + bary.1 * corner_uvs[2].0,
+        w * corner_uvs[0].1 + bary.0 * corner_uvs[1].1 + bary.1 * corner_uvs[2].1,
+    )
+}
+
+/// derivatives (dp/du, dp/dv) of points on the triangle w.r.t. the texture coordinates
+/// falls back to an arbitrary tangent frame around the normal if the texture coordinates
+/// are degenerate
+pub fn tangent_frame(
+    edges: &[Vec3; 2],
+    corner_uvs: &[(f32, f32); 3],
+    normal: &Vec3,
+) -> (Vec3, Vec3) {
+    let du = [
+        corner_uvs[1].0 - corner_uvs[0].0,
+        corner_uvs[2].0 - corner_uvs[0].0,
+    ];
+    let dv = [
+        corner_uvs[1].1 - corner_uvs[0].1,
+        corner_uvs[2].1 - corner_uvs[0].1,
+    ];
+    let det = du[0] * dv[1] - du[1] * dv[0];
+    if det.abs() < 1e-12 {
+        return orthonormal_basis(&normal.normalize());
+    }
+    let inv_det = 1.0 / det;
+    (
+        inv_det * (dv[1] * edges[0] - dv[0] * edges[1]),
+        inv_det * (du[0] * edges[1] - du[1] * edges[0]),
+    )
+}
+
 pub fn get_triangle_normal(corners: &[Vec3; 3]) -> Vec3 {
     let edge1 = corners[1] - corners[0];
     let edge2 = corners[2] - corners[0];
     edge1.cross_product(&edge2).normalize()
 }
 
+/// scalar fallback of the simd code paths, with the same results
 pub fn triangle_soa_intersect_with_ray(
     ray: &Ray,
     vertices: &[[Vec<f32>; 3]; 3],
@@ -41,54 +95,42 @@ pub fn triangle_soa_intersect_with_ray(
     min_dist: f32,
     max_dist: f32,
 ) -> (Option<f32>, Option<usize>) {
-    let eps = min_dist;
+    let vector =
+        |vectors: &[Vec<f32>; 3], i: usize| Vec3::new(vectors[0][i], vectors[1][i], vectors[2][i]);
 
     let mut min_idx = 0;
-    let mut min_param = 1000000.0;
-
+    let mut min_param = max_dist;
     for (i, is_pad) in is_padding_triangle
         .iter()
         .enumerate()
         .take(vertices[0][0].len())
     {
-        let vertex_a = Vec3::new(vertices[0][0][i], vertices[0][1][i], vertices[0][2][i]);
-
-        let edge_a = Vec3::new(edges[0][0][i], edges[0][1][i], edges[0][1][i]);
-        let edge_b = Vec3::new(edges[1][0][i], edges[1][1][i], edges[1][1][i]);
-
-        let h = ray.direction.cross_product(&edge_b);
-        let a = edge_a.dot(&h);
-
-        if -eps < a && a < eps {
-            continue;
-        }
-        let f = 1.0 / a;
-        let s = ray.origin - vertex_a;
-        let u = f * s.dot(&h);
-        if !(0.0..=1.0).contains(&u) {
+        if *is_pad {
             continue;
         }
-        let q = s.cross_product(&edge_a);
-        let v = f * ray.direction.dot(&q);
-        if v < 0.0 || u + v > 1.0 {
-            continue;
-        }
-        // At this stage we can compute t to find out where the intersection point is on the line.
-        let t = f * edge_b.dot(&q);
-        if t > eps && t < min_param && !is_pad {
-            // ray intersection
+        let triangle = [
+            vector(&vertices[0], i),
+            vector(&vertices[1], i),
+            vector(&vertices[2], i),
+        ];
+        let triangle_edges = [vector(&edges[0], i), vector(&edges[1], i)];
+        if let Some(t) =
+            basic_triangle_intersect_w_ray(ray, &triangle, &triangle_edges, min_dist, min_param)
+        {
             min_param = t;
             min_idx = i;
         }
     }
 
-    if min_param > eps && min_param < max_dist {
+    if min_param < max_dist {
         (Some(min_param), Some(min_idx))
     } else {
         (None, None)
     }
 }
 
+/// möller trumbore test, returns the ray parameter of the hit if it lies between min_dist
+/// and max_dist
 pub fn basic_triangle_intersect_w_ray(
     ray: &Ray,
     vertices: &[Vec3; 3],
@@ -99,7 +141,7 @@ pub fn basic_triangle_intersect_w_ray(
     let eps = min_dist;
     let h = ray.direction.cross_product(&edges[1]);
     let a = edges[0].dot(&h);
-    if -eps < a && a < eps {
+    if a == 0.0 {
         return None;
     }
     let f = 1.0 / a;
@@ -115,18 +157,178 @@ pub fn basic_triangle_intersect_w_ray(
     }
     // At this stage we can compute t to find out where the intersection point is on the line.
     let t = f * edges[1].dot(&q);
-    if t > eps
-    // ray intersection
-    {
-        let hit_point = ray.point_at(t);
-        let dist_from_ray_orig = (ray.origin - hit_point).length();
-        if dist_from_ray_orig < min_dist || dist_from_ray_orig > max_dist {
+    // ray intersection, bounded by the ray parameter like the soa code paths
+    if t > eps && t < max_dist {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// ray of the watertight intersection test, see Woop et al. 2013,
+/// "Watertight Ray/Triangle Intersection"
+/// the axes are permuted so that z is the largest component of the direction and vertices
+/// are sheared so that the ray points along z, the hit test then only checks the signs of
+/// 2d edge functions, which are exactly negated for an edge shared by two triangles
+pub struct ShearedRay {
+    pub origin: Vec3,
+    /// indices of the x, y and z axis of the sheared space
+    pub axes: [usize; 3],
+    /// x and y shear and the scale of z
+    pub shear: [f32; 3],
+}
+
+impl ShearedRay {
+    pub fn new(ray: &Ray) -> ShearedRay {
+        let abs_dir = ray.direction.abs();
+        let kz = if abs_dir.x > abs_dir.y && abs_dir.x > abs_dir.z {
+            0
+        } else if abs_dir.y > abs_dir.z {
+            1
+        } else {
+            2
+        };
+        let mut kx = (kz + 1) % 3;
+        let mut ky = (kx + 1) % 3;
+        // keeps the winding order of the triangles
+        let dir_z = ray.direction.axis(kz);
+        if dir_z < 0.0 {
+            mem::swap(&mut kx, &mut ky);
+        }
+        ShearedRay {
+            origin: ray.origin,
+            axes: [kx, ky, kz],
+            shear: [
+                ray.direction.axis(kx) / dir_z,
+                ray.direction.axis(ky) / dir_z,
+                1.0 / dir_z,
+            ],
+        }
+    }
+
+    fn transform(&self, vertex: &Vec3) -> [f32; 3] {
+        let relative = *vertex - self.origin;
+        let z = relative.axis(self.axes[2]);
+        [
+            relative.axis(self.axes[0]) - self.shear[0] * z,
+            relative.axis(self.axes[1]) - self.shear[1] * z,
+            self.shear[2] * z,
+        ]
+    }
+
+    /// ray parameter of the hit with the triangle, hits on edges and vertices count
+    pub fn intersect(&self, vertices: &[Vec3; 3], min_dist: f32, max_dist: f32) -> Option<f32> {
+        let [a, b, c] = [
+            self.transform(&vertices[0]),
+            self.transform(&vertices[1]),
+            self.transform(&vertices[2]),
+        ];
+        let mut u = c[0] * b[1] - c[1] * b[0];
+        let mut v = a[0] * c[1] - a[1] * c[0];
+        let mut w = b[0] * a[1] - b[1] * a[0];
+        // a zero may only be rounding, the products of two f32 are exact in f64, so the
+        // recomputed signs are exact and still negated for shared edges
+        if u == 0.0 || v == 0.0 || w == 0.0 {
+            let [a, b, c] = [a, b, c].map(|p| p.map(f64::from));
+            u = (c[0] * b[1] - c[1] * b[0]) as f32;
+            v = (a[0] * c[1] - a[1] * c[0]) as f32;
+            w = (b[0] * a[1] - b[1] * a[0]) as f32;
+        }
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+            return None;
+        }
+        // the ray is parallel to the triangle or the triangle is degenerate
+        let det = u + v + w;
+        if det == 0.0 {
             return None;
+        }
+        let t = (u * a[2] + v * b[2] + w * c[2]) / det;
+        if t > min_dist && t < max_dist {
+            Some(t)
         } else {
-            return Some(t);
+            None
         }
     }
-    None
+}
+
+/// lanes of the simd watertight tests with an edge function of exactly 0 take the scalar test,
+/// which recomputes them in double precision
+fn recompute_zero_edge_lanes(
+    sheared_ray: &ShearedRay,
+    vertices: &[[Vec<f32>; 3]; 3],
+    first_idx: usize,
+    zero_edge_lanes: i32,
+    ray_params: &mut [f32],
+    min_dist: f32,
+    max_dist: f32,
+) {
+    for (lane, ray_param) in ray_params.iter_mut().enumerate() {
+        if zero_edge_lanes & (1 << lane) == 0 {
+            continue;
+        }
+        let idx = first_idx + lane;
+        let triangle = [0, 1, 2].map(|corner| {
+            Vec3::new(
+                vertices[corner][0][idx],
+                vertices[corner][1][idx],
+                vertices[corner][2][idx],
+            )
+        });
+        *ray_param = sheared_ray
+            .intersect(&triangle, min_dist, max_dist)
+            .unwrap_or(-1000.0);
+    }
+}
+
+/// watertight alternative to basic_triangle_intersect_w_ray
+pub fn basic_triangle_watertight_intersect_w_ray(
+    ray: &Ray,
+    vertices: &[Vec3; 3],
+    min_dist: f32,
+    max_dist: f32,
+) -> Option<f32> {
+    ShearedRay::new(ray).intersect(vertices, min_dist, max_dist)
+}
+
+/// watertight alternative to triangle_soa_intersect_with_ray
+pub fn triangle_soa_watertight_intersect_with_ray(
+    ray: &Ray,
+    vertices: &[[Vec<f32>; 3]; 3],
+    is_padding_triangle: &[bool],
+    min_dist: f32,
+    max_dist: f32,
+) -> (Option<f32>, Option<usize>) {
+    let sheared_ray = ShearedRay::new(ray);
+    let vertex = |corner: usize, i: usize| {
+        Vec3::new(
+            vertices[corner][0][i],
+            vertices[corner][1][i],
+            vertices[corner][2][i],
+        )
+    };
+
+    let mut min_idx = 0;
+    let mut min_param = max_dist;
+    for (i, is_pad) in is_padding_triangle
+        .iter()
+        .enumerate()
+        .take(vertices[0][0].len())
+    {
+        if *is_pad {
+            continue;
+        }
+        let triangle = [vertex(0, i), vertex(1, i), vertex(2, i)];
+        if let Some(t) = sheared_ray.intersect(&triangle, min_dist, min_param) {
+            min_param = t;
+            min_idx = i;
+        }
+    }
+
+    if min_param < max_dist {
+        (Some(min_param), Some(min_idx))
+    } else {
+        (None, None)
+    }
 }
 
 /// # Safety
@@ -137,14 +339,13 @@ pub unsafe fn triangle_soa_avx_intersect_with_ray(
     edges: &[[Vec<f32>; 3]; 2],
     is_padding_triangle: &[bool],
     min_dist: f32,
-    _max_dist: f32,
+    max_dist: f32,
 ) -> (Option<f32>, Option<usize>) {
     let mut ray_params: Vec<f32> = Vec::with_capacity(vertices[0][0].len());
     let eps_f32 = min_dist;
 
     let eps = _mm256_set1_ps(eps_f32);
-    let eps_frac = _mm256_set1_ps(1.0 / eps_f32);
-    let neg_eps = _mm256_set1_ps(-eps_f32);
+    let max_param = _mm256_set1_ps(max_dist);
     let zero = _mm256_set1_ps(0.0);
     let one = _mm256_set1_ps(1.0);
 
@@ -192,12 +393,8 @@ pub unsafe fn triangle_soa_avx_intersect_with_ray(
         // let a = edges[0].dot(&h);
         let a_sum = avx_dot_product(edge_ax, edge_ay, edge_az, h_x, h_y, h_z);
 
-        // condition 1: -eps < a
-        // &&
-        // condition 2:  a < eps
-        let c1_part1 = _mm256_cmp_ps(neg_eps, a_sum, _CMP_LT_OQ);
-        let c1_part2 = _mm256_cmp_ps(a_sum, eps, _CMP_LT_OQ);
-        let c1 = _mm256_and_ps(c1_part1, c1_part2);
+        // condition: a == 0, the ray is parallel to the triangle
+        let c1 = _mm256_cmp_ps(a_sum, zero, _CMP_EQ_OQ);
 
         // f = 1.0/a
         let f = _mm256_div_ps(one, a_sum);
@@ -235,9 +432,9 @@ pub unsafe fn triangle_soa_avx_intersect_with_ray(
 
         // condition1: t > eps
         // &&
-        // condition2: t < 1/eps
+        // condition2: t < max_dist
         let c4_part1 = _mm256_cmp_ps(t, eps, _CMP_GT_OQ);
-        let c4_part2 = _mm256_cmp_ps(t, eps_frac, _CMP_LT_OQ);
+        let c4_part2 = _mm256_cmp_ps(t, max_param, _CMP_LT_OQ);
         let c4 = _mm256_and_ps(c4_part1, c4_part2);
 
         let c23 = _mm256_or_ps(c2, c3);
@@ -258,7 +455,7 @@ pub unsafe fn triangle_soa_avx_intersect_with_ray(
         ray_params.extend_from_slice(&t_unpacked);
     }
 
-    find_smallest_element_bigger_than_eps(&ray_params, is_padding_triangle, eps_f32)
+    find_smallest_element_bigger_than_eps(&ray_params, is_padding_triangle, eps_f32, max_dist)
 }
 
 /// # Safety
@@ -269,13 +466,12 @@ pub unsafe fn triangle_soa_sse_intersect_with_ray(
     edges: &[[Vec<f32>; 3]; 2],
     is_padding_triangle: &[bool],
     min_dist: f32,
-    _max_dist: f32,
+    max_dist: f32,
 ) -> (Option<f32>, Option<usize>) {
     let mut ray_params: Vec<f32> = Vec::with_capacity(vertices[0][0].len());
     let eps_f32 = min_dist;
     let eps = _mm_set1_ps(eps_f32);
-    let eps_frac = _mm_set1_ps(1.0 / eps_f32);
-    let neg_eps = _mm_set1_ps(-eps_f32);
+    let max_param = _mm_set1_ps(max_dist);
     let zero = _mm_set1_ps(0.0);
     let one = _mm_set1_ps(1.0);
 
@@ -320,12 +516,8 @@ pub unsafe fn triangle_soa_sse_intersect_with_ray(
 
         // let a = edges[0].dot(&h);
         let a_sum = sse_dot_product(edge_ax, edge_ay, edge_az, h_x, h_y, h_z);
-        // condition 1: -eps < a
-        // &&
-        // condition 2:  a < eps
-        let c1_part1 = _mm_cmplt_ps(neg_eps, a_sum);
-        let c1_part2 = _mm_cmplt_ps(a_sum, eps);
-        let c1 = _mm_and_ps(c1_part1, c1_part2);
+        // condition: a == 0, the ray is parallel to the triangle
+        let c1 = _mm_cmpeq_ps(a_sum, zero);
 
         // f = 1.0/a
         let f = _mm_div_ps(one, a_sum);
@@ -363,9 +555,9 @@ pub unsafe fn triangle_soa_sse_intersect_with_ray(
 
         // condition1: t > eps
         // &&
-        // condition2: t < 1/eps
+        // condition2: t < max_dist
         let c4_part1 = _mm_cmpgt_ps(t, eps);
-        let c4_part2 = _mm_cmplt_ps(t, eps_frac);
+        let c4_part2 = _mm_cmplt_ps(t, max_param);
         let c4 = _mm_and_ps(c4_part1, c4_part2);
 
         let c23 = _mm_or_ps(c2, c3);
@@ -386,23 +578,253 @@ pub unsafe fn triangle_soa_sse_intersect_with_ray(
         ray_params.extend_from_slice(&t_unpacked);
     }
 
-    find_smallest_element_bigger_than_eps(&ray_params, is_padding_triangle, eps_f32)
+    find_smallest_element_bigger_than_eps(&ray_params, is_padding_triangle, eps_f32, max_dist)
 }
 
+/// # Safety
+/// requires avx
+/// watertight alternative to triangle_soa_avx_intersect_with_ray
+pub unsafe fn triangle_soa_avx_watertight_intersect_with_ray(
+    ray: &Ray,
+    vertices: &[[Vec<f32>; 3]; 3],
+    is_padding_triangle: &[bool],
+    min_dist: f32,
+    max_dist: f32,
+) -> (Option<f32>, Option<usize>) {
+    let num_triangles = vertices[0][0].len();
+    let mut ray_params: Vec<f32> = Vec::with_capacity(num_triangles);
+    let sheared_ray = ShearedRay::new(ray);
+    let [kx, ky, kz] = sheared_ray.axes;
+
+    let eps = _mm256_set1_ps(min_dist);
+    let max_param = _mm256_set1_ps(max_dist);
+    let zero = _mm256_set1_ps(0.0);
+
+    let ro_x = _mm256_set1_ps(ray.origin.axis(kx));
+    let ro_y = _mm256_set1_ps(ray.origin.axis(ky));
+    let ro_z = _mm256_set1_ps(ray.origin.axis(kz));
+    let shear_x = _mm256_set1_ps(sheared_ray.shear[0]);
+    let shear_y = _mm256_set1_ps(sheared_ray.shear[1]);
+    let shear_z = _mm256_set1_ps(sheared_ray.shear[2]);
+
+    let chunk_size = 8;
+
+    for i in (0..num_triangles - num_triangles % chunk_size).step_by(chunk_size) {
+        // corner relative to the ray origin in sheared space
+        let transform = |corner: usize| {
+            let p_x = _mm256_sub_ps(_mm256_loadu_ps(vertices[corner][kx][i..].as_ptr()), ro_x);
+            let p_y = _mm256_sub_ps(_mm256_loadu_ps(vertices[corner][ky][i..].as_ptr()), ro_y);
+            let p_z = _mm256_sub_ps(_mm256_loadu_ps(vertices[corner][kz][i..].as_ptr()), ro_z);
+            (
+                _mm256_sub_ps(p_x, _mm256_mul_ps(shear_x, p_z)),
+                _mm256_sub_ps(p_y, _mm256_mul_ps(shear_y, p_z)),
+                _mm256_mul_ps(shear_z, p_z),
+            )
+        };
+        let (a_x, a_y, a_z) = transform(0);
+        let (b_x, b_y, b_z) = transform(1);
+        let (c_x, c_y, c_z) = transform(2);
+
+        // edge functions
+        let u = _mm256_sub_ps(_mm256_mul_ps(c_x, b_y), _mm256_mul_ps(c_y, b_x));
+        let v = _mm256_sub_ps(_mm256_mul_ps(a_x, c_y), _mm256_mul_ps(a_y, c_x));
+        let w = _mm256_sub_ps(_mm256_mul_ps(b_x, a_y), _mm256_mul_ps(b_y, a_x));
+
+        // condition: the edge functions have different signs
+        let any_negative = _mm256_or_ps(
+            _mm256_or_ps(
+                _mm256_cmp_ps(u, zero, _CMP_LT_OQ),
+                _mm256_cmp_ps(v, zero, _CMP_LT_OQ),
+            ),
+            _mm256_cmp_ps(w, zero, _CMP_LT_OQ),
+        );
+        let any_positive = _mm256_or_ps(
+            _mm256_or_ps(
+                _mm256_cmp_ps(u, zero, _CMP_GT_OQ),
+                _mm256_cmp_ps(v, zero, _CMP_GT_OQ),
+            ),
+            _mm256_cmp_ps(w, zero, _CMP_GT_OQ),
+        );
+        let c1 = _mm256_and_ps(any_negative, any_positive);
+
+        // condition: det == 0, the ray is parallel to the triangle
+        let det = _mm256_add_ps(_mm256_add_ps(u, v), w);
+        let c2 = _mm256_cmp_ps(det, zero, _CMP_EQ_OQ);
+
+        // let t = (u * a_z + v * b_z + w * c_z) / det;
+        let t = _mm256_div_ps(
+            _mm256_add_ps(
+                _mm256_add_ps(_mm256_mul_ps(u, a_z), _mm256_mul_ps(v, b_z)),
+                _mm256_mul_ps(w, c_z),
+            ),
+            det,
+        );
+
+        // condition1: t > eps
+        // &&
+        // condition2: t < max_dist
+        let c3 = _mm256_and_ps(
+            _mm256_cmp_ps(t, eps, _CMP_GT_OQ),
+            _mm256_cmp_ps(t, max_param, _CMP_LT_OQ),
+        );
+
+        let has_intersect = _mm256_andnot_ps(_mm256_or_ps(c1, c2), c3);
+
+        let minus_a_lot = _mm256_set1_ps(-1000.0);
+
+        let res = _mm256_or_ps(
+            _mm256_and_ps(has_intersect, t),
+            _mm256_andnot_ps(has_intersect, minus_a_lot),
+        );
+
+        let mut t_unpacked: [f32; 8] = mem::transmute(res);
+        let zero_edge_lanes = _mm256_movemask_ps(_mm256_or_ps(
+            _mm256_or_ps(
+                _mm256_cmp_ps(u, zero, _CMP_EQ_OQ),
+                _mm256_cmp_ps(v, zero, _CMP_EQ_OQ),
+            ),
+            _mm256_cmp_ps(w, zero, _CMP_EQ_OQ),
+        ));
+        if zero_edge_lanes != 0 {
+            recompute_zero_edge_lanes(
+                &sheared_ray,
+                vertices,
+                i,
+                zero_edge_lanes,
+                &mut t_unpacked,
+                min_dist,
+                max_dist,
+            );
+        }
+        ray_params.extend_from_slice(&t_unpacked);
+    }
+
+    find_smallest_element_bigger_than_eps(&ray_params, is_padding_triangle, min_dist, max_dist)
+}
+
+/// # Safety
+/// requires sse
+/// watertight alternative to triangle_soa_sse_intersect_with_ray
+pub unsafe fn triangle_soa_sse_watertight_intersect_with_ray(
+    ray: &Ray,
+    vertices: &[[Vec<f32>; 3]; 3],
+    is_padding_triangle: &[bool],
+    min_dist: f32,
+    max_dist: f32,
+) -> (Option<f32>, Option<usize>) {
+    let num_triangles = vertices[0][0].len();
+    let mut ray_params: Vec<f32> = Vec::with_capacity(num_triangles);
+    let sheared_ray = ShearedRay::new(ray);
+    let [kx, ky, kz] = sheared_ray.axes;
+
+    let eps = _mm_set1_ps(min_dist);
+    let max_param = _mm_set1_ps(max_dist);
+    let zero = _mm_set1_ps(0.0);
+
+    let ro_x = _mm_set1_ps(ray.origin.axis(kx));
+    let ro_y = _mm_set1_ps(ray.origin.axis(ky));
+    let ro_z = _mm_set1_ps(ray.origin.axis(kz));
+    let shear_x = _mm_set1_ps(sheared_ray.shear[0]);
+    let shear_y = _mm_set1_ps(sheared_ray.shear[1]);
+    let shear_z = _mm_set1_ps(sheared_ray.shear[2]);
+
+    for i in (0..num_triangles - num_triangles % 4).step_by(4) {
+        // corner relative to the ray origin in sheared space
+        let transform = |corner: usize| {
+            let p_x = _mm_sub_ps(_mm_loadu_ps(vertices[corner][kx][i..].as_ptr()), ro_x);
+            let p_y = _mm_sub_ps(_mm_loadu_ps(vertices[corner][ky][i..].as_ptr()), ro_y);
+            let p_z = _mm_sub_ps(_mm_loadu_ps(vertices[corner][kz][i..].as_ptr()), ro_z);
+            (
+                _mm_sub_ps(p_x, _mm_mul_ps(shear_x, p_z)),
+                _mm_sub_ps(p_y, _mm_mul_ps(shear_y, p_z)),
+                _mm_mul_ps(shear_z, p_z),
+            )
+        };
+        let (a_x, a_y, a_z) = transform(0);
+        let (b_x, b_y, b_z) = transform(1);
+        let (c_x, c_y, c_z) = transform(2);
+
+        // edge functions
+        let u = _mm_sub_ps(_mm_mul_ps(c_x, b_y), _mm_mul_ps(c_y, b_x));
+        let v = _mm_sub_ps(_mm_mul_ps(a_x, c_y), _mm_mul_ps(a_y, c_x));
+        let w = _mm_sub_ps(_mm_mul_ps(b_x, a_y), _mm_mul_ps(b_y, a_x));
+
+        // condition: the edge functions have different signs
+        let any_negative = _mm_or_ps(
+            _mm_or_ps(_mm_cmplt_ps(u, zero), _mm_cmplt_ps(v, zero)),
+            _mm_cmplt_ps(w, zero),
+        );
+        let any_positive = _mm_or_ps(
+            _mm_or_ps(_mm_cmpgt_ps(u, zero), _mm_cmpgt_ps(v, zero)),
+            _mm_cmpgt_ps(w, zero),
+        );
+        let c1 = _mm_and_ps(any_negative, any_positive);
+
+        // condition: det == 0, the ray is parallel to the triangle
+        let det = _mm_add_ps(_mm_add_ps(u, v), w);
+        let c2 = _mm_cmpeq_ps(det, zero);
+
+        // let t = (u * a_z + v * b_z + w * c_z) / det;
+        let t = _mm_div_ps(
+            _mm_add_ps(
+                _mm_add_ps(_mm_mul_ps(u, a_z), _mm_mul_ps(v, b_z)),
+                _mm_mul_ps(w, c_z),
+            ),
+            det,
+        );
+
+        // condition1: t > eps
+        // &&
+        // condition2: t < max_dist
+        let c3 = _mm_and_ps(_mm_cmpgt_ps(t, eps), _mm_cmplt_ps(t, max_param));
+
+        let has_intersect = _mm_andnot_ps(_mm_or_ps(c1, c2), c3);
+
+        let minus_a_lot = _mm_set1_ps(-1000.0);
+
+        let res = _mm_or_ps(
+            _mm_and_ps(has_intersect, t),
+            _mm_andnot_ps(has_intersect, minus_a_lot),
+        );
+
+        let mut t_unpacked: [f32; 4] = mem::transmute(res);
+        let zero_edge_lanes = _mm_movemask_ps(_mm_or_ps(
+            _mm_or_ps(_mm_cmpeq_ps(u, zero), _mm_cmpeq_ps(v, zero)),
+            _mm_cmpeq_ps(w, zero),
+        ));
+        if zero_edge_lanes != 0 {
+            recompute_zero_edge_lanes(
+                &sheared_ray,
+                vertices,
+                i,
+                zero_edge_lanes,
+                &mut t_unpacked,
+                min_dist,
+                max_dist,
+            );
+        }
+        ray_params.extend_from_slice(&t_unpacked);
+    }
+
+    find_smallest_element_bigger_than_eps(&ray_params, is_padding_triangle, min_dist, max_dist)
+}
+
+/// smallest ray parameter between eps and max_dist, ignoring padding triangles
 pub fn find_smallest_element_bigger_than_eps(
     ray_params: &[f32],
     is_padding_triangle: &[bool],
     eps: f32,
+    max_dist: f32,
 ) -> (Option<f32>, Option<usize>) {
     let mut min_idx = 0;
-    let mut min_param = 1000000.0;
+    let mut min_param = max_dist;
     for (idx, ray_param) in ray_params.iter().enumerate() {
         if *ray_param > eps && *ray_param < min_param && !is_padding_triangle[idx] {
             min_param = *ray_param;
             min_idx = idx;
         }
     }
-    if min_param > eps && min_param < 100000.0 {
+    if min_param < max_dist {
         (Some(min_param), Some(min_idx))
     } else {
         (None, None)
@@ -416,7 +838,7 @@ impl Intersectable for BasicTriangle {
         ray: &Ray,
         min_dist: f32,
         max_dist: f32,
-    ) -> Option<HitInformation> {
+    ) -> Option<HitInformation<'_>> {
         let ray_param_op =
             basic_triangle_intersect_w_ray(ray, &self.corners, &self.edges, min_dist, max_dist);
 
@@ -429,8 +851,15 @@ impl Intersectable for BasicTriangle {
                 } else {
                     Some(HitInformation {
                         hit_point,
-                        hit_normal: self.normal,
+                        geometric_normal: self.normal,
+                        shading_normal: self.normal,
+                        front_face: ray.direction.dot(&self.normal) < 0.0,
+                        // without texture coordinates, use the barycentric coordinates
+                        uv: barycentric_coords(&hit_point, &self.corners[0], &self.edges),
+                        tangent: self.edges[0],
+                        bitangent: self.edges[1],
                         hit_material: &*self.material,
+                        vertex_color: None,
                         dist_from_ray_orig,
                     })
                 }
@@ -442,9 +871,21 @@ impl Intersectable for BasicTriangle {
 
 #[cfg(test)]
 mod tests {
-    use super::{BasicTriangle, Vec3};
+    use super::{
+        barycentric_coords, basic_triangle_intersect_w_ray,
+        basic_triangle_watertight_intersect_w_ray, get_triangle_normal, interpolate_tex_coords,
+        triangle_soa_avx_intersect_with_ray, triangle_soa_avx_watertight_intersect_with_ray,
+        triangle_soa_intersect_with_ray, triangle_soa_sse_intersect_with_ray,
+        triangle_soa_sse_watertight_intersect_with_ray, triangle_soa_watertight_intersect_with_ray,
+        BasicTriangle, Vec3,
+    };
     // dont need Material here, use Option?
     use crate::lambertian::Lambertian;
+    use crate::mesh::convert_to_soa_mesh;
+    use crate::Ray;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f32::consts::PI;
     #[test]
     fn test_triangle_normal() {
         let test_tri = Box::new(BasicTriangle::new(
@@ -454,7 +895,7 @@ mod tests {
                 Vec3::new(0.0, 0.0, 0.0),
             ],
             Box::new(Lambertian {
-                albedo: Vec3::new(0.5, 0.2, 0.2),
+                albedo: Vec3::new(0.5, 0.2, 0.2).into(),
             }),
         ));
 
@@ -467,10 +908,474 @@ mod tests {
                 Vec3::new(0.0, 1.0, 0.0),
             ],
             Box::new(Lambertian {
-                albedo: Vec3::new(0.5, 0.2, 0.2),
+                albedo: Vec3::new(0.5, 0.2, 0.2).into(),
             }),
         ));
 
         assert_eq!(test_tri.normal, Vec3::new(-1.0, -1.0, 0.0).normalize());
     }
+
+    #[test]
+    fn test_barycentric_tex_coords() {
+        let corners = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(3.0, 0.0, 0.0),
+            Vec3::new(1.0, 2.0, 0.0),
+        ];
+        let edges = [corners[1] - corners[0], corners[2] - corners[0]];
+        let corner_uvs = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+
+        for (corner, corner_uv) in corners.iter().zip(corner_uvs.iter()) {
+            let bary = barycentric_coords(corner, &corners[0], &edges);
+            assert_eq!(interpolate_tex_coords(&corner_uvs, bary), *corner_uv);
+        }
+
+        let bary = barycentric_coords(&Vec3::new(2.0, 0.5, 0.0), &corners[0], &edges);
+        assert_eq!(bary, (0.5, 0.25));
+        assert_eq!(interpolate_tex_coords(&corner_uvs, bary), (0.5, 0.25));
+    }
+
+    type SoaIntersection =
+        fn(&Ray, &[[Vec<f32>; 3]; 3], &[bool], f32, f32) -> (Option<f32>, Option<usize>);
+
+    /// the watertight intersection of every code path supported by this cpu
+    fn watertight_backends() -> Vec<(&'static str, SoaIntersection)> {
+        let mut backends: Vec<(&'static str, SoaIntersection)> =
+            vec![("scalar", triangle_soa_watertight_intersect_with_ray)];
+        if is_x86_feature_detected!("sse") {
+            backends.push((
+                "sse",
+                |ray, vertices, is_padding, min_dist, max_dist| unsafe {
+                    triangle_soa_sse_watertight_intersect_with_ray(
+                        ray, vertices, is_padding, min_dist, max_dist,
+                    )
+                },
+            ));
+        }
+        if is_x86_feature_detected!("avx") {
+            backends.push((
+                "avx",
+                |ray, vertices, is_padding, min_dist, max_dist| unsafe {
+                    triangle_soa_avx_watertight_intersect_with_ray(
+                        ray, vertices, is_padding, min_dist, max_dist,
+                    )
+                },
+            ));
+        }
+        backends
+    }
+
+    /// cube from -1 to 1, every face split into n x n quads of two triangles, facing outwards
+    fn tessellated_cube(n: usize) -> Vec<[Vec3; 3]> {
+        let mut triangles = vec![];
+        let coord = |i: usize| -1.0 + 2.0 * i as f32 / n as f32;
+        for axis in 0..3 {
+            for side in [-1.0, 1.0] {
+                let point = |i: usize, j: usize| {
+                    let mut p = [0.0; 3];
+                    p[axis] = side;
+                    p[(axis + 1) % 3] = coord(i);
+                    p[(axis + 2) % 3] = coord(j);
+                    Vec3::new(p[0], p[1], p[2])
+                };
+                for i in 0..n {
+                    for j in 0..n {
+                        let (a, b) = (point(i, j), point(i + 1, j));
+                        let (c, d) = (point(i + 1, j + 1), point(i, j + 1));
+                        // counter clockwise seen from outside
+                        if side > 0.0 {
+                            triangles.push([a, b, c]);
+                            triangles.push([a, c, d]);
+                        } else {
+                            triangles.push([a, c, b]);
+                            triangles.push([a, d, c]);
+                        }
+                    }
+                }
+            }
+        }
+        triangles
+    }
+
+    /// unit sphere of latitude longitude quads, with triangle fans at the poles
+    fn uv_sphere(num_stacks: usize, num_slices: usize) -> Vec<[Vec3; 3]> {
+        let point = |stack: usize, slice: usize| {
+            let theta = PI * stack as f32 / num_stacks as f32;
+            let phi = 2.0 * PI * (slice % num_slices) as f32 / num_slices as f32;
+            if stack == 0 || stack == num_stacks {
+                return Vec3::new(0.0, 0.0, theta.cos().signum());
+            }
+            Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            )
+        };
+        let mut triangles = vec![];
+        for stack in 0..num_stacks {
+            for slice in 0..num_slices {
+                let (a, b) = (point(stack, slice), point(stack + 1, slice));
+                let (c, d) = (point(stack + 1, slice + 1), point(stack, slice + 1));
+                if stack != 0 {
+                    triangles.push([a, b, d]);
+                }
+                if stack != num_stacks - 1 {
+                    triangles.push([b, c, d]);
+                }
+            }
+        }
+        triangles
+    }
+
+    /// rays from outside towards a dense grid of points inside the unit ball
+    fn rays_into_unit_ball(origin: Vec3, grid_size: usize) -> Vec<Ray> {
+        let to_center = (Vec3::zero() - origin).normalize();
+        let side = if to_center.x.abs() < 0.9 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        let u = to_center.cross_product(&side).normalize();
+        let v = to_center.cross_product(&u);
+        let mut rays = vec![];
+        for i in 0..grid_size {
+            for j in 0..grid_size {
+                let s = 1.2 * (i as f32 / (grid_size - 1) as f32 - 0.5);
+                let t = 1.2 * (j as f32 / (grid_size - 1) as f32 - 0.5);
+                let target = s * u + t * v;
+                rays.push(Ray::new(origin, target - origin));
+            }
+        }
+        rays
+    }
+
+    /// rays from the origin towards the vertices and points on the edges of the triangles
+    /// whose neighbours all face the origin, grazing triangles are left out as rays can miss
+    /// them legitimately near the silhouette
+    fn rays_through_vertices_and_edges(triangles: &[[Vec3; 3]], origin: Vec3) -> Vec<Ray> {
+        let faces_origin = |triangle: &&[Vec3; 3]| {
+            get_triangle_normal(triangle).dot(&(triangle[0] - origin).normalize()) < -0.1
+        };
+        let mut rays = vec![];
+        for triangle in triangles {
+            let is_inner = triangle.iter().all(|corner| {
+                triangles
+                    .iter()
+                    .filter(|other| other.contains(corner))
+                    .all(|other| faces_origin(&other))
+            });
+            if !is_inner {
+                continue;
+            }
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                for k in 0..8 {
+                    let target = a + (k as f32 / 8.0) * (b - a);
+                    rays.push(Ray::new(origin, target - origin));
+                }
+            }
+        }
+        rays
+    }
+
+    fn assert_no_misses(triangles: &[[Vec3; 3]], rays: &[Ray]) {
+        for ray in rays {
+            assert!(
+                triangles.iter().any(|triangle| {
+                    basic_triangle_watertight_intersect_w_ray(ray, triangle, 0.0, f32::INFINITY)
+                        .is_some()
+                }),
+                "basic: ray {:?} -> {:?} slipped through",
+                ray.origin,
+                ray.direction
+            );
+        }
+
+        let mut pre_vertices = triangles.to_vec();
+        let mut pre_edges = triangles
+            .iter()
+            .map(|t| [t[1] - t[0], t[2] - t[0]])
+            .collect();
+        let mut pre_normals = triangles.iter().map(get_triangle_normal).collect();
+        let (vertices, _edges, _normals, is_padding_triangle) =
+            convert_to_soa_mesh(&mut pre_vertices, &mut pre_edges, &mut pre_normals);
+        for (name, intersect) in watertight_backends() {
+            for ray in rays {
+                let (t, idx) = intersect(ray, &vertices, &is_padding_triangle, 0.0, f32::INFINITY);
+                assert!(
+                    t.is_some() && idx.is_some(),
+                    "{}: ray {:?} -> {:?} slipped through",
+                    name,
+                    ray.origin,
+                    ray.direction
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_edge_functions_are_recomputed() {
+        // the edge from b to c passes the ray so closely that its f32 edge function rounds
+        // to 0 for both triangles, only the exact sign decides which one is hit
+        let e = 2.0f32.powi(-13);
+        let b = Vec3::new(1.0 + e, 1.0 - e, 0.0);
+        let c = Vec3::new(-(1.0 + e), -(1.0 - e + 2.0f32.powi(-24)), 0.0);
+        // the hit triangle comes second, so that hitting both is not hidden by the first one
+        let triangles = [
+            [Vec3::new(1.0, -1.0, 0.0), c, b],
+            [Vec3::new(-1.0, 1.0, 0.0), b, c],
+        ];
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let hits: Vec<Option<f32>> = triangles
+            .iter()
+            .map(|triangle| {
+                basic_triangle_watertight_intersect_w_ray(&ray, triangle, 0.0, f32::INFINITY)
+            })
+            .collect();
+        assert_eq!(hits, [None, Some(1.0)]);
+        let expected = closest_hit(&triangles, |triangle| {
+            basic_triangle_watertight_intersect_w_ray(&ray, triangle, 0.0, f32::INFINITY)
+        });
+
+        let mut pre_vertices = triangles.to_vec();
+        let mut pre_edges = triangles
+            .iter()
+            .map(|t| [t[1] - t[0], t[2] - t[0]])
+            .collect();
+        let mut pre_normals = triangles.iter().map(get_triangle_normal).collect();
+        let (vertices, _edges, _normals, is_padding_triangle) =
+            convert_to_soa_mesh(&mut pre_vertices, &mut pre_edges, &mut pre_normals);
+        for (name, intersect) in watertight_backends() {
+            let result = intersect(&ray, &vertices, &is_padding_triangle, 0.0, f32::INFINITY);
+            assert_eq!(result, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_watertight_closed_cube() {
+        let cube = tessellated_cube(4);
+        let origins = [
+            Vec3::new(3.1, 2.3, 4.7),
+            Vec3::new(-2.9, 0.7, -3.3),
+            Vec3::new(0.3, -4.1, 1.9),
+        ];
+        for origin in origins {
+            assert_no_misses(&cube, &rays_into_unit_ball(origin, 64));
+        }
+
+        // rays along the axes exactly through the vertices and edges of the tessellation
+        let mut rays = vec![];
+        for i in 1..32 {
+            for j in 1..32 {
+                let x = -1.0 + i as f32 / 16.0;
+                let y = -1.0 + j as f32 / 16.0;
+                rays.push(Ray::new(Vec3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0)));
+                rays.push(Ray::new(Vec3::new(5.0, x, y), Vec3::new(-1.0, 0.0, 0.0)));
+                rays.push(Ray::new(Vec3::new(y, -5.0, x), Vec3::new(0.0, 1.0, 0.0)));
+            }
+        }
+        assert_no_misses(&cube, &rays);
+        for ray in &rays {
+            let t = cube
+                .iter()
+                .filter_map(|triangle| {
+                    basic_triangle_watertight_intersect_w_ray(ray, triangle, 0.0, f32::INFINITY)
+                })
+                .fold(f32::INFINITY, f32::min);
+            assert_eq!(t, 4.0);
+        }
+    }
+
+    #[test]
+    fn test_watertight_closed_sphere() {
+        let sphere = uv_sphere(8, 12);
+        let origins = [
+            Vec3::new(2.1, -3.7, 1.3),
+            Vec3::new(-0.4, 0.2, 4.5),
+            Vec3::new(-3.3, 2.9, -2.2),
+        ];
+        for origin in origins {
+            assert_no_misses(&sphere, &rays_into_unit_ball(origin, 64));
+        }
+
+        // rays through the poles, where the fans of triangles meet
+        let mut rays = vec![];
+        for i in 0..16 {
+            let angle = 2.0 * PI * i as f32 / 16.0;
+            let origin = Vec3::new(angle.cos(), angle.sin(), 3.0);
+            rays.push(Ray::new(origin, Vec3::new(0.0, 0.0, 1.0) - origin));
+            rays.push(Ray::new(origin, Vec3::new(0.0, 0.0, -1.0) - origin));
+        }
+        rays.push(Ray::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ));
+        rays.push(Ray::new(
+            Vec3::new(0.0, 0.0, -3.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ));
+        assert_no_misses(&sphere, &rays);
+    }
+
+    #[test]
+    fn test_watertight_vertices_and_edges() {
+        let placements = [
+            (2.08, Vec3::new(-9.41, 25.7, 51.2)),
+            (0.8, Vec3::new(3.3, -7.9, 12.6)),
+            (1.0, Vec3::new(0.37, -1.21, 2.53)),
+        ];
+        let origins = [
+            Vec3::new(3.1, 2.3, 4.7),
+            Vec3::new(-2.9, 0.7, -3.3),
+            Vec3::new(0.3, -4.1, 1.9),
+        ];
+        for mesh in [tessellated_cube(4), uv_sphere(8, 12)] {
+            for (scale, offset) in placements {
+                let placed: Vec<[Vec3; 3]> = mesh
+                    .iter()
+                    .map(|triangle| triangle.map(|corner| scale * corner + offset))
+                    .collect();
+                for origin in origins {
+                    let rays = rays_through_vertices_and_edges(&placed, scale * origin + offset);
+                    assert!(!rays.is_empty());
+                    assert_no_misses(&placed, &rays);
+                }
+            }
+        }
+    }
+
+    type MollerTrumboreIntersection = fn(
+        &Ray,
+        &[[Vec<f32>; 3]; 3],
+        &[[Vec<f32>; 3]; 2],
+        &[bool],
+        f32,
+        f32,
+    ) -> (Option<f32>, Option<usize>);
+
+    /// the möller trumbore intersection of every code path supported by this cpu
+    fn moller_trumbore_backends() -> Vec<(&'static str, MollerTrumboreIntersection)> {
+        let mut backends: Vec<(&'static str, MollerTrumboreIntersection)> =
+            vec![("scalar", triangle_soa_intersect_with_ray)];
+        if is_x86_feature_detected!("sse") {
+            backends.push((
+                "sse",
+                |ray, vertices, edges, is_padding, min_dist, max_dist| unsafe {
+                    triangle_soa_sse_intersect_with_ray(
+                        ray, vertices, edges, is_padding, min_dist, max_dist,
+                    )
+                },
+            ));
+        }
+        if is_x86_feature_detected!("avx") {
+            backends.push((
+                "avx",
+                |ray, vertices, edges, is_padding, min_dist, max_dist| unsafe {
+                    triangle_soa_avx_intersect_with_ray(
+                        ray, vertices, edges, is_padding, min_dist, max_dist,
+                    )
+                },
+            ));
+        }
+        backends
+    }
+
+    /// closest hit of the triangles, the first one on ties like the soa code paths
+    fn closest_hit(
+        triangles: &[[Vec3; 3]],
+        intersect: impl Fn(&[Vec3; 3]) -> Option<f32>,
+    ) -> (Option<f32>, Option<usize>) {
+        let mut closest = (None, None);
+        for (idx, triangle) in triangles.iter().enumerate() {
+            if let Some(t) = intersect(triangle) {
+                if closest.0.is_none_or(|closest_t| t < closest_t) {
+                    closest = (Some(t), Some(idx));
+                }
+            }
+        }
+        closest
+    }
+
+    fn random_vec(rng: &mut StdRng) -> Vec3 {
+        Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        )
+    }
+
+    #[test]
+    fn test_backend_conformance() {
+        let mut rng = StdRng::seed_from_u64(50);
+        let mut num_hits = 0;
+        for _ in 0..20 {
+            let scale = 10.0f32.powf(rng.gen_range(-2.0..2.0));
+            let center = 10.0 * scale * random_vec(&mut rng);
+            let num_triangles = rng.gen_range(1..40);
+            let triangles: Vec<[Vec3; 3]> = (0..num_triangles)
+                .map(|_| {
+                    let triangle_center = center + scale * random_vec(&mut rng);
+                    [0, 1, 2].map(|_| triangle_center + 0.3 * scale * random_vec(&mut rng))
+                })
+                .collect();
+            let mut pre_vertices = triangles.clone();
+            let mut pre_edges = triangles
+                .iter()
+                .map(|t| [t[1] - t[0], t[2] - t[0]])
+                .collect();
+            let mut pre_normals = triangles.iter().map(get_triangle_normal).collect();
+            let (vertices, edges, _normals, is_padding_triangle) =
+                convert_to_soa_mesh(&mut pre_vertices, &mut pre_edges, &mut pre_normals);
+
+            for _ in 0..200 {
+                let origin = center + 3.0 * scale * random_vec(&mut rng);
+                // most rays are aimed at a point on a triangle, the others go anywhere
+                let direction = if rng.gen_bool(0.8) {
+                    let triangle = triangles[rng.gen_range(0..num_triangles)];
+                    let u: f32 = rng.gen_range(0.0..1.0);
+                    let v: f32 = rng.gen_range(0.0..1.0 - u);
+                    triangle[0] + u * (triangle[1] - triangle[0]) + v * (triangle[2] - triangle[0])
+                        - origin
+                } else {
+                    random_vec(&mut rng)
+                };
+                // the length of the direction scales the ray parameter
+                let ray = Ray::new(origin, rng.gen_range(0.1..10.0) * direction);
+                let (min_dist, max_dist) = if rng.gen_bool(0.5) {
+                    (0.0, f32::INFINITY)
+                } else {
+                    (rng.gen_range(0.0..0.5), rng.gen_range(0.5..2.0))
+                };
+
+                let expected = closest_hit(&triangles, |triangle| {
+                    let edges = [triangle[1] - triangle[0], triangle[2] - triangle[0]];
+                    basic_triangle_intersect_w_ray(&ray, triangle, &edges, min_dist, max_dist)
+                });
+                num_hits += expected.0.is_some() as usize;
+                for (name, intersect) in moller_trumbore_backends() {
+                    let result = intersect(
+                        &ray,
+                        &vertices,
+                        &edges,
+                        &is_padding_triangle,
+                        min_dist,
+                        max_dist,
+                    );
+                    assert_eq!(result, expected, "{}: {:?}", name, ray);
+                }
+
+                let expected = closest_hit(&triangles, |triangle| {
+                    basic_triangle_watertight_intersect_w_ray(&ray, triangle, min_dist, max_dist)
+                });
+                for (name, intersect) in watertight_backends() {
+                    let result =
+                        intersect(&ray, &vertices, &is_padding_triangle, min_dist, max_dist);
+                    assert_eq!(result, expected, "watertight {}: {:?}", name, ray);
+                }
+            }
+        }
+        // the misses alone would not test much
+        assert!(num_hits > 1000, "{}", num_hits);
+    }
 }
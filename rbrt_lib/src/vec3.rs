@@ -112,6 +112,19 @@ impl Vec3 {
         (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
     }
     #[inline]
+    pub fn abs(&self) -> Vec3 {
+        Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+    /// coordinate along the axis with index 0 (x), 1 (y) or 2 (z)
+    #[inline]
+    pub fn axis(&self, idx: usize) -> f32 {
+        match idx {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+    #[inline]
     pub fn sum(&self) -> f32 {
         self.x + self.y + self.z
     }
@@ -222,7 +235,7 @@ mod tests {
                 }
             }
         }
-        let a = 0.7071067657322372;
+        let a = std::f32::consts::FRAC_1_SQRT_2;
         let f_delta = 0.000001;
 
         let exp_vec = vec![
@@ -230,98 +243,98 @@ mod tests {
                 // point
                 Vec3::new(1.0, 0.0, 0.0),
                 // rotation
-                Vec3::new(0.0, 0.0, (45.0 as f32).to_radians() as f32),
+                Vec3::new(0.0, 0.0, 45.0_f32.to_radians()),
                 // expected point
                 Vec3::new(a, a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(1.0, 0.0, 0.0),
-                Vec3::new(0.0, 0.0, (-45.0 as f32).to_radians() as f32),
+                Vec3::new(0.0, 0.0, (-45.0_f32).to_radians()),
                 Vec3::new(a, -a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 1.0, 0.0),
-                Vec3::new(0.0, 0.0, (45.0 as f32).to_radians() as f32),
+                Vec3::new(0.0, 0.0, 45.0_f32.to_radians()),
                 Vec3::new(-a, a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 1.0, 0.0),
-                Vec3::new(0.0, 0.0, (-45.0 as f32).to_radians() as f32),
+                Vec3::new(0.0, 0.0, (-45.0_f32).to_radians()),
                 Vec3::new(a, a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new(0.0, 0.0, (45.0 as f32).to_radians() as f32),
+                Vec3::new(0.0, 0.0, 45.0_f32.to_radians()),
                 Vec3::new(0.0, 0.0, 1.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new(0.0, 0.0, (-45.0 as f32).to_radians() as f32),
+                Vec3::new(0.0, 0.0, (-45.0_f32).to_radians()),
                 Vec3::new(0.0, 0.0, 1.0),
             ),
             RotTest::new(
                 Vec3::new(1.0, 0.0, 0.0),
-                Vec3::new(0.0, (45.0 as f32).to_radians() as f32, 0.0),
+                Vec3::new(0.0, 45.0_f32.to_radians(), 0.0),
                 Vec3::new(1.0, 0.0, 0.0),
             ),
             RotTest::new(
                 Vec3::new(1.0, 0.0, 0.0),
-                Vec3::new(0.0, (-45.0 as f32).to_radians() as f32, 0.0),
+                Vec3::new(0.0, (-45.0_f32).to_radians(), 0.0),
                 Vec3::new(1.0, 0.0, 0.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 1.0, 0.0),
-                Vec3::new(0.0, (45.0 as f32).to_radians() as f32, 0.0),
+                Vec3::new(0.0, 45.0_f32.to_radians(), 0.0),
                 Vec3::new(0.0, a, a),
             ),
             RotTest::new(
                 Vec3::new(0.0, 1.0, 0.0),
-                Vec3::new(0.0, (-45.0 as f32).to_radians() as f32, 0.0),
+                Vec3::new(0.0, (-45.0_f32).to_radians(), 0.0),
                 Vec3::new(0.0, a, -a),
             ),
             RotTest::new(
                 Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new(0.0, (45.0 as f32).to_radians() as f32, 0.0),
+                Vec3::new(0.0, 45.0_f32.to_radians(), 0.0),
                 Vec3::new(0.0, -a, a),
             ),
             RotTest::new(
                 Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new(0.0, (-45.0 as f32).to_radians() as f32, 0.0),
+                Vec3::new(0.0, (-45.0_f32).to_radians(), 0.0),
                 Vec3::new(0.0, a, a),
             ),
             RotTest::new(
                 Vec3::new(1.0, 0.0, 0.0),
-                Vec3::new((45.0 as f32).to_radians() as f32, 0.0, 0.0),
+                Vec3::new(45.0_f32.to_radians(), 0.0, 0.0),
                 Vec3::new(a, a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(1.0, 0.0, 0.0),
-                Vec3::new((-45.0 as f32).to_radians() as f32, 0.0, 0.0),
+                Vec3::new((-45.0_f32).to_radians(), 0.0, 0.0),
                 Vec3::new(a, -a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 1.0, 0.0),
-                Vec3::new((45.0 as f32).to_radians() as f32, 0.0, 0.0),
+                Vec3::new(45.0_f32.to_radians(), 0.0, 0.0),
                 Vec3::new(-a, a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 1.0, 0.0),
-                Vec3::new((-45.0 as f32).to_radians() as f32, 0.0, 0.0),
+                Vec3::new((-45.0_f32).to_radians(), 0.0, 0.0),
                 Vec3::new(a, a, 0.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new((45.0 as f32).to_radians() as f32, 0.0, 0.0),
+                Vec3::new(45.0_f32.to_radians(), 0.0, 0.0),
                 Vec3::new(0.0, 0.0, 1.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new((45.0 as f32).to_radians() as f32, 0.0, 0.0),
+                Vec3::new(45.0_f32.to_radians(), 0.0, 0.0),
                 Vec3::new(0.0, 0.0, 1.0),
             ),
             RotTest::new(
                 Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new((-45.0 as f32).to_radians() as f32, 0.0, 0.0),
+                Vec3::new((-45.0_f32).to_radians(), 0.0, 0.0),
                 Vec3::new(0.0, 0.0, 1.0),
             ),
         ];
@@ -0,0 +1,377 @@
+use std::fs;
+use std::path::Path;
+
+use crate::aabbox::BoundingBox;
+use crate::medium::{Medium, MediumSample};
+use crate::vec3::Vec3;
+use crate::Ray;
+
+/// first token of the header line of dense grid files
+const DENSE_GRID_HEADER: &str = "dense_grid";
+
+/// densities at the cell centers of a regular grid, x varies fastest, then y, then z
+pub struct DensityGrid {
+    pub resolution: [usize; 3],
+    pub densities: Vec<f32>,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: [usize; 3], densities: Vec<f32>) -> DensityGrid {
+        assert_eq!(
+            resolution.iter().product::<usize>(),
+            densities.len(),
+            "density grid needs one density per cell"
+        );
+        DensityGrid {
+            resolution,
+            densities,
+        }
+    }
+
+    fn cell_density(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.densities[(z * self.resolution[1] + y) * self.resolution[0] + x]
+    }
+
+    /// trilinearly interpolated density at a point in grid coordinates, which run from 0.0
+    /// to 1.0 across the grid, zero outside of the grid
+    pub fn density(&self, point: &Vec3) -> f32 {
+        let coords = [point.x, point.y, point.z];
+        if coords.iter().any(|coord| !(0.0..=1.0).contains(coord)) {
+            return 0.0;
+        }
+        // lower cell and interpolation weight along each axis
+        let mut cells = [0; 3];
+        let mut weights = [0.0; 3];
+        for axis in 0..3 {
+            let cell_coord = (coords[axis] * self.resolution[axis] as f32 - 0.5)
+                .clamp(0.0, (self.resolution[axis] - 1) as f32);
+            cells[axis] = (cell_coord as usize).min(self.resolution[axis].saturating_sub(2));
+            weights[axis] = cell_coord - cells[axis] as f32;
+        }
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut corner_cells = cells;
+            for axis in 0..3 {
+                if corner & (1 << axis) != 0 {
+                    weight *= weights[axis];
+                    corner_cells[axis] = (cells[axis] + 1).min(self.resolution[axis] - 1);
+                } else {
+                    weight *= 1.0 - weights[axis];
+                }
+            }
+            if weight > 0.0 {
+                density +=
+                    weight * self.cell_density(corner_cells[0], corner_cells[1], corner_cells[2]);
+            }
+        }
+        density
+    }
+
+    pub fn max_density(&self) -> f32 {
+        self.densities
+            .iter()
+            .fold(0.0, |max, density| max.max(*density))
+    }
+}
+
+/// parses a dense grid: an ascii header line "dense_grid <nx> <ny> <nz>",
+/// followed by nx * ny * nz densities as little endian f32
+pub fn parse_dense_grid(content: &[u8]) -> DensityGrid {
+    let header_end = content
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .expect("Dense grid has no header line");
+    let header = String::from_utf8_lossy(&content[..header_end]);
+    let tokens: Vec<&str> = header.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != DENSE_GRID_HEADER {
+        panic!(
+            "Dense grid header must be \"{} <nx> <ny> <nz>\": {}",
+            DENSE_GRID_HEADER, header
+        );
+    }
+    let mut resolution = [0; 3];
+    for (axis, token) in tokens[1..].iter().enumerate() {
+        resolution[axis] = token
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid dense grid resolution {}", token));
+        assert!(
+            resolution[axis] > 0,
+            "Dense grid resolution must be positive"
+        );
+    }
+
+    let data = &content[header_end + 1..];
+    let num_cells = resolution.iter().product::<usize>();
+    assert!(
+        data.len() >= 4 * num_cells,
+        "Dense grid ends unexpectedly, expected {} densities",
+        num_cells
+    );
+    let densities = data
+        .chunks_exact(4)
+        .take(num_cells)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect();
+    DensityGrid::new(resolution, densities)
+}
+
+pub fn load_density_grid(filepath: &str) -> DensityGrid {
+    let extension = Path::new(filepath)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    if let Some("vdb") | Some("nvdb") = extension.as_deref() {
+        panic!(
+            "OpenVDB and NanoVDB files are not supported, please convert {} to a dense grid",
+            filepath
+        );
+    }
+    let content = match fs::read(filepath) {
+        Ok(content) => content,
+        Err(error) => panic!("Failed to open {}: {:?}", filepath, error),
+    };
+    let grid = parse_dense_grid(&content);
+    println!(
+        "Successfully loaded {}x{}x{} density grid from file {}!",
+        grid.resolution[0], grid.resolution[1], grid.resolution[2], filepath
+    );
+    grid
+}
+
+/// heterogeneous medium, e.g. smoke or clouds, whose density is given by a grid that fills
+/// an axis aligned box, tracked against the majorant of its extinction (delta tracking for
+/// scattering, ratio tracking for transmittance)
+/// see Novák et al., "Monte Carlo Methods for Volumetric Light Transport Simulation"
+pub struct GridMedium {
+    pub grid: DensityGrid,
+    pub lower_bound: Vec3,
+    pub upper_bound: Vec3,
+    /// absorption coefficients at density 1.0
+    pub absorption: Vec3,
+    /// scattering coefficients at density 1.0
+    pub scattering: Vec3,
+    pub anisotropy: f32,
+    /// upper bound of the extinction in all color channels
+    majorant: f32,
+}
+
+impl GridMedium {
+    pub fn new(
+        grid: DensityGrid,
+        lower_bound: Vec3,
+        upper_bound: Vec3,
+        absorption: Vec3,
+        scattering: Vec3,
+        anisotropy: f32,
+    ) -> GridMedium {
+        let extinction = absorption + scattering;
+        let majorant = grid.max_density() * extinction.x.max(extinction.y).max(extinction.z);
+        GridMedium {
+            grid,
+            lower_bound,
+            upper_bound,
+            absorption,
+            scattering,
+            anisotropy,
+            majorant,
+        }
+    }
+
+    /// absorption and scattering coefficients at a point in world coordinates
+    fn coefficients(&self, point: &Vec3) -> (Vec3, Vec3) {
+        let extent = self.upper_bound - self.lower_bound;
+        let offset = *point - self.lower_bound;
+        let density = self.grid.density(&Vec3::new(
+            offset.x / extent.x,
+            offset.y / extent.y,
+            offset.z / extent.z,
+        ));
+        (density * self.absorption, density * self.scattering)
+    }
+
+    /// coefficients of the fictitious null particles that fill up the extinction to the majorant
+    fn null_coefficients(&self, absorption: &Vec3, scattering: &Vec3) -> Vec3 {
+        Vec3::new(self.majorant, self.majorant, self.majorant) - *absorption - *scattering
+    }
+
+    /// distance at which the ray leaves the box of the grid, the medium is empty beyond it
+    fn exit_dist(&self, ray: &Ray) -> f32 {
+        BoundingBox::new(self.lower_bound, self.upper_bound)
+            .hit_range(ray)
+            .map_or(0.0, |(_entry, exit)| exit)
+    }
+
+    /// distance to the next tentative collision with the majorant medium
+    fn sample_step(&self) -> f32 {
+        -(1.0 - rand::random::<f32>()).ln() / self.majorant
+    }
+}
+
+impl Medium for GridMedium {
+    /// weighted delta tracking: each tentative collision absorbs, scatters or continues
+    /// with probabilities proportional to the coefficients averaged over the color channels
+    fn sample_distance(&self, ray: &Ray, surface_dist: f32) -> MediumSample {
+        let mut weight = Vec3::new(1.0, 1.0, 1.0);
+        if self.majorant <= 0.0 {
+            return MediumSample::Pass { weight };
+        }
+        // rays that find no surface (surface_dist is infinite) stop at the end of the grid
+        let surface_dist = surface_dist.min(self.exit_dist(ray));
+        let mut dist = 0.0;
+        loop {
+            dist += self.sample_step();
+            if dist >= surface_dist {
+                return MediumSample::Pass { weight };
+            }
+            let (absorption, scattering) = self.coefficients(&ray.point_at(dist));
+            let null = self.null_coefficients(&absorption, &scattering);
+            let absorb_probability = absorption.sum() / (3.0 * self.majorant);
+            let scatter_probability = scattering.sum() / (3.0 * self.majorant);
+
+            let event = rand::random::<f32>();
+            if event < absorb_probability {
+                return MediumSample::Absorb;
+            } else if event < absorb_probability + scatter_probability {
+                weight = weight * (1.0 / (self.majorant * scatter_probability)) * scattering;
+                return MediumSample::Scatter { dist, weight };
+            }
+            let null_probability = 1.0 - absorb_probability - scatter_probability;
+            if null_probability <= 0.0 {
+                return MediumSample::Absorb;
+            }
+            weight = weight * (1.0 / (self.majorant * null_probability)) * null;
+        }
+    }
+
+    /// ratio tracking: the product of the null collision probabilities along the ray
+    fn transmittance(&self, ray: &Ray, min_dist: f32, max_dist: f32) -> Vec3 {
+        let mut transmittance = Vec3::new(1.0, 1.0, 1.0);
+        if self.majorant <= 0.0 {
+            return transmittance;
+        }
+        let max_dist = max_dist.min(self.exit_dist(ray));
+        let mut dist = min_dist;
+        loop {
+            dist += self.sample_step();
+            if dist >= max_dist {
+                return transmittance;
+            }
+            let (absorption, scattering) = self.coefficients(&ray.point_at(dist));
+            let null = self.null_coefficients(&absorption, &scattering);
+            transmittance = transmittance * (1.0 / self.majorant) * null;
+            if transmittance.sum() <= 0.0 {
+                return Vec3::zero();
+            }
+        }
+    }
+
+    fn anisotropy(&self) -> f32 {
+        self.anisotropy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_density_grid, DensityGrid, GridMedium};
+    use crate::medium::{HomogeneousMedium, Medium, MediumSample};
+    use crate::vec3::Vec3;
+    use crate::Ray;
+
+    #[test]
+    fn test_load_dense_grid() {
+        let mut content = b"dense_grid 2 1 2\n".to_vec();
+        for density in [0.0f32, 1.0, 2.0, 3.0] {
+            content.extend(density.to_le_bytes());
+        }
+        let filepath = std::env::temp_dir().join("rbrt_test.grid");
+        std::fs::write(&filepath, content).unwrap();
+        let grid = load_density_grid(filepath.to_str().unwrap());
+
+        assert_eq!(grid.resolution, [2, 1, 2]);
+        assert_eq!(grid.max_density(), 3.0);
+        // cell centers
+        assert_eq!(grid.density(&Vec3::new(0.75, 0.5, 0.25)), 1.0);
+        assert_eq!(grid.density(&Vec3::new(0.25, 0.5, 0.75)), 2.0);
+        // interpolated between all four cells, clamped at the border
+        assert!((grid.density(&Vec3::new(0.5, 0.1, 0.5)) - 1.5).abs() < 1e-6);
+        assert!((grid.density(&Vec3::new(0.0, 0.5, 0.5)) - 1.0).abs() < 1e-6);
+        assert_eq!(grid.density(&Vec3::new(0.5, 1.5, 0.5)), 0.0);
+    }
+
+    #[test]
+    fn test_tracking_matches_homogeneous_medium() {
+        // a constant grid of density 0.5 behaves like a homogeneous medium
+        let absorption = Vec3::new(0.2, 0.6, 0.0);
+        let scattering = Vec3::new(0.4, 0.4, 1.0);
+        let grid_medium = GridMedium::new(
+            DensityGrid::new([2, 2, 2], vec![0.5; 8]),
+            Vec3::new(-1.0, -1.0, -4.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            absorption,
+            scattering,
+            0.0,
+        );
+        let medium = HomogeneousMedium {
+            absorption: 0.5 * absorption,
+            scattering: 0.5 * scattering,
+            anisotropy: 0.0,
+        };
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let surface_dist = 3.0;
+
+        let num_samples = 40000;
+        let mut transmittance = Vec3::zero();
+        let mut passed = Vec3::zero();
+        let mut scattered = Vec3::zero();
+        for _i in 0..num_samples {
+            transmittance += grid_medium.transmittance(&ray, 0.5, surface_dist);
+            match grid_medium.sample_distance(&ray, surface_dist) {
+                MediumSample::Pass { weight } => passed += weight,
+                MediumSample::Scatter { weight, .. } => scattered += weight,
+                MediumSample::Absorb => (),
+            }
+        }
+        let scale = 1.0 / num_samples as f32;
+        let expected = medium.transmittance(&ray, 0.5, surface_dist);
+        assert!((scale * transmittance - expected).length() < 0.02);
+        let expected = medium.transmittance(&ray, 0.0, surface_dist);
+        assert!((scale * passed - expected).length() < 0.02);
+        // single scattering albedo times the probability of interacting before the surface
+        let extinction = medium.absorption + medium.scattering;
+        let expected = Vec3::new(
+            medium.scattering.x / extinction.x * (1.0 - expected.x),
+            medium.scattering.y / extinction.y * (1.0 - expected.y),
+            medium.scattering.z / extinction.z * (1.0 - expected.z),
+        );
+        assert!((scale * scattered - expected).length() < 0.02);
+    }
+
+    #[test]
+    fn test_tracking_stops_at_the_end_of_the_grid() {
+        let grid_medium = GridMedium::new(
+            DensityGrid::new([1, 1, 1], vec![1.0]),
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(0.1, 0.1, 0.1),
+            Vec3::new(0.1, 0.1, 0.1),
+            0.0,
+        );
+        // rays that find no surface behind the medium
+        let inside = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let outside = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        for _i in 0..1000 {
+            grid_medium.sample_distance(&inside, f32::INFINITY);
+            let transmittance = grid_medium.transmittance(&inside, 0.0, f32::INFINITY);
+            assert!((0.0..=1.0).contains(&transmittance.x));
+        }
+        assert!(matches!(
+            grid_medium.sample_distance(&outside, f32::INFINITY),
+            MediumSample::Pass { .. }
+        ));
+        assert_eq!(
+            grid_medium.transmittance(&outside, 0.0, f32::INFINITY),
+            Vec3::new(1.0, 1.0, 1.0)
+        );
+    }
+}
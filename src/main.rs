@@ -1,10 +1,13 @@
 extern crate clap;
 extern crate rbrt_lib;
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 
-use rbrt_lib::blueprints::{create_scene_from_scene_blueprint, load_blueprints_from_yaml_file};
+use rbrt_lib::blueprints::{
+    create_camera_from_blueprint, create_scene_from_scene_blueprint, load_blueprints_from_yaml_file,
+};
 
-use rbrt_lib::cam::Camera;
+use rbrt_lib::gltf_import::{is_gltf_file, load_scene_from_gltf_file};
+use rbrt_lib::RenderSettings;
 
 fn main() {
     let app = Command::new("rbrt")
@@ -37,7 +40,7 @@ fn main() {
             Arg::new("config")
                 .short('c')
                 .long("config")
-                .help("YAML file that specifies the scene layout and camera specification.")
+                .help("YAML file that specifies the scene layout and camera specification, or a glTF/GLB file with a camera.")
                 .default_value("scenes/example_scene.yaml"),
         )
         .arg(
@@ -47,6 +50,18 @@ fn main() {
                 .help("number of rays per pixel")
                 .default_value("5")
                 .value_parser(clap::value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("max_dist")
+                .long("max_dist")
+                .help("far clip distance, surfaces further away are not rendered, infinite by default")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("spectral")
+                .long("spectral")
+                .help("trace one wavelength per ray, required for dispersion in dielectrics")
+                .action(ArgAction::SetTrue),
         );
     let matches = app.get_matches();
 
@@ -63,23 +78,35 @@ fn main() {
     let num_samples = matches
         .get_one::<u32>("samples")
         .expect("Please provide valid number of samples per pixel!");
+    let spectral = matches.get_flag("spectral");
+    let max_dist = matches
+        .get_one::<f32>("max_dist")
+        .copied()
+        .unwrap_or(f32::INFINITY);
     let config_file = matches
         .get_one::<String>("config")
         .expect("Please specify a valid scene layout yaml file!");
 
-    let scene_bp = load_blueprints_from_yaml_file(config_file);
-    let cam = Camera::new(
-        scene_bp.camera_blueprint.camera_position,
-        scene_bp.camera_blueprint.camera_look_at,
-        scene_bp.camera_blueprint.camera_up,
-        *height,
-        *width,
-        scene_bp.camera_blueprint.camera_focal_length_mm,
-    );
+    let (cam, scene) = if is_gltf_file(config_file) {
+        let gltf_scene = load_scene_from_gltf_file(config_file);
+        let cam = gltf_scene
+            .camera
+            .expect("The glTF file needs to contain a perspective camera!")
+            .to_camera(*height, *width);
+        (cam, gltf_scene.scene)
+    } else {
+        let scene_bp = load_blueprints_from_yaml_file(config_file);
+        let cam = create_camera_from_blueprint(&scene_bp.camera_blueprint, *height, *width);
+        (cam, create_scene_from_scene_blueprint(scene_bp))
+    };
 
-    let scene = create_scene_from_scene_blueprint(scene_bp);
+    let settings = RenderSettings {
+        spectral,
+        max_dist,
+        ..RenderSettings::new(*num_samples)
+    };
 
-    let img_buf = rbrt_lib::render_scene(cam, *num_samples, scene);
+    let img_buf = rbrt_lib::render_scene(cam, &settings, scene);
 
     println!("Saving rendered image to {}", target_image_path);
 